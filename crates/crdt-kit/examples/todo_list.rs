@@ -1,4 +1,5 @@
-//! Example: Collaborative offline todo list using OR-Set.
+//! Example: Collaborative offline todo list using OR-Set, reordered and edited with RGA
+//! moves and updates.
 
 use crdt_kit::prelude::*;
 
@@ -44,4 +45,28 @@ fn main() {
         alice.contains(&"Buy groceries")
     );
     println!("Total items: {}", alice.len());
+
+    println!("\n=== Prioritised Todo List (RGA moves and updates) ===\n");
+
+    let mut alice = Rga::new(1);
+    for (i, item) in ["Write report", "Fix bike", "Walk the dog"].into_iter().enumerate() {
+        alice.insert_at(i, item).unwrap();
+    }
+    let mut bob = alice.fork(2);
+
+    // Both drag "Walk the dog" somewhere else while offline.
+    alice.move_item(2, 0).unwrap();
+    bob.move_item(2, 1).unwrap();
+    // Alice also rewords an item in place; it keeps its place in Bob's order.
+    alice.set(2, "Fix bike tyre").unwrap();
+    println!("Alice's order: {:?}", alice.to_vec());
+    println!("Bob's order:   {:?}", bob.to_vec());
+
+    alice.merge(&bob);
+    bob.merge(&alice);
+    println!("\nAfter sync (last move and last edit win, no duplicates):");
+    for (i, item) in alice.iter().enumerate() {
+        println!("  {}. {item}", i + 1);
+    }
+    assert_eq!(alice.to_vec(), bob.to_vec());
 }
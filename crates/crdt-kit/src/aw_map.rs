@@ -1,13 +1,18 @@
 use alloc::collections::{BTreeMap, BTreeSet};
 use alloc::vec::Vec;
+use core::hash::Hash;
 
-use crate::{Crdt, DeltaCrdt, NodeId};
+use crate::codec::{ActorTable, CodecError, Decode, Encode, Reader, Writer};
+use crate::merkle::{in_ranges, stable_hash, KeyRange, Reconcilable, KEY_DOMAIN, TOMBSTONE_DOMAIN};
+use crate::or_set::tombstone;
+use crate::{Crdt, DeltaCrdt, DeltaSince, NodeId, VersionVector};
 
 /// An add-wins map (AW-Map).
 ///
 /// A key-value map where each key is tracked with OR-Set semantics: concurrent
-/// add and remove of the same key resolves in favor of add. Values are updated
-/// using a causal version vector per key.
+/// add and remove of the same key resolves in favor of add. Each write is
+/// tagged and replaces the writes it has observed; concurrent writes to the
+/// same key are resolved deterministically by the highest tag.
 ///
 /// # Example
 ///
@@ -29,10 +34,10 @@ use crate::{Crdt, DeltaCrdt, NodeId};
 pub struct AWMap<K: Ord + Clone, V: Clone + Eq> {
     actor: NodeId,
     counter: u64,
-    /// key -> (value, set of unique tags)
-    entries: BTreeMap<K, (V, BTreeSet<(NodeId, u64)>)>,
-    /// Tombstones: tags that have been removed
-    tombstones: BTreeSet<(NodeId, u64)>,
+    /// key -> unique tag -> value written with that tag
+    entries: BTreeMap<K, BTreeMap<(NodeId, u64), V>>,
+    /// Tombstones: removed tag -> tag of the write or remove that hit it
+    tombstones: BTreeMap<(NodeId, u64), (NodeId, u64)>,
 }
 
 impl<K: Ord + Clone, V: Clone + Eq> AWMap<K, V> {
@@ -42,34 +47,41 @@ impl<K: Ord + Clone, V: Clone + Eq> AWMap<K, V> {
             actor,
             counter: 0,
             entries: BTreeMap::new(),
-            tombstones: BTreeSet::new(),
+            tombstones: BTreeMap::new(),
         }
     }
 
     /// Insert or update a key-value pair.
     ///
-    /// Generates a unique tag for this write. If the key already exists,
-    /// the old tags are kept (they accumulate until a remove clears them).
-    /// The value is updated to the new value.
+    /// Generates a unique tag for this write. Tags of writes this replica
+    /// has already observed for the key are tombstoned, so the new value
+    /// supersedes them everywhere; only concurrent writes remain alongside.
     pub fn insert(&mut self, key: K, value: V) {
         self.counter += 1;
         let tag = (self.actor, self.counter);
-        let entry = self.entries.entry(key).or_insert_with(|| {
-            (value.clone(), BTreeSet::new())
-        });
-        entry.0 = value;
-        entry.1.insert(tag);
+        let tags = self.entries.entry(key).or_default();
+        for &old in tags.keys() {
+            tombstone(&mut self.tombstones, old, tag);
+        }
+        tags.clear();
+        tags.insert(tag, value);
     }
 
     /// Remove a key from the map.
     ///
     /// Only removes the tags that this replica has observed. Concurrent
-    /// inserts on other replicas will survive the merge (add wins).
+    /// inserts on other replicas will survive the merge (add wins). The
+    /// remove gets a tag of its own, so peers can tell whether they have
+    /// seen it.
     ///
     /// Returns `true` if the key was present and removed.
     pub fn remove(&mut self, key: &K) -> bool {
-        if let Some((_, tags)) = self.entries.remove(key) {
-            self.tombstones.extend(tags);
+        if let Some(tags) = self.entries.remove(key) {
+            self.counter += 1;
+            let dot = (self.actor, self.counter);
+            for tag in tags.into_keys() {
+                tombstone(&mut self.tombstones, tag, dot);
+            }
             true
         } else {
             false
@@ -77,9 +89,12 @@ impl<K: Ord + Clone, V: Clone + Eq> AWMap<K, V> {
     }
 
     /// Get the value associated with a key, if present.
+    ///
+    /// If several writes to the key were concurrent, the one with the
+    /// highest tag wins.
     #[must_use]
     pub fn get(&self, key: &K) -> Option<&V> {
-        self.entries.get(key).map(|(v, _)| v)
+        self.entries.get(key).and_then(winner)
     }
 
     /// Check if a key is present in the map.
@@ -102,7 +117,9 @@ impl<K: Ord + Clone, V: Clone + Eq> AWMap<K, V> {
 
     /// Iterate over key-value pairs.
     pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
-        self.entries.iter().map(|(k, (v, _))| (k, v))
+        self.entries
+            .iter()
+            .filter_map(|(k, tags)| winner(tags).map(|v| (k, v)))
     }
 
     /// Get all keys.
@@ -112,7 +129,7 @@ impl<K: Ord + Clone, V: Clone + Eq> AWMap<K, V> {
 
     /// Get all values.
     pub fn values(&self) -> impl Iterator<Item = &V> {
-        self.entries.values().map(|(v, _)| v)
+        self.entries.values().filter_map(winner)
     }
 
     /// Get this replica's node ID.
@@ -121,6 +138,18 @@ impl<K: Ord + Clone, V: Clone + Eq> AWMap<K, V> {
         self.actor
     }
 
+    /// Get the version vector of this map: the highest tag counter
+    /// observed per node, across live tags, tombstones and removes.
+    #[must_use]
+    pub fn version_vector(&self) -> VersionVector {
+        self.entries
+            .values()
+            .flat_map(|tags| tags.keys())
+            .chain(self.tombstones.iter().flat_map(|(tag, dot)| [tag, dot]))
+            .copied()
+            .collect()
+    }
+
     /// Returns the number of tombstones stored.
     #[must_use]
     pub fn tombstone_count(&self) -> usize {
@@ -138,9 +167,9 @@ impl<K: Ord + Clone, V: Clone + Eq> AWMap<K, V> {
         let active_tags: BTreeSet<(NodeId, u64)> = self
             .entries
             .values()
-            .flat_map(|(_, tags)| tags.iter().copied())
+            .flat_map(|tags| tags.keys().copied())
             .collect();
-        self.tombstones.retain(|t| active_tags.contains(t));
+        self.tombstones.retain(|t, _| active_tags.contains(t));
     }
 
     /// Remove **all** tombstones unconditionally.
@@ -163,55 +192,39 @@ impl<K: Ord + Clone, V: Clone + Eq> IntoIterator for AWMap<K, V> {
         let items: Vec<(K, V)> = self
             .entries
             .into_iter()
-            .map(|(k, (v, _))| (k, v))
+            .filter_map(|(k, mut tags)| tags.pop_last().map(|(_, v)| (k, v)))
             .collect();
         items.into_iter()
     }
 }
 
+/// The value of the highest tag among concurrent writes.
+fn winner<V>(tags: &BTreeMap<(NodeId, u64), V>) -> Option<&V> {
+    tags.values().next_back()
+}
+
 impl<K: Ord + Clone, V: Clone + Eq> Crdt for AWMap<K, V> {
     fn merge(&mut self, other: &Self) {
-        // Add entries from other that we don't have tombstoned
-        for (key, (other_value, other_tags)) in &other.entries {
-            let entry = self.entries.entry(key.clone()).or_insert_with(|| {
-                (other_value.clone(), BTreeSet::new())
-            });
-            for &tag in other_tags {
-                if !self.tombstones.contains(&tag) {
-                    entry.1.insert(tag);
-                }
-            }
-            // Use the value from the highest tag for determinism
-            if let Some(&max_tag) = entry.1.iter().next_back() {
-                if other_tags.contains(&max_tag) {
-                    entry.0 = other_value.clone();
+        // Add writes from other that we don't have tombstoned
+        for (key, other_tags) in &other.entries {
+            let tags = self.entries.entry(key.clone()).or_default();
+            for (tag, value) in other_tags {
+                if !self.tombstones.contains_key(tag) {
+                    tags.insert(*tag, value.clone());
                 }
             }
         }
 
         // Apply other's tombstones
-        for &tag in &other.tombstones {
-            for (_, (_, tags)) in self.entries.iter_mut() {
+        for (&tag, &dot) in &other.tombstones {
+            for tags in self.entries.values_mut() {
                 tags.remove(&tag);
             }
+            tombstone(&mut self.tombstones, tag, dot);
         }
-        self.tombstones.extend(&other.tombstones);
 
         // Remove entries with no live tags
-        self.entries.retain(|_, (_, tags)| !tags.is_empty());
-
-        // Resolve value for each remaining key: value comes from the max tag owner
-        // We need to reconcile values when both sides contributed tags
-        // Use the value from the side that has the globally highest tag
-        for (key, (value, tags)) in self.entries.iter_mut() {
-            if let Some(&max_tag) = tags.iter().next_back() {
-                if let Some((other_value, other_tags)) = other.entries.get(key) {
-                    if other_tags.contains(&max_tag) {
-                        *value = other_value.clone();
-                    }
-                }
-            }
-        }
+        self.entries.retain(|_, tags| !tags.is_empty());
 
         self.counter = self.counter.max(other.counter);
     }
@@ -222,7 +235,7 @@ impl<K: Ord + Clone, V: Clone + Eq> Crdt for AWMap<K, V> {
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AWMapDelta<K: Ord + Clone, V: Clone + Eq> {
     additions: Vec<(K, V, (NodeId, u64))>,
-    tombstones: BTreeSet<(NodeId, u64)>,
+    tombstones: Vec<((NodeId, u64), (NodeId, u64))>,
 }
 
 impl<K: Ord + Clone, V: Clone + Eq> DeltaCrdt for AWMap<K, V> {
@@ -230,21 +243,22 @@ impl<K: Ord + Clone, V: Clone + Eq> DeltaCrdt for AWMap<K, V> {
 
     fn delta(&self, other: &Self) -> AWMapDelta<K, V> {
         let mut additions = Vec::new();
-        for (key, (value, self_tags)) in &self.entries {
-            let other_tags = other.entries.get(key).map(|(_, t)| t);
-            for &tag in self_tags {
-                let known = other_tags.is_some_and(|ot| ot.contains(&tag))
-                    || other.tombstones.contains(&tag);
+        for (key, self_tags) in &self.entries {
+            let other_tags = other.entries.get(key);
+            for (&tag, value) in self_tags {
+                let known = other_tags.is_some_and(|ot| ot.contains_key(&tag))
+                    || other.tombstones.contains_key(&tag);
                 if !known {
                     additions.push((key.clone(), value.clone(), tag));
                 }
             }
         }
 
-        let tombstones: BTreeSet<_> = self
+        let tombstones = self
             .tombstones
-            .difference(&other.tombstones)
-            .copied()
+            .iter()
+            .filter(|(tag, _)| !other.tombstones.contains_key(*tag))
+            .map(|(&tag, &dot)| (tag, dot))
             .collect();
 
         AWMapDelta {
@@ -255,28 +269,178 @@ impl<K: Ord + Clone, V: Clone + Eq> DeltaCrdt for AWMap<K, V> {
 
     fn apply_delta(&mut self, delta: &AWMapDelta<K, V>) {
         for (key, value, tag) in &delta.additions {
-            if !self.tombstones.contains(tag) {
-                let entry = self.entries.entry(key.clone()).or_insert_with(|| {
-                    (value.clone(), BTreeSet::new())
-                });
-                entry.1.insert(*tag);
-                // Update value if this is the new max tag
-                if let Some(&max_tag) = entry.1.iter().next_back() {
-                    if *tag == max_tag {
-                        entry.0 = value.clone();
-                    }
-                }
+            if !self.tombstones.contains_key(tag) {
+                self.entries
+                    .entry(key.clone())
+                    .or_default()
+                    .insert(*tag, value.clone());
             }
         }
 
-        for &tag in &delta.tombstones {
-            for (_, (_, tags)) in self.entries.iter_mut() {
+        for &(tag, dot) in &delta.tombstones {
+            for tags in self.entries.values_mut() {
                 tags.remove(&tag);
             }
+            tombstone(&mut self.tombstones, tag, dot);
+        }
+
+        self.entries.retain(|_, tags| !tags.is_empty());
+    }
+}
+
+impl<K: Ord + Clone, V: Clone + Eq> DeltaSince for AWMap<K, V> {
+    type Summary = VersionVector;
+
+    fn summary(&self) -> VersionVector {
+        self.version_vector()
+    }
+
+    /// Tombstones are included when the peer has not seen the write or
+    /// remove that made them.
+    fn delta_since(&self, since: &VersionVector) -> AWMapDelta<K, V> {
+        let mut additions = Vec::new();
+        for (key, tags) in &self.entries {
+            for (&(actor, counter), value) in tags {
+                if !since.contains(actor, counter) {
+                    additions.push((key.clone(), value.clone(), (actor, counter)));
+                }
+            }
+        }
+
+        let tombstones = self
+            .tombstones
+            .iter()
+            .filter(|(_, &(actor, counter))| !since.contains(actor, counter))
+            .map(|(&tag, &dot)| (tag, dot))
+            .collect();
+
+        AWMapDelta {
+            additions,
+            tombstones,
+        }
+    }
+}
+
+/// Each key is one entry hashed with its live tags (a tag identifies its
+/// value, so values need not be hashable); each tombstone is an entry of
+/// its own keyed by its tag and hashed with the tag that removed it.
+impl<K: Ord + Clone + Hash, V: Clone + Eq> Reconcilable for AWMap<K, V> {
+    fn digest_entries(&self) -> Vec<(u64, u64)> {
+        let entries = self.entries.iter().map(|(key, tags)| {
+            let tags: Vec<_> = tags.keys().collect();
+            (
+                stable_hash(KEY_DOMAIN, key),
+                stable_hash(KEY_DOMAIN, &(key, tags)),
+            )
+        });
+        let tombstones = self.tombstones.iter().map(|entry| {
+            (
+                stable_hash(TOMBSTONE_DOMAIN, entry.0),
+                stable_hash(TOMBSTONE_DOMAIN, &entry),
+            )
+        });
+        entries.chain(tombstones).collect()
+    }
+
+    fn delta_for(&self, ranges: &[KeyRange]) -> AWMapDelta<K, V> {
+        let mut additions = Vec::new();
+        for (key, tags) in &self.entries {
+            if in_ranges(ranges, stable_hash(KEY_DOMAIN, key)) {
+                for (&tag, value) in tags {
+                    additions.push((key.clone(), value.clone(), tag));
+                }
+            }
+        }
+        AWMapDelta {
+            additions,
+            tombstones: self
+                .tombstones
+                .iter()
+                .filter(|(tag, _)| in_ranges(ranges, stable_hash(TOMBSTONE_DOMAIN, *tag)))
+                .map(|(&tag, &dot)| (tag, dot))
+                .collect(),
+        }
+    }
+}
+
+impl<K: Ord + Clone + Encode, V: Clone + Eq + Encode> Encode for AWMap<K, V> {
+    fn encode(&self, w: &mut Writer) {
+        let table = ActorTable::new(
+            core::iter::once(self.actor)
+                .chain(self.entries.values().flat_map(|tags| tags.keys()).map(|t| t.0))
+                .chain(self.tombstones.iter().flat_map(|(tag, dot)| [tag.0, dot.0])),
+        );
+        table.encode(w);
+        table.write_actor(w, self.actor);
+        w.write_varint(self.counter);
+        w.write_len(self.entries.len());
+        for (key, tags) in &self.entries {
+            key.encode(w);
+            w.write_len(tags.len());
+            for (&tag, value) in tags {
+                table.write_tag(w, tag);
+                value.encode(w);
+            }
+        }
+        let tombstones = self.tombstones.iter().map(|(&tag, &dot)| (tag, dot));
+        table.write_tombstones(w, tombstones);
+    }
+}
+
+impl<K: Ord + Clone + Decode, V: Clone + Eq + Decode> Decode for AWMap<K, V> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        let table = ActorTable::decode(r)?;
+        let actor = table.read_actor(r)?;
+        let counter = r.read_varint()?;
+        let len = r.read_len()?;
+        let mut entries = BTreeMap::new();
+        for _ in 0..len {
+            let key = K::decode(r)?;
+            let tag_count = r.read_len()?;
+            let tags = (0..tag_count)
+                .map(|_| Ok((table.read_tag(r)?, V::decode(r)?)))
+                .collect::<Result<_, CodecError>>()?;
+            entries.insert(key, tags);
         }
-        self.tombstones.extend(&delta.tombstones);
+        Ok(Self {
+            actor,
+            counter,
+            entries,
+            tombstones: table.read_tombstones(r)?,
+        })
+    }
+}
+
+impl<K: Ord + Clone + Encode, V: Clone + Eq + Encode> Encode for AWMapDelta<K, V> {
+    fn encode(&self, w: &mut Writer) {
+        let table = ActorTable::new(
+            self.additions
+                .iter()
+                .map(|(_, _, tag)| tag.0)
+                .chain(self.tombstones.iter().flat_map(|(tag, dot)| [tag.0, dot.0])),
+        );
+        table.encode(w);
+        w.write_len(self.additions.len());
+        for (key, value, tag) in &self.additions {
+            key.encode(w);
+            value.encode(w);
+            table.write_tag(w, *tag);
+        }
+        table.write_tombstones(w, self.tombstones.iter().copied());
+    }
+}
 
-        self.entries.retain(|_, (_, tags)| !tags.is_empty());
+impl<K: Ord + Clone + Decode, V: Clone + Eq + Decode> Decode for AWMapDelta<K, V> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        let table = ActorTable::decode(r)?;
+        let len = r.read_len()?;
+        let additions = (0..len)
+            .map(|_| Ok((K::decode(r)?, V::decode(r)?, table.read_tag(r)?)))
+            .collect::<Result<_, CodecError>>()?;
+        Ok(Self {
+            additions,
+            tombstones: table.read_tombstones(r)?,
+        })
     }
 }
 
@@ -456,4 +620,123 @@ mod tests {
         let keys: Vec<_> = m.keys().collect();
         assert_eq!(keys, vec![&"a", &"c"]);
     }
+
+    #[test]
+    fn delta_since_equivalent_to_merge() {
+        let mut m1 = AWMap::new(1);
+        m1.insert("a", 1);
+        m1.insert("b", 2);
+
+        let mut m2 = AWMap::new(2);
+        m2.merge(&m1);
+        m2.insert("c", 3);
+
+        m1.remove(&"a");
+        m1.insert("d", 4);
+
+        let mut via_merge = m2.clone();
+        via_merge.merge(&m1);
+
+        let d = m1.delta_since(&m2.version_vector());
+        assert_eq!(d.additions, vec![("d", 4, (1, 4))]);
+        assert_eq!(d.tombstones, vec![((1, 1), (1, 3))]);
+
+        let mut via_delta = m2.clone();
+        via_delta.apply_delta(&d);
+        let merge_entries: Vec<_> = via_merge.iter().collect();
+        let delta_entries: Vec<_> = via_delta.iter().collect();
+        assert_eq!(merge_entries, delta_entries);
+    }
+
+    #[test]
+    fn delta_since_skips_seen_tombstones() {
+        let mut m1 = AWMap::new(1);
+        m1.insert("a", 1);
+        m1.insert("a", 2);
+        m1.remove(&"a");
+
+        let mut m2 = AWMap::new(2);
+        m2.merge(&m1);
+        let d = m1.delta_since(&m2.version_vector());
+        assert!(d.additions.is_empty());
+        assert!(d.tombstones.is_empty());
+
+        m1.insert("b", 3);
+        m1.insert("b", 4);
+        let d = m1.delta_since(&m2.version_vector());
+        assert_eq!(d.tombstones, vec![((1, 4), (1, 5))]);
+    }
+
+    #[test]
+    fn local_update_supersedes_observed_write() {
+        let mut m1 = AWMap::new(1);
+        let mut m2 = AWMap::new(2);
+        m2.insert("k", "b");
+        m1.merge(&m2);
+
+        // m1's tag sorts below m2's, but m1 has seen m2's write.
+        m1.insert("k", "c");
+        m2.merge(&m1);
+        assert_eq!(m1.get(&"k"), Some(&"c"));
+        assert_eq!(m2.get(&"k"), Some(&"c"));
+    }
+
+    #[test]
+    fn peers_agree_on_write_that_superseded_an_observed_one() {
+        let mut m2 = AWMap::new(2);
+        m2.insert("k", "x");
+        let mut m1 = AWMap::new(1);
+        m1.merge(&m2);
+        m1.insert("k", "y");
+
+        // m3 hears of the two writes separately, oldest first. With one
+        // value per key, m3 kept "x" because m2's tag sorts highest.
+        let mut m3 = AWMap::new(3);
+        m3.apply_delta(&m2.delta(&m3));
+        m3.apply_delta(&m1.delta(&m3));
+        assert_eq!(m1.get(&"k"), Some(&"y"));
+        assert_eq!(m3.get(&"k"), Some(&"y"));
+    }
+
+    #[test]
+    fn removing_winner_reveals_concurrent_write() {
+        let mut m1 = AWMap::new(1);
+        m1.insert("k", "a");
+        let mut m2 = AWMap::new(2);
+        m2.insert("k", "b");
+
+        // m3 only ever saw m2's write, and removes it.
+        let mut m3 = AWMap::new(3);
+        m3.merge(&m2);
+        m3.remove(&"k");
+
+        m1.merge(&m2);
+        assert_eq!(m1.get(&"k"), Some(&"b"));
+        m1.merge(&m3);
+        m3.apply_delta(&m1.delta(&m3));
+        assert_eq!(m1.get(&"k"), Some(&"a"));
+        assert_eq!(m3.get(&"k"), Some(&"a"));
+    }
+
+    #[test]
+    fn reconcile_matches_merge() {
+        use crate::merkle::reconcile_in_memory;
+
+        let mut m1 = AWMap::new(1);
+        for k in 0..1000 {
+            m1.insert(k, k);
+        }
+        let mut m2 = AWMap::new(2);
+        m2.merge(&m1);
+        m2.insert(3, 30);
+        m2.remove(&4);
+        m1.insert(3, 300);
+
+        let mut via_merge = m1.clone();
+        via_merge.merge(&m2);
+        reconcile_in_memory(&mut m1, &mut m2);
+        assert_eq!(m1.entries, via_merge.entries);
+        assert_eq!(m2.entries, via_merge.entries);
+        assert_eq!(m1.get(&4), None);
+    }
 }
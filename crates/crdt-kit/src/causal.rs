@@ -0,0 +1,415 @@
+//! Causal delivery of deltas over unreliable links.
+//!
+//! Delta-state CRDTs converge whatever order deltas are merged in only if
+//! each delta is *causally complete*. Small per-operation deltas — an
+//! [`RgaDelta`](crate::RgaDelta) anchored on an element the receiver has
+//! not seen yet, or an [`ORSetDelta`](crate::ORSetDelta) removing a tag that
+//! has not arrived — must wait for the deltas they depend on.
+//!
+//! The sender [`stamp`](CausalBuffer::stamp)s every delta it produces with
+//! its origin, a per-origin sequence number and the version vector of
+//! deltas it had delivered at that point. The receiver hands stamped
+//! deltas to [`receive`](CausalBuffer::receive), which applies each one as
+//! soon as its dependencies are met, holds the rest, and reports the
+//! missing sequence numbers through [`gaps`](CausalBuffer::gaps) so they
+//! can be requested again.
+//!
+//! # Example
+//!
+//! ```
+//! use crdt_kit::prelude::*;
+//! use crdt_kit::causal::CausalBuffer;
+//!
+//! let mut sender = TextCrdt::new(1);
+//! let mut out = CausalBuffer::<TextCrdt>::new();
+//!
+//! let mut stamped = Vec::new();
+//! for (i, ch) in "hi".chars().enumerate() {
+//!     let before = sender.version_vector();
+//!     sender.insert(i, ch).unwrap();
+//!     stamped.push(out.stamp(1, sender.delta_since(&before)));
+//! }
+//!
+//! // The second delta overtakes the first.
+//! let mut receiver = TextCrdt::new(2);
+//! let mut inbox = CausalBuffer::new();
+//! assert_eq!(inbox.receive(&mut receiver, stamped[1].clone()), 0);
+//! assert_eq!(inbox.gaps()[0].start, 1);
+//!
+//! assert_eq!(inbox.receive(&mut receiver, stamped[0].clone()), 2);
+//! assert_eq!(receiver.to_string(), "hi");
+//! ```
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{DeltaCrdt, NodeId, VersionVector};
+
+/// A delta stamped with its causal position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CausalDelta<D> {
+    /// Node that produced the delta.
+    pub origin: NodeId,
+    /// Position of this delta among `origin`'s deltas, starting at 1.
+    pub seq: u64,
+    /// Deltas the origin had delivered when it produced this one.
+    pub deps: VersionVector,
+    /// The delta itself.
+    pub delta: D,
+}
+
+/// A run of sequence numbers from one origin that has not arrived.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Gap {
+    /// Node whose deltas are missing.
+    pub origin: NodeId,
+    /// First missing sequence number.
+    pub start: u64,
+    /// Last missing sequence number (inclusive).
+    pub end: u64,
+}
+
+/// Holds deltas until their causal dependencies are satisfied.
+///
+/// Delivery follows the classic causal broadcast rule: the delta numbered
+/// `seq` from `origin` is applied once every earlier delta from `origin`
+/// and everything in its `deps` has been applied. Duplicates are ignored.
+pub struct CausalBuffer<C: DeltaCrdt> {
+    delivered: VersionVector,
+    pending: BTreeMap<(NodeId, u64), CausalDelta<C::Delta>>,
+    /// Pending deltas keyed by a dependency on another origin they wait for.
+    waiting: BTreeMap<(NodeId, u64), Vec<(NodeId, u64)>>,
+}
+
+impl<C: DeltaCrdt> CausalBuffer<C> {
+    /// Create an empty buffer that has delivered nothing.
+    pub fn new() -> Self {
+        Self {
+            delivered: VersionVector::new(),
+            pending: BTreeMap::new(),
+            waiting: BTreeMap::new(),
+        }
+    }
+
+    /// Stamp a delta produced locally by `origin` for sending.
+    ///
+    /// The delta is assumed to be already applied to the local replica, so
+    /// it counts as delivered here.
+    pub fn stamp(&mut self, origin: NodeId, delta: C::Delta) -> CausalDelta<C::Delta> {
+        let seq = self.delivered.get(origin) + 1;
+        let deps = self.delivered.clone();
+        self.delivered.observe(origin, seq);
+        CausalDelta {
+            origin,
+            seq,
+            deps,
+            delta,
+        }
+    }
+
+    /// Accept a stamped delta from a peer.
+    ///
+    /// Applies it to `replica` if its dependencies are met, followed by any
+    /// buffered deltas it unblocks. Returns the number of deltas applied.
+    ///
+    /// Only the deltas that waited on one just applied are checked again,
+    /// so each buffered delta costs O(deps) work per dependency it waits on.
+    pub fn receive(&mut self, replica: &mut C, msg: CausalDelta<C::Delta>) -> usize {
+        let key = (msg.origin, msg.seq);
+        if self.delivered.contains(msg.origin, msg.seq) || self.pending.contains_key(&key) {
+            return 0;
+        }
+        self.pending.insert(key, msg);
+
+        let mut ready = Vec::new();
+        self.schedule(key, &mut ready);
+        let mut applied = 0;
+        while let Some((origin, seq)) = ready.pop() {
+            let msg = self.pending.remove(&(origin, seq)).expect("ready deltas are pending");
+            replica.apply_delta(&msg.delta);
+            self.delivered.observe(origin, seq);
+            applied += 1;
+
+            if self.pending.contains_key(&(origin, seq + 1)) {
+                self.schedule((origin, seq + 1), &mut ready);
+            }
+            for next in self.waiting.remove(&(origin, seq)).unwrap_or_default() {
+                self.schedule(next, &mut ready);
+            }
+        }
+        applied
+    }
+
+    /// Version vector of every delta applied so far.
+    #[must_use]
+    pub fn delivered(&self) -> &VersionVector {
+        &self.delivered
+    }
+
+    /// Number of deltas waiting for their dependencies.
+    #[must_use]
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Check whether nothing is waiting.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Sequence numbers that must arrive before buffered deltas can apply.
+    ///
+    /// Covers both holes in an origin's own sequence and dependencies on
+    /// other origins that have not been seen at all. Sorted by origin.
+    #[must_use]
+    pub fn gaps(&self) -> Vec<Gap> {
+        // Highest sequence number needed per origin.
+        let mut needed = VersionVector::new();
+        for msg in self.pending.values() {
+            needed.observe(msg.origin, msg.seq - 1);
+            for (origin, seq) in msg.deps.iter() {
+                needed.observe(origin, seq);
+            }
+        }
+
+        let mut gaps = Vec::new();
+        for (origin, upto) in needed.iter() {
+            let mut next = self.delivered.get(origin) + 1;
+            let held = self
+                .pending
+                .range((origin, next)..=(origin, upto))
+                .map(|(&(_, seq), _)| seq);
+            for seq in held.chain(core::iter::once(upto + 1)) {
+                if seq > next {
+                    gaps.push(Gap {
+                        origin,
+                        start: next,
+                        end: seq - 1,
+                    });
+                }
+                next = seq + 1;
+            }
+        }
+        gaps
+    }
+
+    /// Queue a pending delta in `ready` if it can be applied, or file it
+    /// under the first dependency on another origin it still waits for.
+    ///
+    /// A delta waiting on its own origin's previous one is picked up when
+    /// that one is applied, so it is not filed.
+    fn schedule(&mut self, (origin, seq): (NodeId, u64), ready: &mut Vec<(NodeId, u64)>) {
+        if seq != self.delivered.get(origin) + 1 {
+            return;
+        }
+        let blocker = self.pending[&(origin, seq)]
+            .deps
+            .iter()
+            .find(|&(dep, count)| dep != origin && self.delivered.get(dep) < count);
+        match blocker {
+            Some(dep) => self.waiting.entry(dep).or_default().push((origin, seq)),
+            None => ready.push((origin, seq)),
+        }
+    }
+}
+
+impl<C: DeltaCrdt> Default for CausalBuffer<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: DeltaCrdt> Clone for CausalBuffer<C>
+where
+    C::Delta: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            delivered: self.delivered.clone(),
+            pending: self.pending.clone(),
+            waiting: self.waiting.clone(),
+        }
+    }
+}
+
+impl<C: DeltaCrdt> fmt::Debug for CausalBuffer<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CausalBuffer")
+            .field("delivered", &self.delivered)
+            .field("pending", &self.pending.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DeltaSince, GCounter, ORSet, Rga};
+
+    /// Perform `op` on `replica` and stamp the resulting delta.
+    fn op<C: DeltaSince<Summary = VersionVector>>(
+        replica: &mut C,
+        buffer: &mut CausalBuffer<C>,
+        origin: NodeId,
+        op: impl FnOnce(&mut C),
+    ) -> CausalDelta<C::Delta> {
+        let before = replica.summary();
+        op(replica);
+        buffer.stamp(origin, replica.delta_since(&before))
+    }
+
+    #[test]
+    fn in_order_applies_immediately() {
+        let mut a = Rga::new(1);
+        let mut out = CausalBuffer::new();
+        let d1 = op(&mut a, &mut out, 1, |r| r.insert_at(0, 'x').unwrap());
+        let d2 = op(&mut a, &mut out, 1, |r| r.insert_at(1, 'y').unwrap());
+
+        let mut b = Rga::new(2);
+        let mut inbox = CausalBuffer::new();
+        assert_eq!(inbox.receive(&mut b, d1), 1);
+        assert_eq!(inbox.receive(&mut b, d2), 1);
+        assert_eq!(b.to_vec(), vec!['x', 'y']);
+        assert!(inbox.is_empty());
+    }
+
+    #[test]
+    fn reversed_rga_deltas_are_held_then_applied() {
+        let mut a = Rga::new(1);
+        let mut out = CausalBuffer::new();
+        let deltas: Vec<_> = "abcd"
+            .chars()
+            .enumerate()
+            .map(|(i, ch)| op(&mut a, &mut out, 1, |r| r.insert_at(i, ch).unwrap()))
+            .collect();
+
+        let mut b = Rga::new(2);
+        let mut inbox = CausalBuffer::new();
+        for d in deltas.iter().skip(1).rev() {
+            assert_eq!(inbox.receive(&mut b, d.clone()), 0);
+        }
+        assert_eq!(inbox.pending_len(), 3);
+        assert_eq!(
+            inbox.gaps(),
+            vec![Gap {
+                origin: 1,
+                start: 1,
+                end: 1
+            }]
+        );
+
+        assert_eq!(inbox.receive(&mut b, deltas[0].clone()), 4);
+        assert_eq!(b.to_vec(), vec!['a', 'b', 'c', 'd']);
+        assert!(inbox.gaps().is_empty());
+    }
+
+    #[test]
+    fn remove_waits_for_add_from_other_origin() {
+        // Node 1 adds, node 2 sees it and removes it.
+        let mut n1 = ORSet::new(1);
+        let mut out1 = CausalBuffer::new();
+        let add = op(&mut n1, &mut out1, 1, |s| {
+            s.insert("x");
+        });
+
+        let mut n2 = ORSet::new(2);
+        let mut buf2 = CausalBuffer::new();
+        buf2.receive(&mut n2, add.clone());
+        let remove = op(&mut n2, &mut buf2, 2, |s| {
+            s.remove(&"x");
+        });
+        assert_eq!(remove.deps.get(1), 1);
+
+        // Node 3 gets the remove first.
+        let mut n3 = ORSet::new(3);
+        let mut inbox = CausalBuffer::new();
+        assert_eq!(inbox.receive(&mut n3, remove), 0);
+        assert_eq!(
+            inbox.gaps(),
+            vec![Gap {
+                origin: 1,
+                start: 1,
+                end: 1
+            }]
+        );
+        assert_eq!(inbox.receive(&mut n3, add), 2);
+        assert!(!n3.contains(&"x"));
+    }
+
+    #[test]
+    fn delta_waiting_on_two_origins_applies_after_both() {
+        let mut n1 = GCounter::new(1);
+        let d1 = op(&mut n1, &mut CausalBuffer::new(), 1, GCounter::increment);
+        let mut n2 = GCounter::new(2);
+        let d2 = op(&mut n2, &mut CausalBuffer::new(), 2, GCounter::increment);
+
+        let mut n3 = GCounter::new(3);
+        let mut buf3 = CausalBuffer::new();
+        buf3.receive(&mut n3, d1.clone());
+        buf3.receive(&mut n3, d2.clone());
+        let d3 = op(&mut n3, &mut buf3, 3, GCounter::increment);
+
+        let mut n4 = GCounter::new(4);
+        let mut inbox = CausalBuffer::new();
+        assert_eq!(inbox.receive(&mut n4, d3), 0);
+        assert_eq!(inbox.receive(&mut n4, d2), 1);
+        assert_eq!(inbox.pending_len(), 1);
+        assert_eq!(inbox.receive(&mut n4, d1), 2);
+        assert_eq!(n4.value(), 3);
+        assert!(inbox.is_empty());
+    }
+
+    #[test]
+    fn duplicates_are_ignored() {
+        let mut a = ORSet::new(1);
+        let mut out = CausalBuffer::new();
+        let d = op(&mut a, &mut out, 1, |s| {
+            s.insert(1);
+        });
+
+        let mut b = ORSet::new(2);
+        let mut inbox = CausalBuffer::new();
+        assert_eq!(inbox.receive(&mut b, d.clone()), 1);
+        assert_eq!(inbox.receive(&mut b, d), 0);
+        assert!(inbox.is_empty());
+    }
+
+    #[test]
+    fn gaps_report_holes_in_sequence() {
+        let mut a = ORSet::new(1);
+        let mut out = CausalBuffer::new();
+        let deltas: Vec<_> = (0..6)
+            .map(|i| {
+                op(&mut a, &mut out, 1, |s| {
+                    s.insert(i);
+                })
+            })
+            .collect();
+
+        let mut b = ORSet::new(2);
+        let mut inbox = CausalBuffer::new();
+        inbox.receive(&mut b, deltas[0].clone());
+        inbox.receive(&mut b, deltas[2].clone());
+        inbox.receive(&mut b, deltas[5].clone());
+        assert_eq!(
+            inbox.gaps(),
+            vec![
+                Gap {
+                    origin: 1,
+                    start: 2,
+                    end: 2
+                },
+                Gap {
+                    origin: 1,
+                    start: 4,
+                    end: 5
+                },
+            ]
+        );
+        assert_eq!(inbox.delivered().get(1), 1);
+    }
+}
@@ -25,9 +25,11 @@
 //! assert!(ts3 > remote_ts);
 //! ```
 
+use alloc::collections::BTreeMap;
 use core::cmp;
 
-use crate::NodeId;
+use crate::codec::{CodecError, Decode, Encode, Reader, Writer};
+use crate::{Crdt, NodeId};
 
 /// A timestamp from a Hybrid Logical Clock.
 ///
@@ -209,6 +211,125 @@ impl HybridClock {
     }
 }
 
+/// The latest [`HybridTimestamp`] observed from each node.
+///
+/// An HLC watermark is the causal summary for timestamp-based CRDTs such as
+/// [`LWWMap`](crate::LWWMap), playing the role a
+/// [`VersionVector`](crate::VersionVector) plays for counter-based ones.
+/// Each node's timestamps are monotonic, so a write is covered once the
+/// watermark for its node has reached the write's timestamp.
+///
+/// # Example
+///
+/// ```
+/// use crdt_kit::clock::{HlcWatermark, HybridTimestamp};
+///
+/// let ts = |ms, node| HybridTimestamp { physical: ms, logical: 0, node_id: node };
+///
+/// let mut wm = HlcWatermark::new();
+/// wm.observe(ts(100, 1));
+/// assert!(wm.covers(&ts(50, 1)));
+/// assert!(!wm.covers(&ts(150, 1)));
+/// assert!(!wm.covers(&ts(50, 2)));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct HlcWatermark {
+    latest: BTreeMap<u16, HybridTimestamp>,
+}
+
+impl HlcWatermark {
+    /// Create an empty watermark.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            latest: BTreeMap::new(),
+        }
+    }
+
+    /// Get the latest timestamp observed from `node_id`, if any.
+    #[must_use]
+    pub fn get(&self, node_id: u16) -> Option<HybridTimestamp> {
+        self.latest.get(&node_id).copied()
+    }
+
+    /// Record that `ts` has been observed.
+    pub fn observe(&mut self, ts: HybridTimestamp) {
+        let entry = self.latest.entry(ts.node_id).or_insert(ts);
+        *entry = cmp::max(*entry, ts);
+    }
+
+    /// Check whether a write stamped with `ts` has been observed.
+    #[must_use]
+    pub fn covers(&self, ts: &HybridTimestamp) -> bool {
+        self.latest.get(&ts.node_id).is_some_and(|seen| seen >= ts)
+    }
+
+    /// Iterate over the latest timestamp per node, in node order.
+    pub fn iter(&self) -> impl Iterator<Item = &HybridTimestamp> {
+        self.latest.values()
+    }
+
+    /// Check whether nothing has been observed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.latest.is_empty()
+    }
+}
+
+impl Crdt for HlcWatermark {
+    fn merge(&mut self, other: &Self) {
+        for ts in other.iter() {
+            self.observe(*ts);
+        }
+    }
+}
+
+impl FromIterator<HybridTimestamp> for HlcWatermark {
+    fn from_iter<I: IntoIterator<Item = HybridTimestamp>>(iter: I) -> Self {
+        let mut wm = Self::new();
+        for ts in iter {
+            wm.observe(ts);
+        }
+        wm
+    }
+}
+
+impl Encode for HybridTimestamp {
+    fn encode(&self, w: &mut Writer) {
+        w.write_varint(self.physical);
+        self.logical.encode(w);
+        self.node_id.encode(w);
+    }
+}
+
+impl Decode for HybridTimestamp {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Ok(Self {
+            physical: r.read_varint()?,
+            logical: u16::decode(r)?,
+            node_id: u16::decode(r)?,
+        })
+    }
+}
+
+impl Encode for HlcWatermark {
+    fn encode(&self, w: &mut Writer) {
+        w.write_len(self.latest.len());
+        for ts in self.latest.values() {
+            ts.encode(w);
+        }
+    }
+}
+
+impl Decode for HlcWatermark {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        let len = r.read_len()?;
+        (0..len).map(|_| HybridTimestamp::decode(r)).collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -338,4 +459,22 @@ mod tests {
         assert!(a.to_u128() < b.to_u128());
         assert!(b.to_u128() < c.to_u128());
     }
+
+    #[test]
+    fn watermark_tracks_latest_per_node() {
+        let ts = |physical, node_id| HybridTimestamp {
+            physical,
+            logical: 0,
+            node_id,
+        };
+        let mut wm: HlcWatermark = [ts(300, 1), ts(100, 1), ts(200, 2)].into_iter().collect();
+        assert_eq!(wm.get(1), Some(ts(300, 1)));
+        assert_eq!(wm.get(3), None);
+
+        let other: HlcWatermark = [ts(400, 2), ts(10, 3)].into_iter().collect();
+        wm.merge(&other);
+        assert!(wm.covers(&ts(400, 2)));
+        assert!(wm.covers(&ts(10, 3)));
+        assert!(!wm.covers(&ts(301, 1)));
+    }
 }
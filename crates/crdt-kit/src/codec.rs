@@ -0,0 +1,1059 @@
+//! Compact binary encoding for all CRDT states and deltas.
+//!
+//! The format is native to crdt-kit and works in `no_std`: integers are
+//! LEB128 varints, signed integers are zigzag-encoded first, and
+//! collections are prefixed with their length. Types that carry many
+//! `(actor, counter)` tags — [`ORSet`](crate::ORSet),
+//! [`AWMap`](crate::AWMap), [`MVRegister`](crate::MVRegister) and
+//! [`Rga`](crate::Rga) — write each distinct actor once in an actor table
+//! and refer to it by index, so a tag usually costs two or three bytes.
+//!
+//! [`encode`] and [`decode`] wrap a whole replica in a
+//! [`VersionedEnvelope`] stamped with [`Versioned::CURRENT_VERSION`];
+//! [`encode_delta`] and [`decode_delta`] do the same for deltas, flagged
+//! with [`EnvelopeFlags::DELTA`]. Decoding rejects envelopes of another CRDT
+//! type or schema version, and a state where a delta is expected or vice
+//! versa.
+//!
+//! Element types (set members, map keys and values, register values)
+//! implement [`Encode`] and [`Decode`]. Implementations are provided for
+//! integers, `bool`, `char`, `String`, `Vec<u8>`, `Option`, tuples and
+//! [`HybridTimestamp`](crate::clock::HybridTimestamp).
+//!
+//! # Example
+//!
+//! ```
+//! use crdt_kit::codec;
+//! use crdt_kit::prelude::*;
+//!
+//! let mut sensor = GCounter::new(7);
+//! sensor.increment_by(1200);
+//!
+//! let bytes = codec::encode(&sensor);
+//! assert!(bytes.len() < 16);
+//!
+//! let decoded: GCounter = codec::decode(&bytes).unwrap();
+//! assert_eq!(decoded, sensor);
+//! ```
+//!
+//! [`BinaryCodec`] plugs the same encoding into [`sync`](crate::sync) and
+//! [`transport`](crate::transport) as a [`SyncCodec`].
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::sync::{SyncCodec, SyncMessage};
+use crate::version::{
+    CrdtType, EnvelopeError, EnvelopeFlags, VersionError, Versioned, VersionedEnvelope, MAGIC_BYTE,
+};
+use crate::{DeltaCrdt, DeltaSince, NodeId};
+
+/// Error raised while decoding binary data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodecError {
+    /// The input ended in the middle of a value.
+    UnexpectedEnd,
+    /// A varint was longer than its target integer allows.
+    VarintOverflow,
+    /// A value was out of range for its type.
+    InvalidValue(&'static str),
+    /// A string was not valid UTF-8.
+    InvalidUtf8,
+    /// A tag referred to an actor missing from the actor table.
+    UnknownActor(u64),
+    /// Bytes were left over after the value was decoded.
+    TrailingBytes(usize),
+    /// The envelope around the payload was malformed.
+    Envelope(EnvelopeError),
+    /// The envelope holds a different CRDT type.
+    CrdtTypeMismatch {
+        /// The type being decoded.
+        expected: CrdtType,
+        /// The type in the envelope.
+        found: CrdtType,
+    },
+    /// The envelope uses a different schema version.
+    VersionMismatch {
+        /// [`Versioned::CURRENT_VERSION`] of the type being decoded.
+        expected: u8,
+        /// The version in the envelope.
+        found: u8,
+    },
+    /// A state was expected but the envelope is flagged as a delta.
+    ExpectedState,
+    /// A delta was expected but the envelope is flagged as a state.
+    ExpectedDelta,
+    /// The payload is compressed or encrypted; undo that before decoding.
+    UnsupportedFlags(EnvelopeFlags),
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd => write!(f, "unexpected end of input"),
+            Self::VarintOverflow => write!(f, "varint overflows its integer type"),
+            Self::InvalidValue(what) => write!(f, "invalid {what}"),
+            Self::InvalidUtf8 => write!(f, "string is not valid UTF-8"),
+            Self::UnknownActor(i) => write!(f, "actor index {i} is not in the actor table"),
+            Self::TrailingBytes(n) => write!(f, "{n} trailing bytes after value"),
+            Self::Envelope(e) => write!(f, "envelope error: {e}"),
+            Self::CrdtTypeMismatch { expected, found } => {
+                write!(f, "envelope holds {found:?}, expected {expected:?}")
+            }
+            Self::VersionMismatch { expected, found } => {
+                write!(f, "envelope uses schema version {found}, expected {expected}")
+            }
+            Self::ExpectedState => write!(f, "expected a state, envelope holds a delta"),
+            Self::ExpectedDelta => write!(f, "expected a delta, envelope holds a state"),
+            Self::UnsupportedFlags(flags) => {
+                write!(f, "unsupported envelope flags: 0b{:08b}", flags.bits())
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CodecError {}
+
+impl From<EnvelopeError> for CodecError {
+    fn from(e: EnvelopeError) -> Self {
+        Self::Envelope(e)
+    }
+}
+
+// ── Writer / Reader ────────────────────────────────────────────────
+
+/// Append-only output buffer.
+#[derive(Debug, Clone, Default)]
+pub struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    /// Create an empty writer.
+    #[must_use]
+    pub fn new() -> Self {
+        Self { buf: Vec::new() }
+    }
+
+    /// Write a single raw byte.
+    pub fn write_u8(&mut self, b: u8) {
+        self.buf.push(b);
+    }
+
+    /// Write an unsigned LEB128 varint (1–10 bytes).
+    pub fn write_varint(&mut self, mut v: u64) {
+        while v >= 0x80 {
+            self.buf.push((v as u8) | 0x80);
+            v >>= 7;
+        }
+        self.buf.push(v as u8);
+    }
+
+    /// Write a collection length.
+    pub fn write_len(&mut self, len: usize) {
+        self.write_varint(len as u64);
+    }
+
+    /// Write a length-prefixed byte string.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_len(bytes.len());
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Number of bytes written so far.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Check whether nothing has been written.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Take the written bytes.
+    #[must_use]
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// Cursor over an input buffer.
+#[derive(Debug, Clone)]
+pub struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    /// Start reading at the beginning of `data`.
+    #[must_use]
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Read a single raw byte.
+    pub fn read_u8(&mut self) -> Result<u8, CodecError> {
+        let (&b, rest) = self.data.split_first().ok_or(CodecError::UnexpectedEnd)?;
+        self.data = rest;
+        Ok(b)
+    }
+
+    /// Read an unsigned LEB128 varint.
+    pub fn read_varint(&mut self) -> Result<u64, CodecError> {
+        let mut v = 0u64;
+        let mut shift = 0u32;
+        loop {
+            let b = self.read_u8()?;
+            let bits = u64::from(b & 0x7F);
+            if shift == 63 && bits > 1 {
+                return Err(CodecError::VarintOverflow);
+            }
+            v |= bits << shift;
+            if b & 0x80 == 0 {
+                return Ok(v);
+            }
+            shift += 7;
+            if shift > 63 {
+                return Err(CodecError::VarintOverflow);
+            }
+        }
+    }
+
+    /// Read a collection length.
+    ///
+    /// Every encoded element takes at least one byte, so a length larger
+    /// than the remaining input is rejected before anything is allocated.
+    pub fn read_len(&mut self) -> Result<usize, CodecError> {
+        let len = self.read_varint()?;
+        if len > self.data.len() as u64 {
+            return Err(CodecError::UnexpectedEnd);
+        }
+        Ok(len as usize)
+    }
+
+    /// Read a length-prefixed byte string.
+    pub fn read_bytes(&mut self) -> Result<&'a [u8], CodecError> {
+        let len = self.read_len()?;
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    /// Number of bytes not yet read.
+    #[must_use]
+    pub fn remaining(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Succeed only if the whole input has been consumed.
+    pub fn finish(self) -> Result<(), CodecError> {
+        match self.data.len() {
+            0 => Ok(()),
+            n => Err(CodecError::TrailingBytes(n)),
+        }
+    }
+}
+
+// ── Traits ─────────────────────────────────────────────────────────
+
+/// A value with a binary encoding.
+pub trait Encode {
+    /// Append this value to `w`.
+    fn encode(&self, w: &mut Writer);
+}
+
+/// A value that can be read back from its binary encoding.
+pub trait Decode: Sized {
+    /// Read one value from `r`.
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError>;
+}
+
+/// Encode a single value without an envelope.
+#[must_use]
+pub fn to_bytes<T: Encode + ?Sized>(value: &T) -> Vec<u8> {
+    let mut w = Writer::new();
+    value.encode(&mut w);
+    w.into_bytes()
+}
+
+/// Decode a single value without an envelope, rejecting trailing bytes.
+pub fn from_bytes<T: Decode>(bytes: &[u8]) -> Result<T, CodecError> {
+    let mut r = Reader::new(bytes);
+    let value = T::decode(&mut r)?;
+    r.finish()?;
+    Ok(value)
+}
+
+/// Encode a replica in a [`VersionedEnvelope`] at its current schema version.
+#[must_use]
+pub fn encode<C: Versioned + Encode>(crdt: &C) -> Vec<u8> {
+    VersionedEnvelope::new(C::CURRENT_VERSION, C::CRDT_TYPE, to_bytes(crdt)).to_bytes()
+}
+
+/// Decode a replica written by [`encode`].
+pub fn decode<C: Versioned + Decode>(bytes: &[u8]) -> Result<C, CodecError> {
+    let envelope = open_envelope::<C>(bytes)?;
+    if envelope.flags.is_delta() {
+        return Err(CodecError::ExpectedState);
+    }
+    from_bytes(&envelope.payload)
+}
+
+/// Encode a delta of `C` in a [`VersionedEnvelope`] at `C`'s schema version.
+#[must_use]
+pub fn encode_delta<C>(delta: &C::Delta) -> Vec<u8>
+where
+    C: Versioned + DeltaCrdt,
+    C::Delta: Encode,
+{
+    VersionedEnvelope::new(C::CURRENT_VERSION, C::CRDT_TYPE, to_bytes(delta))
+        .with_flags(EnvelopeFlags::DELTA)
+        .to_bytes()
+}
+
+/// Decode a delta written by [`encode_delta`].
+///
+/// A v1 envelope cannot say what it holds, so its payload is assumed to be
+/// a delta.
+pub fn decode_delta<C>(bytes: &[u8]) -> Result<C::Delta, CodecError>
+where
+    C: Versioned + DeltaCrdt,
+    C::Delta: Decode,
+{
+    let envelope = open_envelope::<C>(bytes)?;
+    if !envelope.flags.is_delta() && bytes[0] != MAGIC_BYTE {
+        return Err(CodecError::ExpectedDelta);
+    }
+    from_bytes(&envelope.payload)
+}
+
+fn open_envelope<C: Versioned>(bytes: &[u8]) -> Result<VersionedEnvelope, CodecError> {
+    let envelope = VersionedEnvelope::from_bytes(bytes)?;
+    if envelope.crdt_type != C::CRDT_TYPE {
+        return Err(CodecError::CrdtTypeMismatch {
+            expected: C::CRDT_TYPE,
+            found: envelope.crdt_type,
+        });
+    }
+    if envelope.version != C::CURRENT_VERSION {
+        return Err(CodecError::VersionMismatch {
+            expected: C::CURRENT_VERSION,
+            found: envelope.version,
+        });
+    }
+    let unsupported = envelope.flags.bits() & !EnvelopeFlags::DELTA.bits();
+    if unsupported != 0 {
+        return Err(CodecError::UnsupportedFlags(envelope.flags));
+    }
+    Ok(envelope)
+}
+
+// ── Actor table ────────────────────────────────────────────────────
+
+/// Distinct actors of a value, written once and referenced by index.
+pub(crate) struct ActorTable {
+    actors: Vec<NodeId>,
+}
+
+impl ActorTable {
+    /// Build a table from every actor the value mentions.
+    pub(crate) fn new(actors: impl IntoIterator<Item = NodeId>) -> Self {
+        let set: BTreeSet<NodeId> = actors.into_iter().collect();
+        Self {
+            actors: set.into_iter().collect(),
+        }
+    }
+
+    pub(crate) fn write_actor(&self, w: &mut Writer, actor: NodeId) {
+        // Every actor written was collected into the table.
+        let index = self.actors.binary_search(&actor).unwrap_or(0);
+        w.write_len(index);
+    }
+
+    pub(crate) fn read_actor(&self, r: &mut Reader<'_>) -> Result<NodeId, CodecError> {
+        let index = r.read_varint()?;
+        usize::try_from(index)
+            .ok()
+            .and_then(|i| self.actors.get(i).copied())
+            .ok_or(CodecError::UnknownActor(index))
+    }
+
+    pub(crate) fn write_tag(&self, w: &mut Writer, (actor, counter): (NodeId, u64)) {
+        self.write_actor(w, actor);
+        w.write_varint(counter);
+    }
+
+    pub(crate) fn read_tag(&self, r: &mut Reader<'_>) -> Result<(NodeId, u64), CodecError> {
+        Ok((self.read_actor(r)?, r.read_varint()?))
+    }
+
+    pub(crate) fn write_tags(&self, w: &mut Writer, tags: &BTreeSet<(NodeId, u64)>) {
+        w.write_len(tags.len());
+        for &tag in tags {
+            self.write_tag(w, tag);
+        }
+    }
+
+    pub(crate) fn read_tags(
+        &self,
+        r: &mut Reader<'_>,
+    ) -> Result<BTreeSet<(NodeId, u64)>, CodecError> {
+        let len = r.read_len()?;
+        (0..len).map(|_| self.read_tag(r)).collect()
+    }
+
+    /// Write `(tag, dot)` tombstones: a removed tag and the remove that hit it.
+    pub(crate) fn write_tombstones(
+        &self,
+        w: &mut Writer,
+        tombstones: impl ExactSizeIterator<Item = ((NodeId, u64), (NodeId, u64))>,
+    ) {
+        w.write_len(tombstones.len());
+        for (tag, dot) in tombstones {
+            self.write_tag(w, tag);
+            self.write_tag(w, dot);
+        }
+    }
+
+    pub(crate) fn read_tombstones<B>(&self, r: &mut Reader<'_>) -> Result<B, CodecError>
+    where
+        B: FromIterator<((NodeId, u64), (NodeId, u64))>,
+    {
+        let len = r.read_len()?;
+        (0..len)
+            .map(|_| Ok((self.read_tag(r)?, self.read_tag(r)?)))
+            .collect()
+    }
+
+    /// Write a `actor -> counter` map, such as a version vector.
+    pub(crate) fn write_counters(&self, w: &mut Writer, counters: &BTreeMap<NodeId, u64>) {
+        w.write_len(counters.len());
+        for (&actor, &counter) in counters {
+            self.write_tag(w, (actor, counter));
+        }
+    }
+
+    pub(crate) fn read_counters(
+        &self,
+        r: &mut Reader<'_>,
+    ) -> Result<BTreeMap<NodeId, u64>, CodecError> {
+        let len = r.read_len()?;
+        (0..len).map(|_| self.read_tag(r)).collect()
+    }
+}
+
+impl Encode for ActorTable {
+    fn encode(&self, w: &mut Writer) {
+        w.write_len(self.actors.len());
+        for &actor in &self.actors {
+            w.write_varint(actor);
+        }
+    }
+}
+
+impl Decode for ActorTable {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        let len = r.read_len()?;
+        let actors = (0..len)
+            .map(|_| r.read_varint())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { actors })
+    }
+}
+
+// ── Primitive implementations ──────────────────────────────────────
+
+macro_rules! impl_unsigned {
+    ($($t:ty),*) => {$(
+        impl Encode for $t {
+            fn encode(&self, w: &mut Writer) {
+                w.write_varint(*self as u64);
+            }
+        }
+
+        impl Decode for $t {
+            fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+                <$t>::try_from(r.read_varint()?).map_err(|_| CodecError::VarintOverflow)
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_signed {
+    ($($t:ty),*) => {$(
+        impl Encode for $t {
+            fn encode(&self, w: &mut Writer) {
+                let v = *self as i64;
+                w.write_varint(((v << 1) ^ (v >> 63)) as u64);
+            }
+        }
+
+        impl Decode for $t {
+            fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+                let z = r.read_varint()?;
+                let v = ((z >> 1) as i64) ^ -((z & 1) as i64);
+                <$t>::try_from(v).map_err(|_| CodecError::VarintOverflow)
+            }
+        }
+    )*};
+}
+
+impl_unsigned!(u16, u32, u64, usize);
+impl_signed!(i8, i16, i32, i64, isize);
+
+impl Encode for u8 {
+    fn encode(&self, w: &mut Writer) {
+        w.write_u8(*self);
+    }
+}
+
+impl Decode for u8 {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        r.read_u8()
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self, w: &mut Writer) {
+        w.write_u8(u8::from(*self));
+    }
+}
+
+impl Decode for bool {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        match r.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(CodecError::InvalidValue("bool")),
+        }
+    }
+}
+
+impl Encode for char {
+    fn encode(&self, w: &mut Writer) {
+        w.write_varint(u64::from(*self));
+    }
+}
+
+impl Decode for char {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        u32::decode(r)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or(CodecError::InvalidValue("char"))
+    }
+}
+
+impl Encode for str {
+    fn encode(&self, w: &mut Writer) {
+        w.write_bytes(self.as_bytes());
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, w: &mut Writer) {
+        self.as_str().encode(w);
+    }
+}
+
+impl Decode for String {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        let bytes = r.read_bytes()?;
+        core::str::from_utf8(bytes)
+            .map(String::from)
+            .map_err(|_| CodecError::InvalidUtf8)
+    }
+}
+
+impl<T: Encode + ?Sized> Encode for &T {
+    fn encode(&self, w: &mut Writer) {
+        (**self).encode(w);
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, w: &mut Writer) {
+        match self {
+            None => w.write_u8(0),
+            Some(v) => {
+                w.write_u8(1);
+                v.encode(w);
+            }
+        }
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        match r.read_u8()? {
+            0 => Ok(None),
+            1 => Ok(Some(T::decode(r)?)),
+            _ => Err(CodecError::InvalidValue("option tag")),
+        }
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, w: &mut Writer) {
+        w.write_len(self.len());
+        for v in self {
+            v.encode(w);
+        }
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        let len = r.read_len()?;
+        (0..len).map(|_| T::decode(r)).collect()
+    }
+}
+
+impl<T: Encode> Encode for BTreeSet<T> {
+    fn encode(&self, w: &mut Writer) {
+        w.write_len(self.len());
+        for v in self {
+            v.encode(w);
+        }
+    }
+}
+
+impl<T: Decode + Ord> Decode for BTreeSet<T> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        let len = r.read_len()?;
+        (0..len).map(|_| T::decode(r)).collect()
+    }
+}
+
+impl<K: Encode, V: Encode> Encode for BTreeMap<K, V> {
+    fn encode(&self, w: &mut Writer) {
+        w.write_len(self.len());
+        for (k, v) in self {
+            k.encode(w);
+            v.encode(w);
+        }
+    }
+}
+
+impl<K: Decode + Ord, V: Decode> Decode for BTreeMap<K, V> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        let len = r.read_len()?;
+        (0..len)
+            .map(|_| Ok((K::decode(r)?, V::decode(r)?)))
+            .collect()
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+    fn encode(&self, w: &mut Writer) {
+        self.0.encode(w);
+        self.1.encode(w);
+    }
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Ok((A::decode(r)?, B::decode(r)?))
+    }
+}
+
+impl<A: Encode, B: Encode, C: Encode> Encode for (A, B, C) {
+    fn encode(&self, w: &mut Writer) {
+        self.0.encode(w);
+        self.1.encode(w);
+        self.2.encode(w);
+    }
+}
+
+impl<A: Decode, B: Decode, C: Decode> Decode for (A, B, C) {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Ok((A::decode(r)?, B::decode(r)?, C::decode(r)?))
+    }
+}
+
+impl Encode for CrdtType {
+    fn encode(&self, w: &mut Writer) {
+        w.write_u8(*self as u8);
+    }
+}
+
+impl Decode for CrdtType {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        CrdtType::from_byte(r.read_u8()?).ok_or(CodecError::InvalidValue("CRDT type"))
+    }
+}
+
+// ── Sync messages ──────────────────────────────────────────────────
+
+const HELLO: u8 = 0;
+const DELTA: u8 = 1;
+const ACK: u8 = 2;
+const DONE: u8 = 3;
+
+impl<C> Encode for SyncMessage<C>
+where
+    C: DeltaSince,
+    C::Summary: Encode,
+    C::Delta: Encode,
+{
+    fn encode(&self, w: &mut Writer) {
+        match self {
+            Self::Hello {
+                crdt_type,
+                version,
+                summary,
+            } => {
+                w.write_u8(HELLO);
+                crdt_type.encode(w);
+                w.write_u8(*version);
+                summary.encode(w);
+            }
+            Self::Delta(delta) => {
+                w.write_u8(DELTA);
+                delta.encode(w);
+            }
+            Self::Ack => w.write_u8(ACK),
+            Self::Done => w.write_u8(DONE),
+        }
+    }
+}
+
+impl<C> Decode for SyncMessage<C>
+where
+    C: DeltaSince,
+    C::Summary: Decode,
+    C::Delta: Decode,
+{
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        match r.read_u8()? {
+            HELLO => Ok(Self::Hello {
+                crdt_type: CrdtType::decode(r)?,
+                version: r.read_u8()?,
+                summary: C::Summary::decode(r)?,
+            }),
+            DELTA => Ok(Self::Delta(C::Delta::decode(r)?)),
+            ACK => Ok(Self::Ack),
+            DONE => Ok(Self::Done),
+            _ => Err(CodecError::InvalidValue("sync message tag")),
+        }
+    }
+}
+
+impl From<CodecError> for VersionError {
+    fn from(e: CodecError) -> Self {
+        VersionError::Deserialize(alloc::string::ToString::to_string(&e))
+    }
+}
+
+/// [`SyncCodec`] using the binary encoding of this module.
+///
+/// ```
+/// use crdt_kit::codec::BinaryCodec;
+/// use crdt_kit::prelude::*;
+/// use crdt_kit::sync::{SyncCodec, SyncMessage};
+///
+/// let mut c = GCounter::new(1);
+/// c.increment();
+/// let msg = SyncMessage::<GCounter>::Delta(c.delta_since(&VersionVector::new()));
+///
+/// let bytes = BinaryCodec.encode(&msg).unwrap();
+/// assert_eq!(BinaryCodec.decode(&bytes).unwrap(), msg);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BinaryCodec;
+
+impl<C> SyncCodec<C> for BinaryCodec
+where
+    C: DeltaSince,
+    C::Summary: Encode + Decode,
+    C::Delta: Encode + Decode,
+{
+    fn encode(&self, msg: &SyncMessage<C>) -> Result<Vec<u8>, VersionError> {
+        Ok(to_bytes(msg))
+    }
+
+    fn decode(&self, payload: &[u8]) -> Result<SyncMessage<C>, VersionError> {
+        Ok(from_bytes(payload)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{HlcWatermark, HybridTimestamp};
+    use crate::prelude::*;
+    use crate::RgaDelta;
+
+    fn ts(physical: u64, node_id: u16) -> HybridTimestamp {
+        HybridTimestamp {
+            physical,
+            logical: 0,
+            node_id,
+        }
+    }
+
+    fn roundtrip<T: Encode + Decode>(value: &T) -> T {
+        from_bytes(&to_bytes(value)).unwrap()
+    }
+
+    #[test]
+    fn varint_boundaries() {
+        for v in [0, 1, 127, 128, 16_383, 16_384, u64::MAX / 2, u64::MAX] {
+            assert_eq!(roundtrip(&v), v);
+        }
+        assert_eq!(to_bytes(&127u64).len(), 1);
+        assert_eq!(to_bytes(&128u64).len(), 2);
+        assert_eq!(to_bytes(&u64::MAX).len(), 10);
+    }
+
+    #[test]
+    fn signed_zigzag() {
+        for v in [0i64, -1, 1, -64, 63, i64::MIN, i64::MAX] {
+            assert_eq!(roundtrip(&v), v);
+        }
+        assert_eq!(to_bytes(&-1i32).len(), 1);
+    }
+
+    #[test]
+    fn varint_overflow_rejected() {
+        let mut bytes = vec![0xFF; 9];
+        bytes.push(0x02);
+        assert_eq!(from_bytes::<u64>(&bytes), Err(CodecError::VarintOverflow));
+        assert_eq!(
+            from_bytes::<u16>(&to_bytes(&70_000u32)),
+            Err(CodecError::VarintOverflow)
+        );
+    }
+
+    #[test]
+    fn strings_and_options() {
+        let s = String::from("héllo");
+        assert_eq!(roundtrip(&s), s);
+        assert_eq!(roundtrip(&Some('ß')), Some('ß'));
+        assert_eq!(roundtrip(&None::<u32>), None);
+        assert_eq!(from_bytes::<String>(&[2, 0xC3, 0x28]), Err(CodecError::InvalidUtf8));
+        assert_eq!(from_bytes::<bool>(&[2]), Err(CodecError::InvalidValue("bool")));
+    }
+
+    #[test]
+    fn length_larger_than_input_rejected() {
+        assert_eq!(from_bytes::<Vec<u8>>(&[200, 1]), Err(CodecError::UnexpectedEnd));
+    }
+
+    #[test]
+    fn trailing_bytes_rejected() {
+        assert_eq!(from_bytes::<u8>(&[1, 2]), Err(CodecError::TrailingBytes(1)));
+    }
+
+    #[test]
+    fn counters_roundtrip() {
+        let mut a = GCounter::new(1);
+        a.increment_by(300);
+        let mut b = GCounter::new(2);
+        b.increment();
+        a.merge(&b);
+        assert_eq!(roundtrip(&a), a);
+        let delta = a.delta_since(&b.summary());
+        assert_eq!(roundtrip(&delta), delta);
+
+        let mut p = PNCounter::new(1);
+        p.increment();
+        p.decrement();
+        p.decrement();
+        assert_eq!(roundtrip(&p), p);
+        let delta = p.delta(&PNCounter::new(2));
+        assert_eq!(roundtrip(&delta), delta);
+    }
+
+    #[test]
+    fn sets_roundtrip() {
+        let mut g = GSet::new();
+        g.insert(String::from("a"));
+        g.insert(String::from("b"));
+        assert_eq!(roundtrip(&g), g);
+        let delta = g.delta(&GSet::new());
+        assert_eq!(roundtrip(&delta), delta);
+
+        let mut t = TwoPSet::new();
+        t.insert(1u32);
+        t.insert(2);
+        t.remove(&1);
+        assert_eq!(roundtrip(&t), t);
+        let delta = t.delta(&TwoPSet::new());
+        assert_eq!(roundtrip(&delta), delta);
+
+        let mut o = ORSet::new(1);
+        o.insert(10u32);
+        o.insert(20);
+        o.remove(&10);
+        let mut o2 = ORSet::new(2);
+        o2.insert(30);
+        o.merge(&o2);
+        assert_eq!(roundtrip(&o), o);
+        let delta = o.delta_since(&VersionVector::new());
+        assert_eq!(roundtrip(&delta), delta);
+    }
+
+    #[test]
+    fn registers_roundtrip() {
+        let r = LWWRegister::with_timestamp(String::from("on"), ts(100, 3));
+        let decoded = roundtrip(&r);
+        assert_eq!(decoded.value(), r.value());
+        assert_eq!(decoded.timestamp(), r.timestamp());
+        let delta = r.delta(&LWWRegister::with_timestamp(String::from("off"), ts(50, 1)));
+        assert_eq!(roundtrip(&delta), delta);
+
+        let mut m = MVRegister::new(1);
+        m.set(5i32);
+        let mut m2 = MVRegister::new(2);
+        m2.set(-7);
+        m.merge(&m2);
+        assert_eq!(roundtrip(&m), m);
+        let delta = m.delta_since(&VersionVector::new());
+        assert_eq!(roundtrip(&delta), delta);
+    }
+
+    #[test]
+    fn maps_roundtrip() {
+        let mut l = LWWMap::new();
+        l.insert(String::from("temp"), 21u32, ts(10, 1));
+        l.insert(String::from("hum"), 60, ts(11, 1));
+        l.remove(&String::from("hum"), ts(12, 2));
+        assert_eq!(roundtrip(&l), l);
+        let delta = l.delta_since(&HlcWatermark::new());
+        assert_eq!(roundtrip(&delta), delta);
+        let wm = l.watermark();
+        assert_eq!(roundtrip(&wm), wm);
+
+        let mut a = AWMap::new(1);
+        a.insert(String::from("x"), 1u8);
+        a.insert(String::from("y"), 2);
+        a.remove(&String::from("x"));
+        let mut b = a.clone();
+        b.insert(String::from("y"), 3);
+        a.merge(&AWMap::new(2));
+        a.merge(&b);
+        assert_eq!(roundtrip(&a), a);
+        let delta = a.delta_since(&VersionVector::new());
+        assert_eq!(roundtrip(&delta), delta);
+    }
+
+    #[test]
+    fn sequences_roundtrip() {
+        let mut r = Rga::new(1);
+        r.insert_at(0, 1u32).unwrap();
+        r.insert_at(1, 2).unwrap();
+        let mut r2 = r.fork(2);
+        r2.insert_at(1, 3).unwrap();
+        r.remove(0).unwrap();
+        r.merge(&r2);
+        let decoded = roundtrip(&r);
+        assert_eq!(decoded, r);
+        assert_eq!(decoded.len(), r.len());
+        let delta: RgaDelta<u32> = r.delta_since(&VersionVector::new());
+        assert_eq!(roundtrip(&delta), delta);
+
+        let mut t = TextCrdt::new(1);
+        t.insert_str(0, "héllo").unwrap();
+        t.remove(0).unwrap();
+        let decoded = roundtrip(&t);
+        assert_eq!(decoded, t);
+        assert_eq!(decoded.to_string(), "éllo");
+    }
+
+    #[test]
+    fn envelope_checks_type_and_version() {
+        let mut c = GCounter::new(1);
+        c.increment();
+        let bytes = encode(&c);
+        assert_eq!(bytes[1], GCounter::CURRENT_VERSION);
+        assert_eq!(decode::<GCounter>(&bytes).unwrap(), c);
+
+        assert_eq!(
+            decode::<PNCounter>(&bytes),
+            Err(CodecError::CrdtTypeMismatch {
+                expected: CrdtType::PNCounter,
+                found: CrdtType::GCounter,
+            })
+        );
+
+        let stale = VersionedEnvelope::new(9, CrdtType::GCounter, to_bytes(&c)).to_bytes();
+        assert_eq!(
+            decode::<GCounter>(&stale),
+            Err(CodecError::VersionMismatch {
+                expected: 1,
+                found: 9
+            })
+        );
+        assert_eq!(
+            decode::<GCounter>(&[0x00]),
+            Err(CodecError::Envelope(EnvelopeError::TooShort))
+        );
+    }
+
+    #[test]
+    fn delta_envelope() {
+        let mut c = GCounter::new(1);
+        c.increment_by(5);
+        let delta = c.delta_since(&VersionVector::new());
+        let bytes = encode_delta::<GCounter>(&delta);
+        assert_eq!(decode_delta::<GCounter>(&bytes).unwrap(), delta);
+        assert_eq!(decode::<GCounter>(&bytes), Err(CodecError::ExpectedState));
+        assert_eq!(
+            decode_delta::<GCounter>(&encode(&c)),
+            Err(CodecError::ExpectedDelta)
+        );
+
+        // v1 envelopes carry no flags and are accepted either way.
+        let v1 = VersionedEnvelope::from_bytes(&bytes).unwrap().to_bytes_v1();
+        assert_eq!(decode_delta::<GCounter>(&v1).unwrap(), delta);
+    }
+
+    #[test]
+    fn compressed_payload_rejected() {
+        let c = GCounter::new(1);
+        let flags = EnvelopeFlags::COMPRESSED;
+        let bytes = VersionedEnvelope::new(1, CrdtType::GCounter, to_bytes(&c))
+            .with_flags(flags)
+            .to_bytes();
+        assert_eq!(
+            decode::<GCounter>(&bytes),
+            Err(CodecError::UnsupportedFlags(flags))
+        );
+    }
+
+    #[test]
+    fn sync_messages_roundtrip() {
+        let mut o = ORSet::new(1);
+        o.insert(String::from("x"));
+        let messages: Vec<SyncMessage<ORSet<String>>> = vec![
+            SyncMessage::Hello {
+                crdt_type: CrdtType::ORSet,
+                version: 1,
+                summary: o.summary(),
+            },
+            SyncMessage::Delta(o.delta_since(&VersionVector::new())),
+            SyncMessage::Ack,
+            SyncMessage::Done,
+        ];
+        for msg in messages {
+            let bytes = BinaryCodec.encode(&msg).unwrap();
+            assert_eq!(BinaryCodec.decode(&bytes).unwrap(), msg);
+        }
+    }
+
+    #[test]
+    fn actor_table_keeps_tags_small() {
+        let mut o = ORSet::new(1_000_000_007);
+        for i in 0..50u32 {
+            o.insert(i);
+        }
+        // The 5-byte actor id is written once; each element then costs its
+        // value, tag count, actor index and counter: one byte each.
+        assert!(to_bytes(&o).len() <= 50 * 4 + 16);
+    }
+}
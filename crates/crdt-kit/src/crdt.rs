@@ -75,3 +75,43 @@ pub trait DeltaCrdt: Crdt {
     /// but typically much more efficient in terms of data transferred.
     fn apply_delta(&mut self, delta: &Self::Delta);
 }
+
+/// Extension trait for delta-state CRDTs that can produce a delta from a
+/// compact causal summary instead of the peer's full state.
+///
+/// [`DeltaCrdt::delta`] requires the sender to hold a copy of the receiver's
+/// state. With `DeltaSince`, the receiver only sends its
+/// [`summary`](DeltaSince::summary) — typically a
+/// [`VersionVector`](crate::VersionVector) — and the sender answers with
+/// [`delta_since`](DeltaSince::delta_since), containing only what the
+/// receiver has not observed.
+///
+/// Applying `a.delta_since(&b.summary())` to `b` must be equivalent to
+/// merging `a` into `b`.
+///
+/// # Example
+///
+/// ```
+/// use crdt_kit::prelude::*;
+///
+/// let mut gateway = ORSet::new(1);
+/// gateway.insert("temp");
+///
+/// let mut sensor = ORSet::new(2);
+/// sensor.insert("humidity");
+///
+/// // The sensor never needs a copy of the gateway's state.
+/// let delta = sensor.delta_since(&gateway.summary());
+/// gateway.apply_delta(&delta);
+/// assert!(gateway.contains(&"humidity"));
+/// ```
+pub trait DeltaSince: DeltaCrdt {
+    /// Compact summary of the operations a replica has observed.
+    type Summary: Clone;
+
+    /// Summarize the operations observed by this replica.
+    fn summary(&self) -> Self::Summary;
+
+    /// Generate a delta containing the changes in `self` not covered by `summary`.
+    fn delta_since(&self, summary: &Self::Summary) -> Self::Delta;
+}
@@ -0,0 +1,254 @@
+//! Myers' O(ND) difference algorithm, in linear space.
+//!
+//! [`diff`] finds a shortest edit script between two slices by bisecting on
+//! the "middle snake" of the edit graph (searching forwards from the start
+//! and backwards from the end until the two meet), then diffing each half.
+//! Common prefixes and suffixes are stripped first, so typical edits to a
+//! large text only search the changed region.
+//!
+//! The search takes O((N + M) D) steps for D differences. Each bisection
+//! gives up after [`EFFORT`] steps and treats its region as replaced
+//! outright, so pathological inputs (two long, unrelated texts) cost
+//! bounded time at the price of a longer script.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// Steps one bisection may take before giving up.
+const EFFORT: usize = 1 << 25;
+
+/// A stretch of `old` replaced by a stretch of `new`; either may be empty,
+/// but not both.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Hunk {
+    pub(crate) old: Range<usize>,
+    pub(crate) new: Range<usize>,
+}
+
+/// The hunks of a shortest edit script turning `old` into `new`, in order.
+/// Everything between hunks is unchanged.
+pub(crate) fn diff<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Hunk> {
+    let mut hunks = Vec::new();
+    diff_into(old, new, 0, 0, &mut hunks);
+    hunks
+}
+
+fn diff_into<T: PartialEq>(
+    mut old: &[T],
+    mut new: &[T],
+    mut old_start: usize,
+    mut new_start: usize,
+    hunks: &mut Vec<Hunk>,
+) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    old = &old[prefix..];
+    new = &new[prefix..];
+    old_start += prefix;
+    new_start += prefix;
+    let suffix = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    old = &old[..old.len() - suffix];
+    new = &new[..new.len() - suffix];
+
+    if old.is_empty() && new.is_empty() {
+        return;
+    }
+    let split = if old.is_empty() || new.is_empty() {
+        None
+    } else {
+        bisect(old, new)
+    };
+    match split {
+        Some((x, y)) => {
+            diff_into(&old[..x], &new[..y], old_start, new_start, hunks);
+            diff_into(&old[x..], &new[y..], old_start + x, new_start + y, hunks);
+        }
+        None => push(
+            hunks,
+            Hunk {
+                old: old_start..old_start + old.len(),
+                new: new_start..new_start + new.len(),
+            },
+        ),
+    }
+}
+
+/// Append `hunk`, joining it with the last one if they touch.
+fn push(hunks: &mut Vec<Hunk>, hunk: Hunk) {
+    match hunks.last_mut() {
+        Some(last) if last.old.end == hunk.old.start && last.new.end == hunk.new.start => {
+            last.old.end = hunk.old.end;
+            last.new.end = hunk.new.end;
+        }
+        _ => hunks.push(hunk),
+    }
+}
+
+/// Where a shortest edit script from `old` to `new` can be split in two,
+/// found where the forward and backward searches overlap; `None` if the
+/// slices have nothing in common or the search runs out of [`EFFORT`].
+fn bisect<T: PartialEq>(old: &[T], new: &[T]) -> Option<(usize, usize)> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max_d = (n + m + 1) / 2;
+    let offset = max_d;
+    let width = 2 * max_d + 2;
+    // Furthest x reached on each diagonal k = x - y, searching forwards
+    // (`forward`) and backwards from the end (`backward`); -1: not yet.
+    let mut forward = vec![-1isize; width as usize];
+    let mut backward = vec![-1isize; width as usize];
+    forward[(offset + 1) as usize] = 0;
+    backward[(offset + 1) as usize] = 0;
+    let delta = n - m;
+    // With an odd delta the forward search is the one that detects overlap.
+    let front = delta % 2 != 0;
+    let (mut k1_start, mut k1_end, mut k2_start, mut k2_end) = (0, 0, 0, 0);
+    let mut effort = 0;
+
+    for d in 0..max_d {
+        let mut k1 = -d + k1_start;
+        while k1 <= d - k1_end {
+            let i = (offset + k1) as usize;
+            let mut x1 = if k1 == -d || (k1 != d && forward[i - 1] < forward[i + 1]) {
+                forward[i + 1]
+            } else {
+                forward[i - 1] + 1
+            };
+            let mut y1 = x1 - k1;
+            while x1 < n && y1 < m && old[x1 as usize] == new[y1 as usize] {
+                x1 += 1;
+                y1 += 1;
+                effort += 1;
+            }
+            forward[i] = x1;
+            if x1 > n {
+                k1_end += 2;
+            } else if y1 > m {
+                k1_start += 2;
+            } else if front {
+                let j = offset + delta - k1;
+                if (0..width).contains(&j) && backward[j as usize] != -1 {
+                    let x2 = n - backward[j as usize];
+                    if x1 >= x2 {
+                        return Some((x1 as usize, y1 as usize));
+                    }
+                }
+            }
+            k1 += 2;
+        }
+
+        let mut k2 = -d + k2_start;
+        while k2 <= d - k2_end {
+            let i = (offset + k2) as usize;
+            let mut x2 = if k2 == -d || (k2 != d && backward[i - 1] < backward[i + 1]) {
+                backward[i + 1]
+            } else {
+                backward[i - 1] + 1
+            };
+            let mut y2 = x2 - k2;
+            while x2 < n
+                && y2 < m
+                && old[(n - x2 - 1) as usize] == new[(m - y2 - 1) as usize]
+            {
+                x2 += 1;
+                y2 += 1;
+                effort += 1;
+            }
+            backward[i] = x2;
+            if x2 > n {
+                k2_end += 2;
+            } else if y2 > m {
+                k2_start += 2;
+            } else if !front {
+                let j = offset + delta - k2;
+                if (0..width).contains(&j) && forward[j as usize] != -1 {
+                    let x1 = forward[j as usize];
+                    let y1 = offset + x1 - j;
+                    if x1 >= n - x2 {
+                        return Some((x1 as usize, y1 as usize));
+                    }
+                }
+            }
+            k2 += 2;
+        }
+        effort += 2 * d as usize + 2;
+        if effort > EFFORT {
+            return None;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(old: &str, new: &str) -> (String, usize) {
+        let (a, b): (Vec<char>, Vec<char>) = (old.chars().collect(), new.chars().collect());
+        let hunks = diff(&a, &b);
+        let mut out = Vec::new();
+        let mut at = 0;
+        let mut cost = 0;
+        for hunk in hunks {
+            assert!(hunk.old.start >= at);
+            out.extend_from_slice(&a[at..hunk.old.start]);
+            out.extend_from_slice(&b[hunk.new.clone()]);
+            cost += hunk.old.len() + hunk.new.len();
+            at = hunk.old.end;
+        }
+        out.extend_from_slice(&a[at..]);
+        (out.into_iter().collect(), cost)
+    }
+
+    /// Edit distance (inserts and deletes only), by dynamic programming.
+    fn distance(old: &str, new: &str) -> usize {
+        let (a, b): (Vec<char>, Vec<char>) = (old.chars().collect(), new.chars().collect());
+        let mut row: Vec<usize> = (0..=b.len()).collect();
+        for i in 1..=a.len() {
+            let mut prev = row[0];
+            row[0] = i;
+            for j in 1..=b.len() {
+                let next = if a[i - 1] == b[j - 1] {
+                    prev
+                } else {
+                    1 + row[j].min(row[j - 1])
+                };
+                prev = row[j];
+                row[j] = next;
+            }
+        }
+        row[b.len()]
+    }
+
+    #[test]
+    fn edit_scripts_are_shortest() {
+        let cases = [
+            ("", ""),
+            ("abc", "abc"),
+            ("", "abc"),
+            ("abc", ""),
+            ("abc", "xyz"),
+            ("abcabba", "cbabac"),
+            ("hello world", "hello brave new world"),
+            ("the quick brown fox", "a quick brown dog"),
+            ("aaaa", "aa"),
+            ("xaxbx", "abx"),
+        ];
+        for (old, new) in cases {
+            let (out, cost) = apply(old, new);
+            assert_eq!(out, new, "{old:?} -> {new:?}");
+            assert_eq!(cost, distance(old, new), "{old:?} -> {new:?}");
+        }
+    }
+
+    #[test]
+    fn hunks_are_joined() {
+        let a: Vec<char> = "abcdef".chars().collect();
+        let b: Vec<char> = "aXYf".chars().collect();
+        assert_eq!(diff(&a, &b), vec![Hunk { old: 1..5, new: 1..3 }]);
+    }
+}
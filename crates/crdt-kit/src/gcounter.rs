@@ -1,6 +1,7 @@
 use alloc::collections::BTreeMap;
 
-use crate::{Crdt, DeltaCrdt, NodeId};
+use crate::codec::{CodecError, Decode, Encode, Reader, Writer};
+use crate::{Crdt, DeltaCrdt, DeltaSince, NodeId, VersionVector};
 
 /// A grow-only counter (G-Counter).
 ///
@@ -44,7 +45,12 @@ impl GCounter {
     }
 
     /// Increment this replica's count by `n`.
+    ///
+    /// Incrementing by zero is a no-op and records nothing.
     pub fn increment_by(&mut self, n: u64) {
+        if n == 0 {
+            return;
+        }
         *self.counts.entry(self.actor).or_insert(0) += n;
     }
 
@@ -65,6 +71,17 @@ impl GCounter {
     pub fn count_for(&self, actor: NodeId) -> u64 {
         self.counts.get(&actor).copied().unwrap_or(0)
     }
+
+    /// Get the version vector of this counter.
+    ///
+    /// Each node's count doubles as its operation counter.
+    #[must_use]
+    pub fn version_vector(&self) -> VersionVector {
+        self.counts
+            .iter()
+            .map(|(&actor, &count)| (actor, count))
+            .collect()
+    }
 }
 
 impl Crdt for GCounter {
@@ -105,6 +122,54 @@ impl DeltaCrdt for GCounter {
     }
 }
 
+impl DeltaSince for GCounter {
+    type Summary = VersionVector;
+
+    fn summary(&self) -> VersionVector {
+        self.version_vector()
+    }
+
+    fn delta_since(&self, since: &VersionVector) -> GCounterDelta {
+        let counts = self
+            .counts
+            .iter()
+            .filter(|(&actor, &count)| count > since.get(actor))
+            .map(|(&actor, &count)| (actor, count))
+            .collect();
+        GCounterDelta { counts }
+    }
+}
+
+impl Encode for GCounter {
+    fn encode(&self, w: &mut Writer) {
+        w.write_varint(self.actor);
+        self.counts.encode(w);
+    }
+}
+
+impl Decode for GCounter {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Ok(Self {
+            actor: r.read_varint()?,
+            counts: BTreeMap::decode(r)?,
+        })
+    }
+}
+
+impl Encode for GCounterDelta {
+    fn encode(&self, w: &mut Writer) {
+        self.counts.encode(w);
+    }
+}
+
+impl Decode for GCounterDelta {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Ok(Self {
+            counts: BTreeMap::decode(r)?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -255,4 +320,25 @@ mod tests {
 
         assert_eq!(full.value(), via_delta.value());
     }
+
+    #[test]
+    fn delta_since_contains_only_unseen_entries() {
+        let mut c1 = GCounter::new(1);
+        c1.increment_by(3);
+
+        let mut c2 = c1.clone();
+        c2.increment();
+        let mut c3 = GCounter::new(3);
+        c3.increment();
+        c2.merge(&c3);
+
+        let d = c2.delta_since(&c1.version_vector());
+        assert_eq!(d, c2.delta(&c1));
+        assert_eq!(d.counts.get(&1), Some(&4));
+        assert_eq!(d.counts.get(&3), Some(&1));
+
+        c1.apply_delta(&d);
+        assert_eq!(c1.value(), c2.value());
+        assert!(c2.delta_since(&c1.version_vector()).counts.is_empty());
+    }
 }
@@ -0,0 +1,542 @@
+//! Epidemic (gossip) dissemination among many peers.
+//!
+//! [`sync`](crate::sync) reconciles one pair of replicas. A mesh of N peers
+//! instead runs a [`Gossip`] node next to each replica. Every round
+//! ([`tick`](Gossip::tick)) a node samples random peers and does a
+//! push-pull exchange with each:
+//!
+//! ```text
+//!   A (tick)                                B
+//!   |--- Hello(summary_a) ----------------->|
+//!   |<------------ Delta(b.delta_since(a)) -|   pull
+//!   |<----------------- Hello(summary_b) ---|
+//!   |--- Delta(a.delta_since(b)) ---------->|   push
+//! ```
+//!
+//! Every delta is computed against the receiver's summary, so it is always
+//! causally complete and safe to apply for any [`DeltaSince`] type.
+//!
+//! Which peers gossip how much follows rumor mongering: a node that has
+//! news — its summary changed, locally or from a peer — is *hot* and
+//! contacts [`fanout`](GossipConfig::fanout) peers per round. After
+//! [`rumor_rounds`](GossipConfig::rumor_rounds) rounds without news the
+//! rumor is retired and the node falls back to
+//! [`idle_fanout`](GossipConfig::idle_fanout) anti-entropy exchanges, which
+//! catch whatever the rumors missed. News reaches every peer within
+//! `O(log N)` rounds with high probability.
+//!
+//! Like [`SyncSession`](crate::sync::SyncSession) the node is sans-IO:
+//! feed it incoming messages with [`handle`](Gossip::handle) and drain
+//! [`poll_outgoing`](Gossip::poll_outgoing). It speaks [`SyncMessage`]s, so
+//! any [`SyncCodec`] and [`Transport`] carry it; [`flush`](Gossip::flush)
+//! and [`receive`](Gossip::receive) do the envelope plumbing.
+//!
+//! # Example
+//!
+//! ```
+//! use crdt_kit::prelude::*;
+//! use crdt_kit::gossip::{Gossip, GossipConfig};
+//!
+//! let ids = [1, 2, 3];
+//! let mut replicas: Vec<_> = ids.iter().map(|&id| GCounter::new(id)).collect();
+//! let mut nodes: Vec<Gossip<GCounter>> = ids
+//!     .iter()
+//!     .map(|&id| {
+//!         let peers = ids.iter().copied().filter(|&p| p != id);
+//!         Gossip::new(peers, GossipConfig::default(), id)
+//!     })
+//!     .collect();
+//!
+//! replicas[0].increment_by(5);
+//!
+//! for _ in 0..3 {
+//!     for i in 0..ids.len() {
+//!         nodes[i].tick(&replicas[i]);
+//!     }
+//!     // Deliver until the network is quiet.
+//!     let mut delivered = true;
+//!     while delivered {
+//!         delivered = false;
+//!         for i in 0..ids.len() {
+//!             while let Some((to, msg)) = nodes[i].poll_outgoing() {
+//!                 let j = ids.iter().position(|&id| id == to).unwrap();
+//!                 nodes[j].handle(&mut replicas[j], ids[i], msg).unwrap();
+//!                 delivered = true;
+//!             }
+//!         }
+//!     }
+//! }
+//! assert!(replicas.iter().all(|r| r.value() == 5));
+//! ```
+
+use alloc::collections::{BTreeMap, BTreeSet, VecDeque};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::sim::Rng;
+use crate::sync::{SyncCodec, SyncError, SyncMessage, SyncState};
+use crate::transport::{decode_message, encode_message, Transport, TransportError};
+use crate::version::{Versioned, VersionedEnvelope};
+use crate::{DeltaSince, NodeId};
+
+/// Tuning knobs for a [`Gossip`] node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GossipConfig {
+    /// Peers contacted per round while the node has news.
+    pub fanout: usize,
+    /// Rounds a node keeps spreading news before retiring the rumor.
+    pub rumor_rounds: u32,
+    /// Peers contacted per round once the rumor is retired. Zero leaves
+    /// idle nodes silent until a peer contacts them.
+    pub idle_fanout: usize,
+}
+
+impl Default for GossipConfig {
+    fn default() -> Self {
+        Self {
+            fanout: 3,
+            rumor_rounds: 3,
+            idle_fanout: 1,
+        }
+    }
+}
+
+/// One peer's gossip state: membership, rumor status and queued messages.
+pub struct Gossip<C: DeltaSince> {
+    peers: Vec<NodeId>,
+    config: GossipConfig,
+    /// Rounds left before the current rumor retires.
+    hot_rounds: u32,
+    /// Summary at the last tick, to notice local changes.
+    last_summary: Option<C::Summary>,
+    /// Peers we opened an exchange with this round.
+    awaiting: BTreeSet<NodeId>,
+    outgoing: VecDeque<(NodeId, SyncMessage<C>)>,
+    rng: Rng,
+    round: u64,
+}
+
+impl<C> Gossip<C>
+where
+    C: DeltaSince + Versioned,
+    C::Summary: PartialEq,
+{
+    /// Create a node that gossips with `peers`.
+    ///
+    /// `seed` drives peer sampling; give each node a different one.
+    pub fn new(peers: impl IntoIterator<Item = NodeId>, config: GossipConfig, seed: u64) -> Self {
+        let mut gossip = Self {
+            peers: Vec::new(),
+            config,
+            hot_rounds: 0,
+            last_summary: None,
+            awaiting: BTreeSet::new(),
+            outgoing: VecDeque::new(),
+            rng: Rng(seed),
+            round: 0,
+        };
+        for peer in peers {
+            gossip.add_peer(peer);
+        }
+        gossip
+    }
+
+    /// Add a peer to the membership. Duplicates are ignored.
+    pub fn add_peer(&mut self, peer: NodeId) {
+        if !self.peers.contains(&peer) {
+            self.peers.push(peer);
+        }
+    }
+
+    /// Remove a peer from the membership.
+    pub fn remove_peer(&mut self, peer: NodeId) {
+        self.peers.retain(|&p| p != peer);
+        self.awaiting.remove(&peer);
+    }
+
+    /// Current membership.
+    #[must_use]
+    pub fn peers(&self) -> &[NodeId] {
+        &self.peers
+    }
+
+    /// The node's configuration.
+    #[must_use]
+    pub fn config(&self) -> GossipConfig {
+        self.config
+    }
+
+    /// Number of rounds started so far.
+    #[must_use]
+    pub fn round(&self) -> u64 {
+        self.round
+    }
+
+    /// Check whether the node is still spreading news.
+    #[must_use]
+    pub fn is_hot(&self) -> bool {
+        self.hot_rounds > 0
+    }
+
+    /// Start spreading news that does not show in the summary.
+    pub fn touch(&mut self) {
+        self.hot_rounds = self.config.rumor_rounds;
+    }
+
+    /// Start a round: open a push-pull exchange with randomly sampled peers.
+    ///
+    /// Returns the number of peers contacted.
+    pub fn tick(&mut self, replica: &C) -> usize {
+        self.round += 1;
+        // Late replies to last round's exchanges are treated as new ones.
+        self.awaiting.clear();
+
+        let summary = replica.summary();
+        if self.last_summary.as_ref() != Some(&summary) {
+            self.touch();
+        }
+        let fanout = if self.is_hot() {
+            self.hot_rounds -= 1;
+            self.config.fanout
+        } else {
+            self.config.idle_fanout
+        };
+
+        let targets = self.sample(fanout);
+        for &peer in &targets {
+            self.outgoing
+                .push_back((peer, Self::hello(summary.clone())));
+            self.awaiting.insert(peer);
+        }
+        self.last_summary = Some(summary);
+        targets.len()
+    }
+
+    /// Handle a message from `from`.
+    ///
+    /// A `Hello` is answered with the delta the sender is missing, plus our
+    /// own `Hello` unless it answers an exchange we opened. Deltas are
+    /// applied to `replica`; any that teach us something make the node hot.
+    pub fn handle(
+        &mut self,
+        replica: &mut C,
+        from: NodeId,
+        msg: SyncMessage<C>,
+    ) -> Result<(), SyncError> {
+        match msg {
+            SyncMessage::Hello {
+                crdt_type,
+                version,
+                summary,
+            } => {
+                if crdt_type != C::CRDT_TYPE {
+                    return Err(SyncError::CrdtTypeMismatch {
+                        expected: C::CRDT_TYPE,
+                        found: crdt_type,
+                    });
+                }
+                if version != C::CURRENT_VERSION {
+                    return Err(SyncError::VersionMismatch {
+                        expected: C::CURRENT_VERSION,
+                        found: version,
+                    });
+                }
+                self.outgoing
+                    .push_back((from, SyncMessage::Delta(replica.delta_since(&summary))));
+                if !self.awaiting.remove(&from) {
+                    self.outgoing
+                        .push_back((from, Self::hello(replica.summary())));
+                }
+            }
+            SyncMessage::Delta(delta) => {
+                let before = replica.summary();
+                replica.apply_delta(&delta);
+                let after = replica.summary();
+                if after != before {
+                    self.touch();
+                    self.last_summary = Some(after);
+                }
+            }
+            SyncMessage::Ack | SyncMessage::Done => {
+                return Err(SyncError::UnexpectedMessage {
+                    state: SyncState::Done,
+                })
+            }
+        }
+        Ok(())
+    }
+
+    /// Take the next message to send, with its destination.
+    pub fn poll_outgoing(&mut self) -> Option<(NodeId, SyncMessage<C>)> {
+        self.outgoing.pop_front()
+    }
+
+    /// Send every queued message over the link to its destination.
+    /// Messages to peers without a link are dropped.
+    ///
+    /// Returns the number of envelopes sent.
+    pub fn flush<T, K>(
+        &mut self,
+        codec: &K,
+        links: &mut BTreeMap<NodeId, T>,
+    ) -> Result<usize, TransportError>
+    where
+        T: Transport,
+        K: SyncCodec<C> + ?Sized,
+    {
+        let mut sent = 0;
+        while let Some((to, msg)) = self.poll_outgoing() {
+            if let Some(transport) = links.get_mut(&to) {
+                transport.send(&encode_message(codec, &msg)?)?;
+                sent += 1;
+            }
+        }
+        Ok(sent)
+    }
+
+    /// Decode an envelope received from `from` and [`handle`](Self::handle) it.
+    pub fn receive<K: SyncCodec<C> + ?Sized>(
+        &mut self,
+        replica: &mut C,
+        from: NodeId,
+        envelope: &VersionedEnvelope,
+        codec: &K,
+    ) -> Result<(), TransportError> {
+        let msg = decode_message(codec, envelope)?;
+        Ok(self.handle(replica, from, msg)?)
+    }
+
+    fn hello(summary: C::Summary) -> SyncMessage<C> {
+        SyncMessage::Hello {
+            crdt_type: C::CRDT_TYPE,
+            version: C::CURRENT_VERSION,
+            summary,
+        }
+    }
+
+    /// Up to `k` distinct peers, uniformly at random.
+    fn sample(&mut self, k: usize) -> Vec<NodeId> {
+        let n = self.peers.len();
+        let k = k.min(n);
+        for i in 0..k {
+            let j = self.rng.range(i as u64, n as u64 - 1) as usize;
+            self.peers.swap(i, j);
+        }
+        self.peers[..k].to_vec()
+    }
+}
+
+impl<C: DeltaSince> fmt::Debug for Gossip<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Gossip")
+            .field("peers", &self.peers)
+            .field("config", &self.config)
+            .field("hot_rounds", &self.hot_rounds)
+            .field("round", &self.round)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::CrdtType;
+    use crate::{GCounter, Rga};
+
+    struct Mesh<C: DeltaSince> {
+        ids: Vec<NodeId>,
+        replicas: Vec<C>,
+        nodes: Vec<Gossip<C>>,
+    }
+
+    impl<C> Mesh<C>
+    where
+        C: DeltaSince + Versioned,
+        C::Summary: PartialEq,
+    {
+        fn new(n: u64, config: GossipConfig, replica: impl Fn(NodeId) -> C) -> Self {
+            let ids: Vec<NodeId> = (1..=n).collect();
+            let nodes = ids
+                .iter()
+                .map(|&id| Gossip::new(ids.iter().copied().filter(|&p| p != id), config, id))
+                .collect();
+            Self {
+                replicas: ids.iter().map(|&id| replica(id)).collect(),
+                ids,
+                nodes,
+            }
+        }
+
+        /// One round: every node ticks, then messages flow until quiet.
+        /// Returns the number of messages delivered.
+        fn round(&mut self) -> usize {
+            for (node, replica) in self.nodes.iter_mut().zip(&self.replicas) {
+                node.tick(replica);
+            }
+            let mut delivered = 0;
+            loop {
+                let before = delivered;
+                for i in 0..self.nodes.len() {
+                    while let Some((to, msg)) = self.nodes[i].poll_outgoing() {
+                        let j = self.ids.iter().position(|&id| id == to).unwrap();
+                        self.nodes[j]
+                            .handle(&mut self.replicas[j], self.ids[i], msg)
+                            .unwrap();
+                        delivered += 1;
+                    }
+                }
+                if delivered == before {
+                    return delivered;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn converges_within_logarithmic_rounds() {
+        let mut mesh = Mesh::new(32, GossipConfig::default(), GCounter::new);
+        for (i, r) in mesh.replicas.iter_mut().enumerate() {
+            r.increment_by(i as u64 + 1);
+        }
+        let expected: u64 = (1..=32).sum();
+
+        let mut rounds = 0;
+        while !mesh.replicas.iter().all(|r| r.value() == expected) {
+            mesh.round();
+            rounds += 1;
+            assert!(rounds <= 6, "not converged after {rounds} rounds");
+        }
+    }
+
+    #[test]
+    fn single_update_spreads_from_one_node() {
+        let config = GossipConfig {
+            idle_fanout: 0,
+            ..GossipConfig::default()
+        };
+        let mut mesh = Mesh::new(64, config, Rga::new);
+        // Settle the initial (empty) summaries.
+        mesh.round();
+        assert!(mesh.nodes.iter().all(|n| n.is_hot()));
+        for _ in 0..config.rumor_rounds {
+            mesh.round();
+        }
+        assert!(mesh.nodes.iter().all(|n| !n.is_hot()));
+        assert_eq!(mesh.round(), 0);
+
+        mesh.replicas[5].insert_at(0, 'x').unwrap();
+        let mut rounds = 0;
+        while !mesh.replicas.iter().all(|r| r.to_vec() == ['x']) {
+            mesh.round();
+            rounds += 1;
+            assert!(rounds <= 8, "not converged after {rounds} rounds");
+        }
+    }
+
+    #[test]
+    fn single_delete_spreads_from_one_node() {
+        let config = GossipConfig {
+            idle_fanout: 0,
+            ..GossipConfig::default()
+        };
+        let mut mesh = Mesh::new(8, config, Rga::new);
+        mesh.replicas[0].insert_at(0, 'x').unwrap();
+        for _ in 0..8 + config.rumor_rounds {
+            mesh.round();
+        }
+        assert!(mesh.replicas.iter().all(|r| r.len() == 1));
+        assert_eq!(mesh.round(), 0);
+
+        // Removes advance the summary, so they are rumors too.
+        mesh.replicas[5].remove(0).unwrap();
+        let mut rounds = 0;
+        while !mesh.replicas.iter().all(|r| r.is_empty()) {
+            mesh.round();
+            rounds += 1;
+            assert!(rounds <= 8, "not converged after {rounds} rounds");
+        }
+    }
+
+    #[test]
+    fn rumors_retire_to_idle_fanout() {
+        let counter = GCounter::new(1);
+        let mut node = Gossip::<GCounter>::new(2..10, GossipConfig::default(), 7);
+        // The first summary counts as news.
+        for _ in 0..3 {
+            assert_eq!(node.tick(&counter), 3);
+        }
+        assert!(!node.is_hot());
+        assert_eq!(node.tick(&counter), 1);
+
+        node.touch();
+        assert_eq!(node.tick(&counter), 3);
+    }
+
+    #[test]
+    fn fanout_samples_distinct_peers() {
+        let counter = GCounter::new(1);
+        let mut node = Gossip::<GCounter>::new(2..10, GossipConfig::default(), 7);
+        node.tick(&counter);
+        let targets: BTreeSet<_> = core::iter::from_fn(|| node.poll_outgoing())
+            .map(|(to, _)| to)
+            .collect();
+        assert_eq!(targets.len(), 3);
+        assert!(targets.iter().all(|t| (2..10).contains(t)));
+
+        let mut lonely = Gossip::<GCounter>::new([], GossipConfig::default(), 7);
+        assert_eq!(lonely.tick(&counter), 0);
+        assert!(lonely.poll_outgoing().is_none());
+    }
+
+    #[test]
+    fn exchange_is_push_pull() {
+        let mut a = GCounter::new(1);
+        a.increment_by(2);
+        let mut b = GCounter::new(2);
+        b.increment_by(3);
+        let mut ga = Gossip::<GCounter>::new([2], GossipConfig::default(), 1);
+        let mut gb = Gossip::<GCounter>::new([1], GossipConfig::default(), 2);
+
+        ga.tick(&a);
+        let mut messages = 0;
+        loop {
+            let mut quiet = true;
+            while let Some((_, msg)) = ga.poll_outgoing() {
+                gb.handle(&mut b, 1, msg).unwrap();
+                messages += 1;
+                quiet = false;
+            }
+            while let Some((_, msg)) = gb.poll_outgoing() {
+                ga.handle(&mut a, 2, msg).unwrap();
+                messages += 1;
+                quiet = false;
+            }
+            if quiet {
+                break;
+            }
+        }
+        assert_eq!(messages, 4);
+        assert_eq!(a.value(), 5);
+        assert_eq!(b.value(), 5);
+    }
+
+    #[test]
+    fn rejects_other_crdt_types() {
+        let mut counter = GCounter::new(1);
+        let mut node = Gossip::<GCounter>::new([2], GossipConfig::default(), 1);
+        let hello = SyncMessage::Hello {
+            crdt_type: CrdtType::PNCounter,
+            version: GCounter::CURRENT_VERSION,
+            summary: counter.summary(),
+        };
+        assert_eq!(
+            node.handle(&mut counter, 2, hello),
+            Err(SyncError::CrdtTypeMismatch {
+                expected: CrdtType::GCounter,
+                found: CrdtType::PNCounter,
+            })
+        );
+        assert!(node.poll_outgoing().is_none());
+    }
+}
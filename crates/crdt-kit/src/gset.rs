@@ -1,5 +1,9 @@
 use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use core::hash::Hash;
 
+use crate::codec::{CodecError, Decode, Encode, Reader, Writer};
+use crate::merkle::{in_ranges, stable_hash, KeyRange, Reconcilable, KEY_DOMAIN};
 use crate::{Crdt, DeltaCrdt};
 
 /// A grow-only set (G-Set).
@@ -107,6 +111,29 @@ impl<T: Ord + Clone> DeltaCrdt for GSet<T> {
     }
 }
 
+impl<T: Ord + Clone + Hash> Reconcilable for GSet<T> {
+    fn digest_entries(&self) -> Vec<(u64, u64)> {
+        self.elements
+            .iter()
+            .map(|e| {
+                let h = stable_hash(KEY_DOMAIN, e);
+                (h, h)
+            })
+            .collect()
+    }
+
+    fn delta_for(&self, ranges: &[KeyRange]) -> GSetDelta<T> {
+        GSetDelta {
+            elements: self
+                .elements
+                .iter()
+                .filter(|e| in_ranges(ranges, stable_hash(KEY_DOMAIN, *e)))
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
 impl<T: Ord + Clone> IntoIterator for GSet<T> {
     type Item = T;
     type IntoIter = alloc::collections::btree_set::IntoIter<T>;
@@ -124,6 +151,34 @@ impl<T: Ord + Clone> FromIterator<T> for GSet<T> {
     }
 }
 
+impl<T: Ord + Clone + Encode> Encode for GSet<T> {
+    fn encode(&self, w: &mut Writer) {
+        self.elements.encode(w);
+    }
+}
+
+impl<T: Ord + Clone + Decode> Decode for GSet<T> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Ok(Self {
+            elements: BTreeSet::decode(r)?,
+        })
+    }
+}
+
+impl<T: Ord + Clone + Encode> Encode for GSetDelta<T> {
+    fn encode(&self, w: &mut Writer) {
+        self.elements.encode(w);
+    }
+}
+
+impl<T: Ord + Clone + Decode> Decode for GSetDelta<T> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Ok(Self {
+            elements: BTreeSet::decode(r)?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
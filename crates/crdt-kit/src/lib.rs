@@ -54,12 +54,17 @@
 //!
 //! ### Sequences
 //! - [`Rga`] - Replicated Growable Array (ordered sequence)
-//! - [`TextCrdt`] - Collaborative text (thin wrapper over `Rga<char>`)
+//! - [`TextCrdt`] - Collaborative text (an `Rga` of characters stored as `String` runs)
+//! - [`RichText`] - Collaborative text with formatting marks (bold, links, …)
 //!
 //! ## The `Crdt` Trait
 //!
 //! All types implement the [`Crdt`] trait, which provides the [`Crdt::merge`]
 //! method. Merge is guaranteed to be commutative, associative, and idempotent.
+//!
+//! Types that can compute deltas from a compact causal summary (a
+//! [`VersionVector`], or an HLC watermark for [`LWWMap`]) also implement
+//! [`DeltaSince`], so a peer never needs a copy of the other side's state.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 #![warn(missing_docs)]
@@ -68,6 +73,7 @@ extern crate alloc;
 
 mod aw_map;
 mod crdt;
+pub(crate) mod diff;
 pub(crate) mod rope;
 mod gcounter;
 mod gset;
@@ -78,29 +84,42 @@ mod or_set;
 mod pncounter;
 /// Replicated Growable Array (RGA) — ordered sequence CRDT.
 pub mod rga;
+mod rich_text;
 mod text;
 mod twop_set;
+mod version_vector;
 /// Versioned serialization and envelope format.
 pub mod version;
 #[cfg(feature = "wasm")]
 mod wasm;
 
+pub mod causal;
 pub mod clock;
+pub mod codec;
+pub mod gossip;
+pub mod merkle;
 pub mod prelude;
+pub mod sim;
+pub mod sync;
+pub mod transport;
+pub mod undo;
 
 pub use aw_map::{AWMap, AWMapDelta};
-pub use crdt::{Crdt, DeltaCrdt, NodeId};
+pub use crdt::{Crdt, DeltaCrdt, DeltaSince, NodeId};
 pub use gcounter::{GCounter, GCounterDelta};
 pub use lww_map::{LWWMap, LWWMapDelta};
 pub use gset::{GSet, GSetDelta};
 pub use lww_register::{LWWRegister, LWWRegisterDelta};
 pub use mv_register::{MVRegister, MVRegisterDelta};
 pub use or_set::{ORSet, ORSetDelta};
-pub use pncounter::{PNCounter, PNCounterDelta};
-pub use rga::{Rga, RgaDelta, RgaError, RgaNode};
+pub use pncounter::{PNCounter, PNCounterDelta, PNCounterSummary};
+pub use rga::{Anchor, BlameRun, Gravity, Rga, RgaDelta, RgaError, RgaNode, Run, Splice};
+pub use rich_text::{Expand, Format, FormattedRun, MarkOp, RichText, RichTextDelta};
 pub use text::{TextCrdt, TextDelta, TextError};
 pub use twop_set::{TwoPSet, TwoPSetDelta};
+pub use version_vector::VersionVector;
 pub use version::{
-    CrdtType, EnvelopeError, VersionError, Versioned, VersionedEnvelope, ENVELOPE_HEADER_SIZE,
-    MAGIC_BYTE,
+    CrdtType, EnvelopeError, EnvelopeFlags, VersionError, Versioned, VersionedEnvelope,
+    ENVELOPE_CHECKSUM_SIZE, ENVELOPE_HEADER_SIZE, ENVELOPE_V2_HEADER_SIZE, MAGIC_BYTE,
+    MAGIC_BYTE_V2,
 };
@@ -1,8 +1,11 @@
 use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
+use core::hash::Hash;
 
-use crate::clock::HybridTimestamp;
-use crate::{Crdt, DeltaCrdt};
+use crate::clock::{HlcWatermark, HybridTimestamp};
+use crate::codec::{CodecError, Decode, Encode, Reader, Writer};
+use crate::merkle::{in_ranges, stable_hash, KeyRange, Reconcilable, KEY_DOMAIN};
+use crate::{Crdt, DeltaCrdt, DeltaSince};
 
 /// A last-writer-wins map (LWW-Map).
 ///
@@ -141,7 +144,16 @@ impl<K: Ord + Clone, V: Clone> LWWMap<K, V> {
     pub fn values(&self) -> impl Iterator<Item = &V> {
         self.iter().map(|(_, v)| v)
     }
-}
+
+    /// Get the HLC watermark of this map: the latest timestamp observed
+    /// from each node, across live entries and tombstones.
+    ///
+    /// Assumes each node stamps its writes with a monotonic clock such as
+    /// [`HybridClock`](crate::clock::HybridClock).
+    #[must_use]
+    pub fn watermark(&self) -> HlcWatermark {
+        self.entries.values().map(|e| e.timestamp).collect()
+    }
 
     /// Returns the number of tombstoned (removed) entries.
     #[must_use]
@@ -256,6 +268,110 @@ impl<K: Ord + Clone, V: Clone> DeltaCrdt for LWWMap<K, V> {
     }
 }
 
+impl<K: Ord + Clone, V: Clone> DeltaSince for LWWMap<K, V> {
+    type Summary = HlcWatermark;
+
+    fn summary(&self) -> HlcWatermark {
+        self.watermark()
+    }
+
+    fn delta_since(&self, since: &HlcWatermark) -> LWWMapDelta<K, V> {
+        let entries = self
+            .entries
+            .iter()
+            .filter(|(_, e)| !since.covers(&e.timestamp))
+            .map(|(key, e)| (key.clone(), e.value.clone(), e.timestamp, e.alive))
+            .collect();
+        LWWMapDelta { entries }
+    }
+}
+
+/// The timestamp identifies the write, so values need not be hashable.
+impl<K: Ord + Clone + Hash, V: Clone> Reconcilable for LWWMap<K, V> {
+    fn digest_entries(&self) -> Vec<(u64, u64)> {
+        self.entries
+            .iter()
+            .map(|(key, e)| {
+                (
+                    stable_hash(KEY_DOMAIN, key),
+                    stable_hash(KEY_DOMAIN, &(key, e.timestamp, e.alive)),
+                )
+            })
+            .collect()
+    }
+
+    fn delta_for(&self, ranges: &[KeyRange]) -> LWWMapDelta<K, V> {
+        let entries = self
+            .entries
+            .iter()
+            .filter(|(key, _)| in_ranges(ranges, stable_hash(KEY_DOMAIN, *key)))
+            .map(|(key, e)| (key.clone(), e.value.clone(), e.timestamp, e.alive))
+            .collect();
+        LWWMapDelta { entries }
+    }
+}
+
+impl<V: Clone + Encode> Encode for Entry<V> {
+    fn encode(&self, w: &mut Writer) {
+        self.value.encode(w);
+        self.timestamp.encode(w);
+        self.alive.encode(w);
+    }
+}
+
+impl<V: Clone + Decode> Decode for Entry<V> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Ok(Self {
+            value: Option::decode(r)?,
+            timestamp: HybridTimestamp::decode(r)?,
+            alive: bool::decode(r)?,
+        })
+    }
+}
+
+impl<K: Ord + Clone + Encode, V: Clone + Encode> Encode for LWWMap<K, V> {
+    fn encode(&self, w: &mut Writer) {
+        self.entries.encode(w);
+    }
+}
+
+impl<K: Ord + Clone + Decode, V: Clone + Decode> Decode for LWWMap<K, V> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Ok(Self {
+            entries: BTreeMap::decode(r)?,
+        })
+    }
+}
+
+impl<K: Ord + Clone + Encode, V: Clone + Encode> Encode for LWWMapDelta<K, V> {
+    fn encode(&self, w: &mut Writer) {
+        w.write_len(self.entries.len());
+        for (key, value, timestamp, alive) in &self.entries {
+            key.encode(w);
+            value.encode(w);
+            timestamp.encode(w);
+            alive.encode(w);
+        }
+    }
+}
+
+impl<K: Ord + Clone + Decode, V: Clone + Decode> Decode for LWWMapDelta<K, V> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        let len = r.read_len()?;
+        let entries = (0..len)
+            .map(|_| {
+                Ok((
+                    K::decode(r)?,
+                    Option::decode(r)?,
+                    HybridTimestamp::decode(r)?,
+                    bool::decode(r)?,
+                ))
+            })
+            .collect::<Result<_, CodecError>>()?;
+        Ok(Self { entries })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -425,4 +541,68 @@ mod tests {
         let keys: Vec<_> = m.keys().collect();
         assert_eq!(keys, vec![&"a", &"c"]);
     }
+
+    #[test]
+    fn watermark_tracks_latest_write_per_node() {
+        let mut m = LWWMap::new();
+        m.insert("a", 1, ts(1, 1));
+        m.insert("b", 2, ts(5, 1));
+        m.remove(&"a", ts(3, 2));
+        let wm = m.watermark();
+        assert_eq!(wm.get(1), Some(ts(5, 1)));
+        assert_eq!(wm.get(2), Some(ts(3, 2)));
+    }
+
+    #[test]
+    fn delta_since_equivalent_to_merge() {
+        let mut m1 = LWWMap::new();
+        m1.insert("a", 1, ts(1, 1));
+        m1.insert("b", 2, ts(2, 1));
+
+        let mut m2 = m1.clone();
+        m2.insert("c", 3, ts(3, 2));
+
+        m1.insert("a", 10, ts(4, 1));
+        m1.remove(&"b", ts(5, 1));
+
+        let d = m1.delta_since(&m2.watermark());
+        assert_eq!(d.entries.len(), 2);
+
+        let mut via_merge = m2.clone();
+        via_merge.merge(&m1);
+        m2.apply_delta(&d);
+        assert_eq!(m2, via_merge);
+        assert!(m1.delta_since(&m2.watermark()).entries.is_empty());
+    }
+
+    #[test]
+    fn reconcile_sends_only_differing_ranges() {
+        use crate::merkle::reconcile_in_memory;
+
+        let mut m1 = LWWMap::new();
+        for k in 0..20_000u32 {
+            m1.insert(k, k, ts(1, 1));
+        }
+        let mut m2 = m1.clone();
+        m1.insert(7, 70, ts(2, 1));
+        m2.remove(&9_000, ts(2, 2));
+
+        let mut r1 = crate::merkle::Reconciler::new(&m1);
+        let mut r2 = crate::merkle::Reconciler::new(&m2);
+        let mut step = r1.start();
+        while let Some(reply) = r2.handle(step) {
+            match r1.handle(reply) {
+                Some(next) => step = next,
+                None => break,
+            }
+        }
+        let ranges = r1.differing();
+        assert!(m1.delta_for(&ranges).entries.len() < 100);
+
+        let mut via_merge = m1.clone();
+        via_merge.merge(&m2);
+        reconcile_in_memory(&mut m1, &mut m2);
+        assert_eq!(m1, via_merge);
+        assert_eq!(m2, via_merge);
+    }
 }
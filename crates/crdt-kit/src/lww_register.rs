@@ -1,4 +1,5 @@
 use crate::clock::{HybridClock, HybridTimestamp};
+use crate::codec::{CodecError, Decode, Encode, Reader, Writer};
 use crate::{Crdt, DeltaCrdt};
 
 /// A last-writer-wins register (LWW-Register).
@@ -130,6 +131,36 @@ impl<T: Clone> Crdt for LWWRegister<T> {
     }
 }
 
+impl<T: Clone + Encode> Encode for LWWRegister<T> {
+    fn encode(&self, w: &mut Writer) {
+        self.value.encode(w);
+        self.timestamp.encode(w);
+    }
+}
+
+impl<T: Clone + Decode> Decode for LWWRegister<T> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Ok(Self {
+            value: T::decode(r)?,
+            timestamp: HybridTimestamp::decode(r)?,
+        })
+    }
+}
+
+impl<T: Clone + Encode> Encode for LWWRegisterDelta<T> {
+    fn encode(&self, w: &mut Writer) {
+        self.update.encode(w);
+    }
+}
+
+impl<T: Clone + Decode> Decode for LWWRegisterDelta<T> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Ok(Self {
+            update: Option::decode(r)?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,489 @@
+//! Hash-tree range reconciliation for large key-value replicas.
+//!
+//! Comparing two 100k-key replicas entry by entry costs a full state
+//! transfer. Instead, every entry is placed in a fixed 16-ary tree by the
+//! hash of its key, and each tree node summarizes its range with a
+//! [`RangeDigest`] (entry count plus an order-independent hash). Two peers
+//! that exchange digests top-down only descend into ranges that differ, so
+//! they find the differing ranges in `O(log n)` round trips and then
+//! exchange deltas for those ranges alone.
+//!
+//! The protocol is sans-IO, like [`sync`](crate::sync): each side runs a
+//! [`Reconciler`], feeding it the peer's [`ReconcileStep`]s.
+//!
+//! ```text
+//!   A                                        B
+//!   |--- Step { digests: [root] } ---------->|
+//!   |<---------- Step { child digests } -----|   only where root differs
+//!   |--- Step { grandchild digests, ... } -->|
+//!   |            ...                         |
+//!   |<---------- Step { digests: [] } -------|   both know the differing ranges
+//!   |--- delta_for(differing) -------------->|
+//!   |<-------------- delta_for(differing) ---|
+//! ```
+//!
+//! Implemented for [`LWWMap`](crate::LWWMap), [`AWMap`](crate::AWMap),
+//! [`ORSet`](crate::ORSet) and [`GSet`](crate::GSet). Tombstones of the
+//! tag-based types are entries of their own, keyed by tag, so removes are
+//! reconciled too.
+//!
+//! # Example
+//!
+//! ```
+//! use crdt_kit::prelude::*;
+//! use crdt_kit::merkle::reconcile_in_memory;
+//!
+//! let mut a: GSet<u32> = (0..10_000).collect();
+//! let mut b = a.clone();
+//! a.insert(10_000);
+//! b.insert(20_000);
+//!
+//! let messages = reconcile_in_memory(&mut a, &mut b);
+//! assert!(messages < 10);
+//! assert_eq!(a, b);
+//! ```
+
+use alloc::vec::Vec;
+use core::hash::{Hash, Hasher};
+
+use crate::DeltaCrdt;
+
+/// [`stable_hash`] domain for keys and elements.
+pub(crate) const KEY_DOMAIN: u8 = 0;
+
+/// [`stable_hash`] domain for tombstone tags.
+pub(crate) const TOMBSTONE_DOMAIN: u8 = 1;
+
+/// Bits of the key hash consumed per tree level.
+const FANOUT_BITS: u32 = 4;
+
+/// Children per tree node.
+pub const FANOUT: usize = 1 << FANOUT_BITS;
+
+/// Depth at which a range covers a single key hash.
+pub const MAX_DEPTH: u8 = (64 / FANOUT_BITS) as u8;
+
+/// Ranges holding at most this many entries (on both sides together) are
+/// exchanged directly instead of being split further.
+pub const LEAF_SIZE: u64 = 16;
+
+/// A CRDT whose state can be compared and transferred range by range.
+pub trait Reconcilable: DeltaCrdt {
+    /// One `(key_hash, entry_hash)` pair per entry, in any order.
+    ///
+    /// `key_hash` places the entry in the tree and must depend only on the
+    /// entry's key. `entry_hash` must change whenever the entry's state
+    /// does. Use [`stable_hash`] for both so that peers on different
+    /// platforms agree.
+    fn digest_entries(&self) -> Vec<(u64, u64)>;
+
+    /// A delta carrying every entry whose key hash lies in one of `ranges`.
+    fn delta_for(&self, ranges: &[KeyRange]) -> Self::Delta;
+}
+
+/// A platform-independent 64-bit hash of `value`.
+///
+/// `domain` separates different kinds of entries (for example elements and
+/// tombstones) that may share a type. Integers are hashed in little-endian
+/// order and `usize` as `u64`, so 32- and 64-bit peers agree.
+#[must_use]
+pub fn stable_hash<H: Hash + ?Sized>(domain: u8, value: &H) -> u64 {
+    let mut hasher = StableHasher::new();
+    hasher.write_u8(domain);
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// FNV-1a with a final avalanche step so the top bits, which select tree
+/// branches, are well mixed.
+struct StableHasher(u64);
+
+impl StableHasher {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= u64::from(b);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn write_u16(&mut self, n: u16) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, n: u128) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        let mut z = self.0;
+        z = (z ^ (z >> 33)).wrapping_mul(0xff51_afd7_ed55_8ccd);
+        z = (z ^ (z >> 33)).wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+        z ^ (z >> 33)
+    }
+}
+
+/// A node of the hash tree: all key hashes sharing the top `depth` nibbles
+/// of `prefix`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeyRange {
+    prefix: u64,
+    depth: u8,
+}
+
+impl KeyRange {
+    /// The whole key space.
+    pub const ROOT: Self = Self {
+        prefix: 0,
+        depth: 0,
+    };
+
+    /// Depth of this range in the tree (0 for the root).
+    #[must_use]
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    /// Smallest key hash in the range.
+    #[must_use]
+    pub fn start(&self) -> u64 {
+        self.prefix
+    }
+
+    /// Largest key hash in the range.
+    #[must_use]
+    pub fn end(&self) -> u64 {
+        self.prefix | !self.mask()
+    }
+
+    /// Check whether `key_hash` falls in this range.
+    #[must_use]
+    pub fn contains(&self, key_hash: u64) -> bool {
+        key_hash & self.mask() == self.prefix
+    }
+
+    /// The [`FANOUT`] sub-ranges, or none at [`MAX_DEPTH`].
+    pub fn children(&self) -> impl Iterator<Item = KeyRange> {
+        let depth = self.depth + 1;
+        let shift = 64u32.saturating_sub(u32::from(depth) * FANOUT_BITS);
+        let prefix = self.prefix;
+        let count = if self.depth < MAX_DEPTH { FANOUT } else { 0 };
+        (0..count as u64).map(move |i| KeyRange {
+            prefix: prefix | (i << shift),
+            depth,
+        })
+    }
+
+    fn mask(&self) -> u64 {
+        match u32::from(self.depth) * FANOUT_BITS {
+            0 => 0,
+            bits => !0u64 << (64 - bits),
+        }
+    }
+}
+
+/// Check whether `key_hash` falls in any of `ranges`.
+#[must_use]
+pub fn in_ranges(ranges: &[KeyRange], key_hash: u64) -> bool {
+    ranges.iter().any(|r| r.contains(key_hash))
+}
+
+/// Summary of the entries in one [`KeyRange`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RangeDigest {
+    /// The range summarized.
+    pub range: KeyRange,
+    /// Number of entries in the range.
+    pub count: u64,
+    /// Wrapping sum of the entries' hashes.
+    pub hash: u64,
+}
+
+impl RangeDigest {
+    fn matches(&self, other: &Self) -> bool {
+        self.count == other.count && self.hash == other.hash
+    }
+}
+
+/// Entry hashes sorted by key hash, with prefix sums for `O(log n)` range
+/// digests.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HashTree {
+    keys: Vec<u64>,
+    sums: Vec<u64>,
+}
+
+impl HashTree {
+    /// Build the tree for a replica.
+    pub fn new<C: Reconcilable + ?Sized>(replica: &C) -> Self {
+        let mut entries = replica.digest_entries();
+        entries.sort_unstable();
+        let mut sums = Vec::with_capacity(entries.len() + 1);
+        let mut acc = 0u64;
+        sums.push(acc);
+        for &(_, h) in &entries {
+            acc = acc.wrapping_add(h);
+            sums.push(acc);
+        }
+        Self {
+            keys: entries.into_iter().map(|(k, _)| k).collect(),
+            sums,
+        }
+    }
+
+    /// Number of entries.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Check whether the tree has no entries.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Digest of the whole replica.
+    #[must_use]
+    pub fn root(&self) -> RangeDigest {
+        self.digest(KeyRange::ROOT)
+    }
+
+    /// Digest of the entries in `range`.
+    #[must_use]
+    pub fn digest(&self, range: KeyRange) -> RangeDigest {
+        let lo = self.keys.partition_point(|&k| k < range.start());
+        let hi = self.keys.partition_point(|&k| k <= range.end());
+        RangeDigest {
+            range,
+            count: (hi - lo) as u64,
+            hash: self.sums[hi].wrapping_sub(self.sums[lo]),
+        }
+    }
+}
+
+/// One message of the reconciliation protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReconcileStep {
+    /// Digests the peer should compare against its own tree.
+    pub digests: Vec<RangeDigest>,
+    /// Ranges the sender found to differ and will not split further.
+    pub differing: Vec<KeyRange>,
+}
+
+/// One side of a hash-tree reconciliation.
+///
+/// Once [`is_done`](Self::is_done), both sides hold the same
+/// [`differing`](Self::differing) ranges; each then sends
+/// [`Reconcilable::delta_for`] those ranges to the other.
+#[derive(Debug, Clone)]
+pub struct Reconciler {
+    tree: HashTree,
+    differing: Vec<KeyRange>,
+    done: bool,
+}
+
+impl Reconciler {
+    /// Snapshot `replica` into a hash tree.
+    pub fn new<C: Reconcilable + ?Sized>(replica: &C) -> Self {
+        Self {
+            tree: HashTree::new(replica),
+            differing: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// Opening message: the root digest.
+    pub fn start(&mut self) -> ReconcileStep {
+        ReconcileStep {
+            digests: alloc::vec![self.tree.root()],
+            differing: Vec::new(),
+        }
+    }
+
+    /// Handle the peer's step and produce the reply, if one is needed.
+    pub fn handle(&mut self, step: ReconcileStep) -> Option<ReconcileStep> {
+        self.differing.extend_from_slice(&step.differing);
+        if step.digests.is_empty() {
+            self.done = true;
+            return None;
+        }
+
+        let mut reply = ReconcileStep {
+            digests: Vec::new(),
+            differing: Vec::new(),
+        };
+        for theirs in step.digests {
+            let ours = self.tree.digest(theirs.range);
+            if ours.matches(&theirs) {
+                continue;
+            }
+            if theirs.range.depth() == MAX_DEPTH || ours.count + theirs.count <= LEAF_SIZE {
+                reply.differing.push(theirs.range);
+            } else {
+                reply
+                    .digests
+                    .extend(theirs.range.children().map(|r| self.tree.digest(r)));
+            }
+        }
+        self.differing.extend_from_slice(&reply.differing);
+        self.done = reply.digests.is_empty();
+        Some(reply)
+    }
+
+    /// Check whether the differing ranges are fully known.
+    #[must_use]
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Ranges whose entries must be exchanged, sorted.
+    #[must_use]
+    pub fn differing(&self) -> Vec<KeyRange> {
+        let mut ranges = self.differing.clone();
+        ranges.sort_unstable();
+        ranges.dedup();
+        ranges
+    }
+}
+
+/// Reconcile two in-process replicas and exchange the differing ranges.
+///
+/// Returns the number of protocol steps exchanged before the deltas.
+pub fn reconcile_in_memory<C: Reconcilable>(a: &mut C, b: &mut C) -> usize {
+    let mut ra = Reconciler::new(a);
+    let mut rb = Reconciler::new(b);
+
+    let mut messages = 1;
+    let mut next = Some(ra.start());
+    let mut to_b = true;
+    while let Some(step) = next {
+        next = if to_b {
+            rb.handle(step)
+        } else {
+            ra.handle(step)
+        };
+        messages += usize::from(next.is_some());
+        to_b = !to_b;
+    }
+
+    let ranges = ra.differing();
+    debug_assert_eq!(ranges, rb.differing());
+    if !ranges.is_empty() {
+        let to_b = a.delta_for(&ranges);
+        let to_a = b.delta_for(&ranges);
+        b.apply_delta(&to_b);
+        a.apply_delta(&to_a);
+    }
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GSet;
+
+    #[test]
+    fn stable_hash_ignores_usize_width() {
+        assert_eq!(stable_hash(0, &5usize), stable_hash(0, &5u64));
+        assert_ne!(stable_hash(0, &5u64), stable_hash(1, &5u64));
+    }
+
+    #[test]
+    fn key_range_geometry() {
+        let root = KeyRange::ROOT;
+        assert_eq!((root.start(), root.end()), (0, u64::MAX));
+        let children: Vec<_> = root.children().collect();
+        assert_eq!(children.len(), FANOUT);
+        assert_eq!(children[1].start(), 1 << 60);
+        assert_eq!(children[1].end(), (2 << 60) - 1);
+        assert!(children[15].contains(u64::MAX));
+
+        let mut leaf = root;
+        for _ in 0..MAX_DEPTH {
+            leaf = leaf.children().last().unwrap();
+        }
+        assert_eq!((leaf.start(), leaf.end()), (u64::MAX, u64::MAX));
+        assert_eq!(leaf.children().count(), 0);
+    }
+
+    #[test]
+    fn digests_partition_the_root() {
+        let set: GSet<u32> = (0..1000).collect();
+        let tree = HashTree::new(&set);
+        let children: Vec<_> = KeyRange::ROOT.children().map(|r| tree.digest(r)).collect();
+        let count: u64 = children.iter().map(|d| d.count).sum();
+        let hash = children
+            .iter()
+            .fold(0u64, |acc, d| acc.wrapping_add(d.hash));
+        assert_eq!(count, 1000);
+        assert_eq!(hash, tree.root().hash);
+    }
+
+    #[test]
+    fn identical_replicas_need_one_round_trip() {
+        let mut a: GSet<u32> = (0..500).collect();
+        let mut b = a.clone();
+        assert_eq!(reconcile_in_memory(&mut a, &mut b), 2);
+    }
+
+    #[test]
+    fn few_differences_in_large_sets() {
+        let mut a: GSet<u32> = (0..50_000).collect();
+        let mut b = a.clone();
+        a.insert(1_000_000);
+        b.insert(2_000_000);
+        b.insert(3_000_000);
+
+        let mut ra = Reconciler::new(&a);
+        let mut rb = Reconciler::new(&b);
+        let mut step = Some(ra.start());
+        let mut messages = 0;
+        let mut to_b = true;
+        while let Some(s) = step {
+            messages += 1;
+            step = if to_b { rb.handle(s) } else { ra.handle(s) };
+            to_b = !to_b;
+        }
+        assert!(ra.is_done() && rb.is_done());
+        // 50k entries split to leaf size within four levels.
+        assert!(messages <= 7, "took {messages} messages");
+
+        let ranges = ra.differing();
+        assert_eq!(ranges, rb.differing());
+        let delta = a.delta_for(&ranges);
+        assert!(delta.elements.len() < 100);
+        assert!(delta.elements.contains(&1_000_000));
+        b.apply_delta(&delta);
+        a.apply_delta(&b.delta_for(&ranges));
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn empty_against_full() {
+        let mut a: GSet<u32> = (0..300).collect();
+        let mut b = GSet::new();
+        reconcile_in_memory(&mut a, &mut b);
+        assert_eq!(b.len(), 300);
+    }
+}
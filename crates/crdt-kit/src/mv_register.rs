@@ -1,7 +1,8 @@
 use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
 
-use crate::{Crdt, DeltaCrdt, NodeId};
+use crate::codec::{ActorTable, CodecError, Decode, Encode, Reader, Writer};
+use crate::{Crdt, DeltaCrdt, DeltaSince, NodeId, VersionVector};
 
 /// A multi-value register (MV-Register).
 ///
@@ -79,6 +80,12 @@ impl<T: Clone + Ord> MVRegister<T> {
     pub fn actor(&self) -> NodeId {
         self.actor
     }
+
+    /// Get the version vector of this register.
+    #[must_use]
+    pub fn version_vector(&self) -> VersionVector {
+        VersionVector::from(self.version.clone())
+    }
 }
 
 /// Check if version `a` dominates (is strictly greater than or equal to) version `b`.
@@ -175,6 +182,113 @@ impl<T: Clone + Ord> Crdt for MVRegister<T> {
     }
 }
 
+impl<T: Clone + Ord> DeltaSince for MVRegister<T> {
+    type Summary = VersionVector;
+
+    fn summary(&self) -> VersionVector {
+        self.version_vector()
+    }
+
+    fn delta_since(&self, since: &VersionVector) -> MVRegisterDelta<T> {
+        let entries: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|entry| {
+                !entry
+                    .1
+                    .iter()
+                    .all(|(&actor, &count)| since.contains(actor, count))
+            })
+            .cloned()
+            .collect();
+
+        MVRegisterDelta {
+            entries,
+            version: self.version.clone(),
+        }
+    }
+}
+
+/// Written values, each with the version vector at the time of the write.
+type Entries<T> = Vec<(T, BTreeMap<NodeId, u64>)>;
+
+fn entries_actors<T>(
+    entries: &[(T, BTreeMap<NodeId, u64>)],
+) -> impl Iterator<Item = NodeId> + '_ {
+    entries.iter().flat_map(|(_, v)| v.keys().copied())
+}
+
+fn write_entries<T: Encode>(
+    w: &mut Writer,
+    table: &ActorTable,
+    entries: &[(T, BTreeMap<NodeId, u64>)],
+) {
+    w.write_len(entries.len());
+    for (value, version) in entries {
+        value.encode(w);
+        table.write_counters(w, version);
+    }
+}
+
+fn read_entries<T: Decode>(
+    r: &mut Reader<'_>,
+    table: &ActorTable,
+) -> Result<Entries<T>, CodecError> {
+    let len = r.read_len()?;
+    (0..len)
+        .map(|_| Ok((T::decode(r)?, table.read_counters(r)?)))
+        .collect()
+}
+
+impl<T: Clone + Ord + Encode> Encode for MVRegister<T> {
+    fn encode(&self, w: &mut Writer) {
+        let table = ActorTable::new(
+            core::iter::once(self.actor)
+                .chain(self.version.keys().copied())
+                .chain(entries_actors(&self.entries)),
+        );
+        table.encode(w);
+        table.write_actor(w, self.actor);
+        table.write_counters(w, &self.version);
+        write_entries(w, &table, &self.entries);
+    }
+}
+
+impl<T: Clone + Ord + Decode> Decode for MVRegister<T> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        let table = ActorTable::decode(r)?;
+        Ok(Self {
+            actor: table.read_actor(r)?,
+            version: table.read_counters(r)?,
+            entries: read_entries(r, &table)?,
+        })
+    }
+}
+
+impl<T: Clone + Ord + Encode> Encode for MVRegisterDelta<T> {
+    fn encode(&self, w: &mut Writer) {
+        let table = ActorTable::new(
+            self.version
+                .keys()
+                .copied()
+                .chain(entries_actors(&self.entries)),
+        );
+        table.encode(w);
+        write_entries(w, &table, &self.entries);
+        table.write_counters(w, &self.version);
+    }
+}
+
+impl<T: Clone + Ord + Decode> Decode for MVRegisterDelta<T> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        let table = ActorTable::decode(r)?;
+        Ok(Self {
+            entries: read_entries(r, &table)?,
+            version: table.read_counters(r)?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -315,4 +429,23 @@ mod tests {
         assert_eq!(r1.values(), vec![&"second"]);
         assert!(!r1.is_conflicted());
     }
+
+    #[test]
+    fn delta_since_matches_delta() {
+        let mut r1 = MVRegister::new(1);
+        r1.set("first");
+
+        let mut r2 = r1.clone();
+        let mut r3 = MVRegister::new(3);
+        r3.set("other");
+        r2.merge(&r3);
+        r1.set("second");
+
+        let d = r1.delta_since(&r2.version_vector());
+        assert_eq!(d, r1.delta(&r2));
+
+        r2.apply_delta(&d);
+        assert_eq!(r2.values(), vec![&"other", &"second"]);
+        assert!(r1.delta_since(&r1.version_vector()).entries.is_empty());
+    }
 }
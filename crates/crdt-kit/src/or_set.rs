@@ -1,7 +1,10 @@
 use alloc::collections::{BTreeMap, BTreeSet};
 use alloc::vec::Vec;
+use core::hash::Hash;
 
-use crate::{Crdt, DeltaCrdt, NodeId};
+use crate::codec::{ActorTable, CodecError, Decode, Encode, Reader, Writer};
+use crate::merkle::{in_ranges, stable_hash, KeyRange, Reconcilable, KEY_DOMAIN, TOMBSTONE_DOMAIN};
+use crate::{Crdt, DeltaCrdt, DeltaSince, NodeId, VersionVector};
 
 /// An observed-remove set (OR-Set), also known as an add-wins set.
 ///
@@ -35,8 +38,8 @@ pub struct ORSet<T: Ord + Clone> {
     counter: u64,
     /// element -> set of unique tags (actor, counter)
     elements: BTreeMap<T, BTreeSet<(NodeId, u64)>>,
-    /// Tombstones: tags that have been removed
-    tombstones: BTreeSet<(NodeId, u64)>,
+    /// Tombstones: removed tag -> tag of the remove that hit it
+    tombstones: BTreeMap<(NodeId, u64), (NodeId, u64)>,
 }
 
 impl<T: Ord + Clone> ORSet<T> {
@@ -46,7 +49,7 @@ impl<T: Ord + Clone> ORSet<T> {
             actor,
             counter: 0,
             elements: BTreeMap::new(),
-            tombstones: BTreeSet::new(),
+            tombstones: BTreeMap::new(),
         }
     }
 
@@ -63,12 +66,17 @@ impl<T: Ord + Clone> ORSet<T> {
     /// Remove an element from the set.
     ///
     /// Only removes the tags that this replica has observed. Concurrent
-    /// adds on other replicas will survive the merge.
+    /// adds on other replicas will survive the merge. The remove gets a tag
+    /// of its own, so peers can tell whether they have seen it.
     ///
     /// Returns `true` if the element was present and removed.
     pub fn remove(&mut self, value: &T) -> bool {
         if let Some(tags) = self.elements.remove(value) {
-            self.tombstones.extend(tags);
+            self.counter += 1;
+            let dot = (self.actor, self.counter);
+            for tag in tags {
+                tombstone(&mut self.tombstones, tag, dot);
+            }
             true
         } else {
             false
@@ -112,6 +120,18 @@ impl<T: Ord + Clone> ORSet<T> {
         self.actor
     }
 
+    /// Get the version vector of this set: the highest tag counter
+    /// observed per node, across live tags, tombstones and removes.
+    #[must_use]
+    pub fn version_vector(&self) -> VersionVector {
+        self.elements
+            .values()
+            .flat_map(|tags| tags.iter())
+            .chain(self.tombstones.iter().flat_map(|(tag, dot)| [tag, dot]))
+            .copied()
+            .collect()
+    }
+
     /// Get the number of tombstones stored.
     #[must_use]
     pub fn tombstone_count(&self) -> usize {
@@ -139,7 +159,7 @@ impl<T: Ord + Clone> ORSet<T> {
         // Keep only tombstones whose tag is still live somewhere (shouldn't
         // normally happen, but guards against partial merges). All others
         // are safely discarded.
-        self.tombstones.retain(|tag| live_tags.contains(tag));
+        self.tombstones.retain(|tag, _| live_tags.contains(tag));
         before - self.tombstones.len()
     }
 
@@ -175,20 +195,19 @@ impl<T: Ord + Clone> Crdt for ORSet<T> {
         for (value, other_tags) in &other.elements {
             let self_tags = self.elements.entry(value.clone()).or_default();
             for &tag in other_tags {
-                if !self.tombstones.contains(&tag) {
+                if !self.tombstones.contains_key(&tag) {
                     self_tags.insert(tag);
                 }
             }
         }
 
-        for &tag in &other.tombstones {
+        for (&tag, &dot) in &other.tombstones {
             for tags in self.elements.values_mut() {
                 tags.remove(&tag);
             }
+            tombstone(&mut self.tombstones, tag, dot);
         }
 
-        self.tombstones.extend(&other.tombstones);
-
         self.elements.retain(|_, tags| !tags.is_empty());
 
         self.counter = self.counter.max(other.counter);
@@ -201,8 +220,9 @@ impl<T: Ord + Clone> Crdt for ORSet<T> {
 pub struct ORSetDelta<T: Ord + Clone> {
     /// New element-tag pairs that the other replica doesn't have.
     additions: BTreeMap<T, BTreeSet<(NodeId, u64)>>,
-    /// New tombstones that the other replica doesn't have.
-    tombstones: BTreeSet<(NodeId, u64)>,
+    /// New tombstones that the other replica doesn't have, as
+    /// `(removed tag, remove tag)` pairs.
+    tombstones: Vec<((NodeId, u64), (NodeId, u64))>,
 }
 
 impl<T: Ord + Clone> DeltaCrdt for ORSet<T> {
@@ -216,7 +236,7 @@ impl<T: Ord + Clone> DeltaCrdt for ORSet<T> {
                 .iter()
                 .filter(|tag| {
                     other_tags.map_or(true, |ot| !ot.contains(*tag))
-                        && !other.tombstones.contains(*tag)
+                        && !other.tombstones.contains_key(*tag)
                 })
                 .copied()
                 .collect();
@@ -225,10 +245,11 @@ impl<T: Ord + Clone> DeltaCrdt for ORSet<T> {
             }
         }
 
-        let tombstones: BTreeSet<_> = self
+        let tombstones = self
             .tombstones
-            .difference(&other.tombstones)
-            .copied()
+            .iter()
+            .filter(|(tag, _)| !other.tombstones.contains_key(*tag))
+            .map(|(&tag, &dot)| (tag, dot))
             .collect();
 
         ORSetDelta {
@@ -241,23 +262,191 @@ impl<T: Ord + Clone> DeltaCrdt for ORSet<T> {
         for (value, tags) in &delta.additions {
             let self_tags = self.elements.entry(value.clone()).or_default();
             for &tag in tags {
-                if !self.tombstones.contains(&tag) {
+                if !self.tombstones.contains_key(&tag) {
                     self_tags.insert(tag);
                 }
             }
         }
 
-        for &tag in &delta.tombstones {
+        for &(tag, dot) in &delta.tombstones {
             for tags in self.elements.values_mut() {
                 tags.remove(&tag);
             }
+            tombstone(&mut self.tombstones, tag, dot);
         }
-        self.tombstones.extend(&delta.tombstones);
 
         self.elements.retain(|_, tags| !tags.is_empty());
     }
 }
 
+impl<T: Ord + Clone> DeltaSince for ORSet<T> {
+    type Summary = VersionVector;
+
+    fn summary(&self) -> VersionVector {
+        self.version_vector()
+    }
+
+    /// Tombstones are included when the peer has not seen the remove that
+    /// made them.
+    fn delta_since(&self, since: &VersionVector) -> ORSetDelta<T> {
+        let mut additions = BTreeMap::new();
+        for (value, tags) in &self.elements {
+            let new_tags: BTreeSet<_> = tags
+                .iter()
+                .filter(|&&(actor, counter)| !since.contains(actor, counter))
+                .copied()
+                .collect();
+            if !new_tags.is_empty() {
+                additions.insert(value.clone(), new_tags);
+            }
+        }
+
+        let tombstones = self
+            .tombstones
+            .iter()
+            .filter(|(_, &(actor, counter))| !since.contains(actor, counter))
+            .map(|(&tag, &dot)| (tag, dot))
+            .collect();
+
+        ORSetDelta {
+            additions,
+            tombstones,
+        }
+    }
+}
+
+/// Each element is one entry hashed with its live tags; each tombstone is an
+/// entry of its own keyed by its tag and hashed with its remove.
+impl<T: Ord + Clone + Hash> Reconcilable for ORSet<T> {
+    fn digest_entries(&self) -> Vec<(u64, u64)> {
+        let elements = self.elements.iter().map(|(value, tags)| {
+            (
+                stable_hash(KEY_DOMAIN, value),
+                stable_hash(KEY_DOMAIN, &(value, tags)),
+            )
+        });
+        let tombstones = self.tombstones.iter().map(|entry| {
+            (
+                stable_hash(TOMBSTONE_DOMAIN, entry.0),
+                stable_hash(TOMBSTONE_DOMAIN, &entry),
+            )
+        });
+        elements.chain(tombstones).collect()
+    }
+
+    fn delta_for(&self, ranges: &[KeyRange]) -> ORSetDelta<T> {
+        ORSetDelta {
+            additions: self
+                .elements
+                .iter()
+                .filter(|(value, _)| in_ranges(ranges, stable_hash(KEY_DOMAIN, *value)))
+                .map(|(value, tags)| (value.clone(), tags.clone()))
+                .collect(),
+            tombstones: self
+                .tombstones
+                .iter()
+                .filter(|(tag, _)| in_ranges(ranges, stable_hash(TOMBSTONE_DOMAIN, *tag)))
+                .map(|(&tag, &dot)| (tag, dot))
+                .collect(),
+        }
+    }
+}
+
+/// Record that `tag` was removed by the remove tagged `dot`. When several
+/// removes hit one tag, every replica keeps the smallest, so they agree.
+pub(crate) fn tombstone(
+    tombstones: &mut BTreeMap<(NodeId, u64), (NodeId, u64)>,
+    tag: (NodeId, u64),
+    dot: (NodeId, u64),
+) {
+    let kept = tombstones.entry(tag).or_insert(dot);
+    *kept = (*kept).min(dot);
+}
+
+fn tag_actors<'a, T>(
+    elements: &'a BTreeMap<T, BTreeSet<(NodeId, u64)>>,
+    tombstones: impl Iterator<Item = ((NodeId, u64), (NodeId, u64))> + 'a,
+) -> impl Iterator<Item = NodeId> + 'a {
+    elements
+        .values()
+        .flatten()
+        .map(|&(actor, _)| actor)
+        .chain(tombstones.flat_map(|((a, _), (b, _))| [a, b]))
+}
+
+fn write_elements<T: Encode>(
+    w: &mut Writer,
+    table: &ActorTable,
+    elements: &BTreeMap<T, BTreeSet<(NodeId, u64)>>,
+) {
+    w.write_len(elements.len());
+    for (value, tags) in elements {
+        value.encode(w);
+        table.write_tags(w, tags);
+    }
+}
+
+fn read_elements<T: Ord + Decode>(
+    r: &mut Reader<'_>,
+    table: &ActorTable,
+) -> Result<BTreeMap<T, BTreeSet<(NodeId, u64)>>, CodecError> {
+    let len = r.read_len()?;
+    (0..len)
+        .map(|_| Ok((T::decode(r)?, table.read_tags(r)?)))
+        .collect()
+}
+
+impl<T: Ord + Clone + Encode> Encode for ORSet<T> {
+    fn encode(&self, w: &mut Writer) {
+        let table = ActorTable::new(
+            core::iter::once(self.actor).chain(tag_actors(
+                &self.elements,
+                self.tombstones.iter().map(|(&tag, &dot)| (tag, dot)),
+            )),
+        );
+        table.encode(w);
+        table.write_actor(w, self.actor);
+        w.write_varint(self.counter);
+        write_elements(w, &table, &self.elements);
+        let tombstones = self.tombstones.iter().map(|(&tag, &dot)| (tag, dot));
+        table.write_tombstones(w, tombstones);
+    }
+}
+
+impl<T: Ord + Clone + Decode> Decode for ORSet<T> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        let table = ActorTable::decode(r)?;
+        Ok(Self {
+            actor: table.read_actor(r)?,
+            counter: r.read_varint()?,
+            elements: read_elements(r, &table)?,
+            tombstones: table.read_tombstones(r)?,
+        })
+    }
+}
+
+impl<T: Ord + Clone + Encode> Encode for ORSetDelta<T> {
+    fn encode(&self, w: &mut Writer) {
+        let table = ActorTable::new(tag_actors(
+            &self.additions,
+            self.tombstones.iter().copied(),
+        ));
+        table.encode(w);
+        write_elements(w, &table, &self.additions);
+        table.write_tombstones(w, self.tombstones.iter().copied());
+    }
+}
+
+impl<T: Ord + Clone + Decode> Decode for ORSetDelta<T> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        let table = ActorTable::decode(r)?;
+        Ok(Self {
+            additions: read_elements(r, &table)?,
+            tombstones: table.read_tombstones(r)?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -466,4 +655,101 @@ mod tests {
         via_delta.apply_delta(&d);
         assert!(!via_delta.contains(&"x"));
     }
+
+    #[test]
+    fn version_vector_covers_tags_and_tombstones() {
+        let mut s = ORSet::new(1);
+        s.insert("x");
+        s.insert("y");
+        s.remove(&"y");
+        assert_eq!(s.version_vector().get(1), 3);
+    }
+
+    #[test]
+    fn delta_since_equivalent_to_merge() {
+        let mut s1 = ORSet::new(1);
+        s1.insert("x");
+        s1.insert("y");
+
+        let mut s2 = ORSet::new(2);
+        s2.merge(&s1);
+        s2.insert("z");
+
+        s1.remove(&"x");
+        s1.insert("w");
+
+        let mut full = s2.clone();
+        full.merge(&s1);
+
+        let d = s1.delta_since(&s2.version_vector());
+        assert_eq!(d.additions.len(), 1);
+        assert!(d.additions.contains_key(&"w"));
+
+        let mut via_delta = s2.clone();
+        via_delta.apply_delta(&d);
+        let full_elems: BTreeSet<_> = full.iter().collect();
+        let delta_elems: BTreeSet<_> = via_delta.iter().collect();
+        assert_eq!(full_elems, delta_elems);
+    }
+
+    #[test]
+    fn delta_since_skips_seen_removes() {
+        let mut s1 = ORSet::new(1);
+        s1.insert("x");
+        s1.insert("y");
+        s1.remove(&"x");
+
+        let mut s2 = ORSet::new(2);
+        s2.merge(&s1);
+        assert!(s1.delta_since(&s2.version_vector()).tombstones.is_empty());
+
+        s1.remove(&"y");
+        let d = s1.delta_since(&s2.version_vector());
+        assert_eq!(d.tombstones, vec![((1, 2), (1, 4))]);
+
+        s2.apply_delta(&d);
+        assert!(s2.is_empty());
+        assert_eq!(s2.tombstones, s1.tombstones);
+    }
+
+    #[test]
+    fn concurrent_removes_keep_the_smallest_dot() {
+        let mut s1 = ORSet::new(1);
+        s1.insert("x");
+        let mut s2 = ORSet::new(2);
+        s2.merge(&s1);
+
+        s1.remove(&"x");
+        s2.remove(&"x");
+
+        let mut left = s1.clone();
+        left.merge(&s2);
+        let mut right = s2.clone();
+        right.merge(&s1);
+        assert_eq!(left.tombstones, right.tombstones);
+        assert_eq!(left.tombstones[&(1, 1)], (1, 2));
+    }
+
+    #[test]
+    fn reconcile_propagates_removes() {
+        use crate::merkle::reconcile_in_memory;
+
+        let mut s1 = ORSet::new(1);
+        for i in 0..1000 {
+            s1.insert(i);
+        }
+        let mut s2 = ORSet::new(2);
+        s2.merge(&s1);
+        s2.remove(&10);
+        s1.insert(5000);
+
+        let mut via_merge = s1.clone();
+        via_merge.merge(&s2);
+        reconcile_in_memory(&mut s1, &mut s2);
+        assert!(!s1.contains(&10));
+        assert!(s2.contains(&5000));
+        assert_eq!(s1.elements, via_merge.elements);
+        assert_eq!(s2.elements, via_merge.elements);
+        assert_eq!(s1.tombstones, s2.tombstones);
+    }
 }
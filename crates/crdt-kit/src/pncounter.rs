@@ -1,4 +1,5 @@
-use crate::{Crdt, DeltaCrdt, GCounter, GCounterDelta, NodeId};
+use crate::codec::{CodecError, Decode, Encode, Reader, Writer};
+use crate::{Crdt, DeltaCrdt, DeltaSince, GCounter, GCounterDelta, NodeId, VersionVector};
 
 /// A positive-negative counter (PN-Counter).
 ///
@@ -87,6 +88,85 @@ impl DeltaCrdt for PNCounter {
     }
 }
 
+/// Summary of a [`PNCounter`]: one version vector per internal G-Counter.
+///
+/// Keeping the halves apart lets [`DeltaSince::delta_since`] tell which of
+/// them a peer is behind on.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PNCounterSummary {
+    /// Per-node increment counts seen.
+    pub increments: VersionVector,
+    /// Per-node decrement counts seen.
+    pub decrements: VersionVector,
+}
+
+impl DeltaSince for PNCounter {
+    type Summary = PNCounterSummary;
+
+    fn summary(&self) -> PNCounterSummary {
+        PNCounterSummary {
+            increments: self.increments.version_vector(),
+            decrements: self.decrements.version_vector(),
+        }
+    }
+
+    fn delta_since(&self, since: &PNCounterSummary) -> PNCounterDelta {
+        PNCounterDelta {
+            increments: self.increments.delta_since(&since.increments),
+            decrements: self.decrements.delta_since(&since.decrements),
+        }
+    }
+}
+
+impl Encode for PNCounter {
+    fn encode(&self, w: &mut Writer) {
+        self.increments.encode(w);
+        self.decrements.encode(w);
+    }
+}
+
+impl Decode for PNCounter {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Ok(Self {
+            increments: GCounter::decode(r)?,
+            decrements: GCounter::decode(r)?,
+        })
+    }
+}
+
+impl Encode for PNCounterSummary {
+    fn encode(&self, w: &mut Writer) {
+        self.increments.encode(w);
+        self.decrements.encode(w);
+    }
+}
+
+impl Decode for PNCounterSummary {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Ok(Self {
+            increments: VersionVector::decode(r)?,
+            decrements: VersionVector::decode(r)?,
+        })
+    }
+}
+
+impl Encode for PNCounterDelta {
+    fn encode(&self, w: &mut Writer) {
+        self.increments.encode(w);
+        self.decrements.encode(w);
+    }
+}
+
+impl Decode for PNCounterDelta {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Ok(Self {
+            increments: GCounterDelta::decode(r)?,
+            decrements: GCounterDelta::decode(r)?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -179,4 +259,57 @@ mod tests {
 
         assert_eq!(full.value(), via_delta.value());
     }
+
+    #[test]
+    fn delta_since_equivalent_to_merge() {
+        let mut c1 = PNCounter::new(1);
+        c1.increment();
+        c1.decrement();
+        c1.decrement();
+
+        let mut c2 = PNCounter::new(2);
+        c2.increment();
+        let mut c3 = c1.clone();
+        c3.merge(&c2);
+        c1.increment();
+
+        let mut full = c3.clone();
+        full.merge(&c1);
+
+        let d = c1.delta_since(&c3.summary());
+        c3.apply_delta(&d);
+        assert_eq!(c3, full);
+        assert_eq!(c3.value(), 1);
+    }
+
+    #[test]
+    fn delta_since_is_empty_when_up_to_date() {
+        let mut c1 = PNCounter::new(1);
+        c1.increment();
+        c1.decrement();
+
+        let c2 = c1.clone();
+        let d = c1.delta_since(&c2.summary());
+        assert_eq!(d, c1.delta(&c2));
+    }
+
+    #[test]
+    fn delta_since_sends_only_the_half_that_moved() {
+        let mut c1 = PNCounter::new(1);
+        c1.increment();
+        c1.decrement();
+        let c2 = c1.clone();
+
+        // Same op count per node as an extra increment, but a different half.
+        c1.decrement();
+        let d = c1.delta_since(&c2.summary());
+        let empty = GCounter::new(1).delta(&GCounter::new(1));
+        assert_eq!(d.increments, empty);
+        assert_ne!(d.decrements, empty);
+
+        let mut c3 = c2.clone();
+        c3.apply_delta(&d);
+        assert_eq!(c3, c1);
+        assert_eq!(c3.value(), -1);
+    }
 }
@@ -4,16 +4,21 @@
 //! use crdt_kit::prelude::*;
 //! ```
 
+pub use crate::Anchor;
 pub use crate::AWMap;
 pub use crate::AWMapDelta;
+pub use crate::BlameRun;
 pub use crate::Crdt;
 pub use crate::CrdtType;
 pub use crate::DeltaCrdt;
+pub use crate::DeltaSince;
+pub use crate::Expand;
 pub use crate::GCounter;
 pub use crate::LWWMap;
 pub use crate::LWWMapDelta;
 pub use crate::GCounterDelta;
 pub use crate::GSet;
+pub use crate::Gravity;
 pub use crate::GSetDelta;
 pub use crate::LWWRegister;
 pub use crate::LWWRegisterDelta;
@@ -24,14 +29,19 @@ pub use crate::ORSet;
 pub use crate::ORSetDelta;
 pub use crate::PNCounter;
 pub use crate::PNCounterDelta;
+pub use crate::PNCounterSummary;
+pub use crate::RichText;
+pub use crate::RichTextDelta;
 pub use crate::Rga;
 pub use crate::RgaDelta;
 pub use crate::RgaError;
 pub use crate::RgaNode;
+pub use crate::Splice;
 pub use crate::TextCrdt;
 pub use crate::TextDelta;
 pub use crate::TextError;
 pub use crate::TwoPSet;
 pub use crate::TwoPSetDelta;
+pub use crate::VersionVector;
 pub use crate::Versioned;
 pub use crate::VersionedEnvelope;
@@ -1,9 +1,14 @@
 use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
 use alloc::vec::Vec;
 use core::fmt;
+use core::marker::PhantomData;
+use core::ops::Range;
 
-use crate::rope::ChunkedVec;
-use crate::{Crdt, DeltaCrdt, NodeId};
+use crate::clock::HybridTimestamp;
+use crate::codec::{ActorTable, CodecError, Decode, Encode, Reader, Writer};
+use crate::rope::{ChunkedVec, ElementId, Metrics};
+use crate::{Crdt, DeltaCrdt, DeltaSince, NodeId, VersionVector};
 
 /// Error type for RGA operations.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -30,16 +35,896 @@ impl fmt::Display for RgaError {
 #[cfg(feature = "std")]
 impl std::error::Error for RgaError {}
 
-/// A single node in the RGA sequence.
+/// Storage for the values of an [`RgaNode`], a run of consecutive elements.
+///
+/// [`Rga<T>`] keeps runs in a `Vec<T>`; [`TextCrdt`](crate::TextCrdt) keeps
+/// them in a `String`, so typed text costs little more than the text itself.
+pub trait Run<T>: Clone {
+    /// Iterator over the values of a run.
+    type Values<'a>: Iterator<Item = T>
+    where
+        Self: 'a;
+
+    /// A run holding just `value`.
+    fn single(value: T) -> Self;
+
+    /// Number of values in the run.
+    fn count(&self) -> usize;
+
+    /// Split the run before value `at`, returning the values from `at` on.
+    fn split_tail(&mut self, at: usize) -> Self;
+
+    /// Append the values of `other` to the run.
+    fn append_run(&mut self, other: Self);
+
+    /// Split the run into runs of at most `max` values each, in order.
+    fn into_chunks(mut self, max: usize) -> Vec<Self> {
+        // Split from the back, so that each split only moves one chunk.
+        let mut chunks = Vec::new();
+        let mut len = self.count();
+        while len > max {
+            let at = (len - 1) / max * max;
+            chunks.push(self.split_tail(at));
+            len = at;
+        }
+        chunks.push(self);
+        chunks.reverse();
+        chunks
+    }
+
+    /// Iterate over the values in order.
+    fn values(&self) -> Self::Values<'_>;
+
+    /// Length of the run in UTF-8 bytes, if it is text (0 otherwise).
+    fn utf8_len(&self) -> usize {
+        0
+    }
+
+    /// Length of the run in UTF-16 code units, if it is text (0 otherwise).
+    fn utf16_len(&self) -> usize {
+        0
+    }
+
+    /// Number of line breaks (`'\n'`) in the run, if it is text (0
+    /// otherwise).
+    fn line_breaks(&self) -> usize {
+        0
+    }
+}
+
+impl<T: Clone> Run<T> for Vec<T> {
+    type Values<'a> = core::iter::Cloned<core::slice::Iter<'a, T>> where T: 'a;
+
+    fn single(value: T) -> Self {
+        alloc::vec![value]
+    }
+
+    fn count(&self) -> usize {
+        self.len()
+    }
+
+    fn split_tail(&mut self, at: usize) -> Self {
+        self.split_off(at)
+    }
+
+    fn append_run(&mut self, mut other: Self) {
+        self.append(&mut other);
+    }
+
+    fn values(&self) -> Self::Values<'_> {
+        self.iter().cloned()
+    }
+}
+
+impl Run<char> for String {
+    type Values<'a> = core::str::Chars<'a>;
+
+    fn single(value: char) -> Self {
+        String::from(value)
+    }
+
+    fn count(&self) -> usize {
+        self.chars().count()
+    }
+
+    fn split_tail(&mut self, at: usize) -> Self {
+        let byte = self.char_indices().nth(at).map_or(self.len(), |(i, _)| i);
+        self.split_off(byte)
+    }
+
+    fn append_run(&mut self, other: Self) {
+        self.push_str(&other);
+    }
+
+    fn into_chunks(self, max: usize) -> Vec<Self> {
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        for (i, (byte, _)) in self.char_indices().enumerate() {
+            if i > 0 && i % max == 0 {
+                chunks.push(String::from(&self[start..byte]));
+                start = byte;
+            }
+        }
+        chunks.push(String::from(&self[start..]));
+        chunks
+    }
+
+    fn values(&self) -> Self::Values<'_> {
+        self.chars()
+    }
+
+    fn utf8_len(&self) -> usize {
+        self.len()
+    }
+
+    fn utf16_len(&self) -> usize {
+        self.chars().map(char::len_utf16).sum()
+    }
+
+    fn line_breaks(&self) -> usize {
+        self.bytes().filter(|&b| b == b'\n').count()
+    }
+}
+
+/// Most elements one [`RgaNode`] holds, so that splitting or scanning a run
+/// takes bounded time however long the text typed in one go. Runs break
+/// where the counter reaches a multiple of it, so where they break does not
+/// depend on the order edits were made in.
+const MAX_RUN_LEN: usize = 256;
+
+/// Text widths and line breaks of a run, measured once when the run is
+/// made or split so that the rope never has to walk its text again.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Widths {
+    utf8: usize,
+    utf16: usize,
+    lines: usize,
+}
+
+impl Widths {
+    fn of<T, S: Run<T>>(values: &S) -> Self {
+        Self {
+            utf8: values.utf8_len(),
+            utf16: values.utf16_len(),
+            lines: values.line_breaks(),
+        }
+    }
+}
+
+/// A run of consecutive elements in the RGA sequence.
+///
+/// The elements of a run were inserted one after another by the same actor,
+/// so they have ids `(actor, c)`, `(actor, c + 1)`, … and share their
+/// tombstone flag and right origin; the left origin of every element but the
+/// first is the element before it. Runs are split when an insert or delete
+/// lands inside them and joined again once their parts line up, so the ids
+/// and merge semantics are those of one node per element. A run holds at
+/// most 256 elements; longer inserts are stored as several runs.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-pub struct RgaNode<T: Clone + Ord> {
-    /// Unique identifier: (actor, counter).
-    pub id: (NodeId, u64),
-    /// The element value.
-    pub value: T,
-    /// Whether this element has been tombstoned (logically deleted).
-    pub deleted: bool,
+pub struct RgaNode<T: Clone + Ord, S: Run<T> = Vec<T>> {
+    id: (NodeId, u64),
+    len: usize,
+    values: S,
+    deleted: bool,
+    moved: bool,
+    origin_left: Option<(NodeId, u64)>,
+    origin_right: Option<(NodeId, u64)>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    widths: Widths,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: Clone + Ord, S: Run<T>> RgaNode<T, S> {
+    fn new(
+        id: (NodeId, u64),
+        values: S,
+        origin_left: Option<(NodeId, u64)>,
+        origin_right: Option<(NodeId, u64)>,
+    ) -> Self {
+        Self {
+            id,
+            len: values.count(),
+            widths: Widths::of(&values),
+            values,
+            deleted: false,
+            moved: false,
+            origin_left,
+            origin_right,
+            marker: PhantomData,
+        }
+    }
+
+    /// Measure the text widths again, for nodes read without them.
+    #[cfg(feature = "serde")]
+    fn measured(mut self) -> Self {
+        self.widths = Widths::of(&self.values);
+        self
+    }
+
+    fn set_values(&mut self, values: S) {
+        self.len = values.count();
+        self.widths = Widths::of(&values);
+        self.values = values;
+    }
+
+    /// Id of the first element: (actor, counter).
+    #[must_use]
+    pub fn id(&self) -> (NodeId, u64) {
+        self.id
+    }
+
+    /// Ids of the elements, in order.
+    pub fn ids(&self) -> impl Iterator<Item = (NodeId, u64)> {
+        let (actor, first) = self.id;
+        (first..first + self.len as u64).map(move |counter| (actor, counter))
+    }
+
+    /// Number of elements in the run.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the run holds no elements (never true for a stored run).
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The element values.
+    #[must_use]
+    pub fn values(&self) -> &S {
+        &self.values
+    }
+
+    /// Whether the elements have been tombstoned (logically deleted).
+    #[must_use]
+    pub fn is_deleted(&self) -> bool {
+        self.deleted
+    }
+
+    /// Whether the elements were moved elsewhere by
+    /// [`Rga::move_item`]; they stay behind as invisible placeholders.
+    #[must_use]
+    pub fn is_moved(&self) -> bool {
+        self.moved
+    }
+
+    fn is_visible(&self) -> bool {
+        !self.deleted && !self.moved
+    }
+
+    /// Element immediately before the first element when it was inserted
+    /// (`None`: the head).
+    #[must_use]
+    pub fn origin_left(&self) -> Option<(NodeId, u64)> {
+        self.origin_left
+    }
+
+    /// Element immediately after the elements when they were inserted
+    /// (`None`: the end).
+    #[must_use]
+    pub fn origin_right(&self) -> Option<(NodeId, u64)> {
+        self.origin_right
+    }
+
+    fn id_at(&self, offset: usize) -> (NodeId, u64) {
+        (self.id.0, self.id.1 + offset as u64)
+    }
+
+    fn metrics(&self) -> Metrics {
+        let mut metrics = Metrics {
+            units: self.len,
+            ..Metrics::default()
+        };
+        if self.is_visible() {
+            metrics.visible = self.len;
+            metrics.utf8 = self.widths.utf8;
+            metrics.utf16 = self.widths.utf16;
+            metrics.lines = self.widths.lines;
+        }
+        metrics
+    }
+
+    fn id_span(&self) -> (ElementId, u64) {
+        (self.id, self.len as u64)
+    }
+
+    /// Split before element `at`, returning the elements from `at` on.
+    fn split_off(&mut self, at: usize) -> Self {
+        let values = self.values.split_tail(at);
+        let widths = Widths::of(&values);
+        let rest = Self {
+            id: self.id_at(at),
+            len: self.len - at,
+            values,
+            deleted: self.deleted,
+            moved: self.moved,
+            origin_left: Some(self.id_at(at - 1)),
+            origin_right: self.origin_right,
+            widths,
+            marker: PhantomData,
+        };
+        self.len = at;
+        self.widths.utf8 -= widths.utf8;
+        self.widths.utf16 -= widths.utf16;
+        self.widths.lines -= widths.lines;
+        rest
+    }
+
+    /// Split where the counter crosses a multiple of [`MAX_RUN_LEN`].
+    fn into_capped(self) -> Vec<Self> {
+        let first = MAX_RUN_LEN - (self.id.1 % MAX_RUN_LEN as u64) as usize;
+        if self.len <= first {
+            return alloc::vec![self];
+        }
+        let Self {
+            id: (actor, mut counter),
+            mut values,
+            deleted,
+            moved,
+            mut origin_left,
+            origin_right,
+            ..
+        } = self;
+        let rest = values.split_tail(first);
+        let mut runs = Vec::new();
+        let chunks = core::iter::once(values).chain(rest.into_chunks(MAX_RUN_LEN));
+        for values in chunks {
+            let len = values.count();
+            runs.push(Self {
+                id: (actor, counter),
+                len,
+                widths: Widths::of(&values),
+                values,
+                deleted,
+                moved,
+                origin_left,
+                origin_right,
+                marker: PhantomData,
+            });
+            counter += len as u64;
+            origin_left = Some((actor, counter - 1));
+        }
+        runs
+    }
+
+    /// Elements `from..to` as a run of their own.
+    fn slice(&self, from: usize, to: usize) -> Self {
+        let mut run = self.clone();
+        if to < run.len {
+            run.split_off(to);
+        }
+        if from > 0 {
+            run = run.split_off(from);
+        }
+        run
+    }
+
+    /// Whether `next` continues this run, as if both were one node, within
+    /// one block of [`MAX_RUN_LEN`] counters.
+    fn continued_by(&self, next: &Self) -> bool {
+        let block = |counter: u64| counter / MAX_RUN_LEN as u64;
+        block(self.id.1) == block(next.id.1 + next.len as u64 - 1) && self.continues_into(next)
+    }
+
+    /// Whether `next` continues this run, however long both are.
+    fn continues_into(&self, next: &Self) -> bool {
+        next.id == self.id_at(self.len)
+            && next.origin_left == Some(self.id_at(self.len - 1))
+            && next.origin_right == self.origin_right
+            && next.deleted == self.deleted
+            && next.moved == self.moved
+    }
+
+    fn absorb(&mut self, next: Self) {
+        self.len += next.len;
+        self.widths.utf8 += next.widths.utf8;
+        self.widths.utf16 += next.widths.utf16;
+        self.widths.lines += next.widths.lines;
+        self.values.append_run(next.values);
+    }
+}
+
+/// Which neighbour an [`Anchor`] binds to, and so which way it moves when
+/// text is inserted exactly at its position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Gravity {
+    /// Bind to the element before the position; inserts there land after
+    /// the anchor.
+    Left,
+    /// Bind to the element after the position; inserts there land before
+    /// the anchor.
+    Right,
+}
+
+/// A position in an [`Rga`] that stays between the same elements while
+/// remote edits are merged, e.g. a cursor or one end of a selection.
+///
+/// The anchor is bound to an element id rather than an index. If that
+/// element is tombstoned, the anchor resolves to where it used to be: just
+/// after the preceding visible element ([`Gravity::Left`]) or just before
+/// the following one ([`Gravity::Right`]).
+///
+/// # Example
+///
+/// ```
+/// use crdt_kit::prelude::*;
+///
+/// let mut alice = TextCrdt::new(1);
+/// alice.insert_str(0, "hello world").unwrap();
+/// let mut bob = alice.fork(2);
+///
+/// // Alice's cursor sits before "world".
+/// let cursor = alice.anchor_at(6, Gravity::Right).unwrap();
+///
+/// bob.insert_str(0, "oh, ").unwrap();
+/// alice.merge(&bob);
+/// assert_eq!(alice.resolve(cursor), 10);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Anchor {
+    target: Option<(NodeId, u64)>,
+    gravity: Gravity,
+}
+
+impl Anchor {
+    /// Id of the element the anchor is bound to (`None`: the start of the
+    /// sequence for [`Gravity::Left`], the end for [`Gravity::Right`]).
+    #[must_use]
+    pub fn target(&self) -> Option<(NodeId, u64)> {
+        self.target
+    }
+
+    /// The anchor's gravity.
+    #[must_use]
+    pub fn gravity(&self) -> Gravity {
+        self.gravity
+    }
+}
+
+/// One change to the visible sequence: `deleted` elements removed from
+/// `index` on, and `inserted` put in their place.
+///
+/// [`Rga::merge_with_patches`] and [`Rga::apply_delta_with_patches`] return
+/// a list of splices that, applied in order to the sequence as it was
+/// before, turn it into the sequence after, so a view of it (e.g. an
+/// editor buffer) can be updated without comparing the two.
+///
+/// ```
+/// use crdt_kit::prelude::*;
+///
+/// let mut alice = TextCrdt::new(1);
+/// alice.insert_str(0, "hello world").unwrap();
+/// let mut bob = alice.fork(2);
+/// bob.remove_range(0, 5).unwrap();
+/// bob.insert_str(0, "howdy").unwrap();
+///
+/// let patches = alice.merge_with_patches(&bob);
+/// assert_eq!(patches, vec![Splice { index: 0, deleted: 5, inserted: "howdy".into() }]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Splice<S> {
+    /// Visible index the change starts at.
+    pub index: usize,
+    /// Number of visible elements removed from `index` on.
+    pub deleted: usize,
+    /// Elements inserted at `index` (empty for a pure deletion).
+    pub inserted: S,
+}
+
+/// Visible elements `range` inserted by `actor` in one go, as reported by
+/// [`Rga::blame`].
+///
+/// `timestamp` is when they were inserted, if the insert recorded one
+/// (see [`Rga::insert_run_with_timestamp`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlameRun {
+    /// Visible indices of the elements.
+    pub range: Range<usize>,
+    /// Replica that inserted them.
+    pub actor: NodeId,
+    /// Time of the insert, if recorded.
+    pub timestamp: Option<HybridTimestamp>,
+}
+
+/// Splice being recorded; `inserted` is filled in when something is.
+struct PendingSplice<S> {
+    index: usize,
+    deleted: usize,
+    inserted: Option<S>,
+    inserted_len: usize,
+}
+
+/// Changes to the visible sequence made while integrating remote state,
+/// recorded only when switched on.
+struct Patches<S>(Option<Vec<PendingSplice<S>>>);
+
+impl<S> Patches<S> {
+    fn off() -> Self {
+        Self(None)
+    }
+
+    fn on() -> Self {
+        Self(Some(Vec::new()))
+    }
+
+    fn is_on(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Record that `count` visible elements were removed from `index` on.
+    fn delete(&mut self, index: usize, count: usize) {
+        let Some(splices) = &mut self.0 else {
+            return;
+        };
+        match splices.last_mut() {
+            Some(last) if last.index + last.inserted_len == index => last.deleted += count,
+            _ => splices.push(PendingSplice {
+                index,
+                deleted: count,
+                inserted: None,
+                inserted_len: 0,
+            }),
+        }
+    }
+
+    /// Record that the `len` elements of `run` became visible at `index`.
+    fn insert<T>(&mut self, index: usize, run: S, len: usize)
+    where
+        S: Run<T>,
+    {
+        let Some(splices) = &mut self.0 else {
+            return;
+        };
+        match splices.last_mut() {
+            Some(last) if last.index + last.inserted_len == index => {
+                match &mut last.inserted {
+                    Some(inserted) => inserted.append_run(run),
+                    None => last.inserted = Some(run),
+                }
+                last.inserted_len += len;
+            }
+            _ => splices.push(PendingSplice {
+                index,
+                deleted: 0,
+                inserted: Some(run),
+                inserted_len: len,
+            }),
+        }
+    }
+
+    fn finish(self) -> Vec<Splice<S>>
+    where
+        S: Default,
+    {
+        self.0
+            .unwrap_or_default()
+            .into_iter()
+            .map(|p| Splice {
+                index: p.index,
+                deleted: p.deleted,
+                inserted: p.inserted.unwrap_or_default(),
+            })
+            .collect()
+    }
+}
+
+/// Where moves have placed items. [`Rga::move_item`] inserts a new position
+/// element for the item it moves, and of all the positions an item has had
+/// (including the one it was inserted at), the one with the greatest
+/// `(counter, actor)` is current.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct MoveTable {
+    /// Item each move-created position belongs to.
+    item_of: BTreeMap<ElementId, ElementId>,
+    /// Move-created positions of each moved item.
+    positions: BTreeMap<ElementId, BTreeSet<ElementId>>,
+}
+
+impl MoveTable {
+    /// Record that `position` was created by moving `item`. Returns
+    /// whether the move is new.
+    fn insert(&mut self, position: ElementId, item: ElementId) -> bool {
+        if self.item_of.insert(position, item).is_some() {
+            return false;
+        }
+        self.positions.entry(item).or_default().insert(position);
+        true
+    }
+
+    /// The item whose position `id` is (an element is its own item until
+    /// it is moved).
+    fn item_of(&self, id: ElementId) -> ElementId {
+        self.item_of.get(&id).copied().unwrap_or(id)
+    }
+
+    /// All positions `item` has had, starting with its original one.
+    fn positions(&self, item: ElementId) -> impl Iterator<Item = ElementId> + '_ {
+        core::iter::once(item).chain(self.positions.get(&item).into_iter().flatten().copied())
+    }
+
+    /// Moved items with a position among the `len` ids from `first` on.
+    fn items_in(&self, (actor, first): ElementId, len: usize) -> BTreeSet<ElementId> {
+        let range = (actor, first)..(actor, first + len as u64);
+        self.item_of
+            .range(range.clone())
+            .map(|(_, &item)| item)
+            .chain(self.positions.range(range).map(|(&item, _)| item))
+            .collect()
+    }
+
+    /// `(position, item)` for every move.
+    fn iter(&self) -> impl Iterator<Item = (ElementId, ElementId)> + '_ {
+        self.item_of.iter().map(|(&position, &item)| (position, item))
+    }
+
+    /// Forget the moves of every item `keep` rejects.
+    fn retain_items(&mut self, mut keep: impl FnMut(ElementId) -> bool) {
+        let item_of = &mut self.item_of;
+        self.positions.retain(|&item, positions| {
+            let kept = keep(item);
+            if !kept {
+                for position in positions.iter() {
+                    item_of.remove(position);
+                }
+            }
+            kept
+        });
+    }
+
+    fn len(&self) -> usize {
+        self.item_of.len()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for MoveTable {
+    fn serialize<Ser: serde::Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for MoveTable {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let moves: Vec<(ElementId, ElementId)> = serde::Deserialize::deserialize(deserializer)?;
+        let mut table = Self::default();
+        for (position, item) in moves {
+            table.insert(position, item);
+        }
+        Ok(table)
+    }
+}
+
+/// Latest value written to each item by [`Rga::set`]: of all updates to an
+/// item, the one with the greatest `(counter, actor)` id wins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct UpdateTable<T> {
+    /// Winning update id and value of each updated item.
+    winners: BTreeMap<ElementId, (ElementId, T)>,
+}
+
+impl<T> Default for UpdateTable<T> {
+    fn default() -> Self {
+        Self {
+            winners: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T> UpdateTable<T> {
+    /// Record that `update` set `item` to `value`. Returns whether it is
+    /// the new winner.
+    fn insert(&mut self, update: ElementId, item: ElementId, value: T) -> bool {
+        let newer = |(current, _): &(ElementId, T)| {
+            (update.1, update.0) > (current.1, current.0)
+        };
+        if self.winners.get(&item).map_or(true, newer) {
+            self.winners.insert(item, (update, value));
+            return true;
+        }
+        false
+    }
+
+    /// The winning value of `item`, if it was ever updated.
+    fn get(&self, item: ElementId) -> Option<&T> {
+        self.winners.get(&item).map(|(_, value)| value)
+    }
+
+    /// Updated items among the `len` ids from `first` on.
+    fn items_in(
+        &self,
+        (actor, first): ElementId,
+        len: usize,
+    ) -> impl Iterator<Item = ElementId> + '_ {
+        let range = (actor, first)..(actor, first + len as u64);
+        self.winners.range(range).map(|(&item, _)| item)
+    }
+
+    /// `(update, item, value)` for every winning update.
+    fn iter(&self) -> impl Iterator<Item = (ElementId, ElementId, &T)> + '_ {
+        self.winners
+            .iter()
+            .map(|(&item, (update, value))| (*update, item, value))
+    }
+
+    /// Forget the updates of every item `keep` rejects.
+    fn retain_items(&mut self, mut keep: impl FnMut(ElementId) -> bool) {
+        self.winners.retain(|&item, _| keep(item));
+    }
+
+    fn len(&self) -> usize {
+        self.winners.len()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for UpdateTable<T> {
+    fn serialize<Ser: serde::Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for UpdateTable<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let updates: Vec<(ElementId, ElementId, T)> =
+            serde::Deserialize::deserialize(deserializer)?;
+        let mut table = Self::default();
+        for (update, item, value) in updates {
+            table.insert(update, item, value);
+        }
+        Ok(table)
+    }
+}
+
+/// Timestamps recorded by [`Rga::insert_run_with_timestamp`], one per
+/// insert.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct StampTable {
+    /// End counter (exclusive) and timestamp of each insert, by first id.
+    inserts: BTreeMap<ElementId, (u64, HybridTimestamp)>,
+}
+
+impl StampTable {
+    /// Record that the ids `span` of `actor` were inserted at `timestamp`.
+    fn insert(&mut self, actor: NodeId, span: Range<u64>, timestamp: HybridTimestamp) {
+        self.inserts.insert((actor, span.start), (span.end, timestamp));
+    }
+
+    /// When element `id` was inserted, if that was recorded.
+    fn get(&self, (actor, counter): ElementId) -> Option<HybridTimestamp> {
+        let (&(a, _), &(end, timestamp)) = self.inserts.range(..=(actor, counter)).next_back()?;
+        (a == actor && counter < end).then_some(timestamp)
+    }
+
+    /// `(actor, span, timestamp)` for every recorded insert.
+    fn iter(&self) -> impl Iterator<Item = (NodeId, Range<u64>, HybridTimestamp)> + '_ {
+        self.inserts
+            .iter()
+            .map(|(&(actor, start), &(end, timestamp))| (actor, start..end, timestamp))
+    }
+
+    fn len(&self) -> usize {
+        self.inserts.len()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for StampTable {
+    fn serialize<Ser: serde::Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for StampTable {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let inserts: Vec<(NodeId, Range<u64>, HybridTimestamp)> =
+            serde::Deserialize::deserialize(deserializer)?;
+        let mut table = Self::default();
+        for (actor, span, timestamp) in inserts {
+            table.insert(actor, span, timestamp);
+        }
+        Ok(table)
+    }
+}
+
+/// Ids deleted by each remove, so that deltas send a peer only the removes
+/// it has not seen. Every remove gets an id of its own, like an insert.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct RemoveTable {
+    /// End counter (exclusive) of each span of ids a remove deleted, by
+    /// remove id and first id. Spans of one remove and actor that touch
+    /// are joined, so the table does not depend on how removes arrived.
+    spans: BTreeMap<(ElementId, ElementId), u64>,
+}
+
+impl RemoveTable {
+    /// Record that the remove `dot` deleted the ids `span` of `actor`.
+    fn insert(&mut self, dot: ElementId, actor: NodeId, span: Range<u64>) {
+        let (mut start, mut end) = (span.start, span.end);
+        if start >= end {
+            return;
+        }
+        let touching: Vec<_> = self
+            .spans
+            .range((dot, (actor, 0))..=(dot, (actor, end)))
+            .filter(|&(_, &e)| e >= start)
+            .map(|(&key, &e)| (key, e))
+            .collect();
+        for (key, e) in touching {
+            self.spans.remove(&key);
+            start = start.min(key.1 .1);
+            end = end.max(e);
+        }
+        self.spans.insert((dot, (actor, start)), end);
+    }
+
+    /// `(actor, span, remove id)` for every recorded span.
+    fn iter(&self) -> impl Iterator<Item = (NodeId, Range<u64>, ElementId)> + '_ {
+        self.spans
+            .iter()
+            .map(|(&(dot, (actor, start)), &end)| (actor, start..end, dot))
+    }
+
+    fn clear(&mut self) {
+        self.spans.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.spans.len()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for RemoveTable {
+    fn serialize<Ser: serde::Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for RemoveTable {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let spans: Vec<(NodeId, Range<u64>, ElementId)> =
+            serde::Deserialize::deserialize(deserializer)?;
+        let mut table = Self::default();
+        for (actor, span, dot) in spans {
+            table.insert(dot, actor, span);
+        }
+        Ok(table)
+    }
+}
+
+/// Rope of runs counting visible elements and indexed by id.
+fn node_store<T, S>(nodes: Vec<RgaNode<T, S>>) -> ChunkedVec<RgaNode<T, S>>
+where
+    T: Clone + Ord,
+    S: Run<T>,
+{
+    let nodes = nodes.into_iter().flat_map(RgaNode::into_capped).collect();
+    ChunkedVec::from_vec_with_measure(nodes, RgaNode::metrics).indexed_by(RgaNode::id_span)
+}
+
+#[cfg(feature = "serde")]
+fn deserialize_nodes<'de, D, T, S>(
+    deserializer: D,
+) -> Result<ChunkedVec<RgaNode<T, S>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Clone + Ord,
+    S: Run<T> + serde::Deserialize<'de>,
+{
+    let nodes: Vec<RgaNode<T, S>> = serde::Deserialize::deserialize(deserializer)?;
+    Ok(node_store(nodes.into_iter().map(RgaNode::measured).collect()))
 }
 
 /// A Replicated Growable Array (RGA) — an ordered sequence CRDT.
@@ -49,6 +934,26 @@ pub struct RgaNode<T: Clone + Ord> {
 /// a unique identifier `(actor, counter)` which determines causal
 /// ordering.
 ///
+/// Every element also records its left and right neighbours at the time
+/// it was inserted. Remote elements are placed between those origins
+/// with the YATA integration rules, so runs typed concurrently at the
+/// same spot stay contiguous instead of interleaving.
+///
+/// Elements typed one after another by the same actor are stored as a
+/// single [`RgaNode`] whose values live in an `S` (see [`Run`]), rather
+/// than one node per element.
+///
+/// [`move_item`](Self::move_item) moves an element without copying it:
+/// concurrent moves of the same element resolve last-writer-wins, so it
+/// still appears exactly once after merging. [`set`](Self::set) replaces
+/// an element's value in place, last-writer-wins as well, so the element
+/// keeps its id and position.
+///
+/// Inserts can record when they happened
+/// ([`insert_run_with_timestamp`](Self::insert_run_with_timestamp)), and
+/// [`blame`](Self::blame) reports who inserted each visible element and
+/// when. Timestamps are optional and cost nothing when unused.
+///
 /// # Example
 ///
 /// ```
@@ -72,110 +977,512 @@ pub struct RgaNode<T: Clone + Ord> {
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-pub struct Rga<T: Clone + Ord> {
+pub struct Rga<T: Clone + Ord, S: Run<T> = Vec<T>> {
     actor: NodeId,
     counter: u64,
-    /// Ordered sequence of elements (including tombstones), backed by a chunked rope.
-    elements: ChunkedVec<RgaNode<T>>,
+    /// Ordered sequence of runs (including tombstones), backed by a chunked
+    /// rope that counts visible elements per chunk. Adjacent runs that could
+    /// be joined always are, so equal sequences have equal runs.
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            deserialize_with = "deserialize_nodes",
+            bound(deserialize = "S: serde::Deserialize<'de>")
+        )
+    )]
+    elements: ChunkedVec<RgaNode<T, S>>,
+    /// Positions created by [`move_item`](Self::move_item).
+    moves: MoveTable,
+    /// Values written by [`set`](Self::set).
+    updates: UpdateTable<T>,
+    /// Timestamps of inserts that recorded one.
+    stamps: StampTable,
+    /// Ids deleted by each remove.
+    removes: RemoveTable,
     /// Version vector: max counter observed per actor.
     version: BTreeMap<NodeId, u64>,
-    /// Cached count of visible (non-tombstoned) elements.
-    visible_len: usize,
 }
 
 impl<T: Clone + Ord> Rga<T> {
     /// Create a new empty RGA for the given node.
     pub fn new(actor: NodeId) -> Self {
+        Self::with_runs(actor)
+    }
+
+    /// Get a reference to the element at the given index in the visible sequence.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&T> {
+        let (raw, offset) = self.elements.find_by(|m| m.visible, index)?;
+        Some(&self.elements[raw].values[offset])
+    }
+
+    /// Iterate over the visible elements in order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        self.elements
+            .iter()
+            .filter(|n| n.is_visible())
+            .flat_map(|n| n.values.iter())
+    }
+}
+
+impl<T: Clone + Ord, S: Run<T>> Rga<T, S> {
+    /// Create a new empty RGA for the given node that stores runs as `S`;
+    /// [`Rga::new`] stores them as `Vec<T>`.
+    pub fn with_runs(actor: NodeId) -> Self {
         Self {
             actor,
             counter: 0,
-            elements: ChunkedVec::new(),
+            elements: node_store(Vec::new()),
+            moves: MoveTable::default(),
+            updates: UpdateTable::default(),
+            stamps: StampTable::default(),
+            removes: RemoveTable::default(),
             version: BTreeMap::new(),
-            visible_len: 0,
         }
     }
 
-    /// Create a fork of this replica with a different node ID.
-    pub fn fork(&self, new_actor: NodeId) -> Self {
-        Self {
-            actor: new_actor,
-            counter: self.counter,
-            elements: self.elements.clone(),
-            version: self.version.clone(),
-            visible_len: self.visible_len,
+    /// Create a fork of this replica with a different node ID.
+    pub fn fork(&self, new_actor: NodeId) -> Self {
+        Self {
+            actor: new_actor,
+            counter: self.counter,
+            elements: self.elements.clone(),
+            moves: self.moves.clone(),
+            updates: self.updates.clone(),
+            stamps: self.stamps.clone(),
+            removes: self.removes.clone(),
+            version: self.version.clone(),
+        }
+    }
+
+    /// Insert a value at the given index in the visible sequence.
+    pub fn insert_at(&mut self, index: usize, value: T) -> Result<(), RgaError> {
+        self.insert_run(index, S::single(value))
+    }
+
+    /// Insert all values of `run` at the given index in the visible
+    /// sequence, as if they were inserted one after another.
+    pub fn insert_run(&mut self, index: usize, run: S) -> Result<(), RgaError> {
+        self.insert_span(index, run).map(|_| ())
+    }
+
+    /// [`insert_run`](Self::insert_run), recording `timestamp` (e.g. from a
+    /// [`HybridClock`](crate::clock::HybridClock)) as the time of the
+    /// insert for [`blame`](Self::blame).
+    pub fn insert_run_with_timestamp(
+        &mut self,
+        index: usize,
+        run: S,
+        timestamp: HybridTimestamp,
+    ) -> Result<(), RgaError> {
+        let ((actor, first), len) = self.insert_span(index, run)?;
+        if len > 0 {
+            self.stamps.insert(actor, first..first + len as u64, timestamp);
+        }
+        Ok(())
+    }
+
+    /// [`insert_run`](Self::insert_run), returning the id of the first
+    /// inserted element and the number inserted.
+    pub(crate) fn insert_span(
+        &mut self,
+        index: usize,
+        run: S,
+    ) -> Result<((NodeId, u64), usize), RgaError> {
+        if index > self.len() {
+            return Err(RgaError::IndexOutOfBounds {
+                index,
+                len: self.len(),
+            });
+        }
+        let len = run.count();
+        if len == 0 {
+            return Ok(((self.actor, self.counter + 1), 0));
+        }
+        Ok((self.insert_unchecked(index, run, len), len))
+    }
+
+    /// Insert `run` just before element `at`, tombstoned or not (at the
+    /// end if `at` is unknown); returns the id of the first inserted element.
+    pub(crate) fn insert_at_id(&mut self, at: (NodeId, u64), run: S) -> (NodeId, u64) {
+        let len = run.count();
+        let Some((raw, offset)) = self.raw_position(at) else {
+            return self.insert_unchecked(self.len(), run, len);
+        };
+        self.split(raw, offset);
+        let raw = raw + usize::from(offset > 0);
+        let origin_left = raw.checked_sub(1).map(|before| {
+            let node = &self.elements[before];
+            node.id_at(node.len - 1)
+        });
+        self.insert_raw(raw, origin_left, run, len)
+    }
+
+    /// Insert the `len` values of `run` at a valid visible index, returning
+    /// the id of the first.
+    fn insert_unchecked(&mut self, index: usize, run: S, len: usize) -> (NodeId, u64) {
+        let (raw, origin_left) = match index.checked_sub(1) {
+            None => (0, None),
+            Some(before) => {
+                let (raw, offset) = self.visible_to_raw(before);
+                self.split(raw, offset + 1);
+                (raw + 1, Some(self.elements[raw].id_at(offset)))
+            }
+        };
+        self.insert_raw(raw, origin_left, run, len)
+    }
+
+    /// Insert the `len` values of `run` from run `raw` on, after
+    /// `origin_left`.
+    fn insert_raw(
+        &mut self,
+        raw: usize,
+        origin_left: Option<(NodeId, u64)>,
+        run: S,
+        len: usize,
+    ) -> (NodeId, u64) {
+        let origin_right = self.elements.get(raw).map(|n| n.id);
+        let id = self.next_ids(len);
+        let runs = RgaNode::new(id, run, origin_left, origin_right).into_capped();
+        let last = raw + runs.len() - 1;
+        for (i, node) in runs.into_iter().enumerate() {
+            self.elements.insert(raw + i, node);
+        }
+        self.join_next(last);
+        if raw > 0 {
+            self.join_next(raw - 1);
+        }
+        id
+    }
+
+    /// Remove the element at the given index from the visible sequence.
+    pub fn remove(&mut self, index: usize) -> Result<T, RgaError> {
+        if index >= self.len() {
+            return Err(RgaError::IndexOutOfBounds {
+                index,
+                len: self.len(),
+            });
+        }
+        let (raw, offset) = self.visible_to_raw(index);
+        let node = &self.elements[raw];
+        let id = node.id_at(offset);
+        let value = node.values.values().nth(offset);
+        let dot = self.next_id();
+        self.remove_known(dot, id, 1);
+        Ok(value.expect("offset lies within the run"))
+    }
+
+    /// Remove `count` visible elements starting at `start`.
+    ///
+    /// Each run of consecutive ids in the range is tombstoned in one step,
+    /// and a delta carries it as a single span.
+    pub fn remove_range(&mut self, start: usize, count: usize) -> Result<(), RgaError> {
+        self.remove_span(start, count).map(|_| ())
+    }
+
+    /// Remove `count` visible elements from `start` on, returning each
+    /// stretch of consecutive ids removed as its first id and values.
+    pub(crate) fn remove_span(
+        &mut self,
+        start: usize,
+        count: usize,
+    ) -> Result<Vec<(ElementId, S)>, RgaError> {
+        let len = self.len();
+        let end = start.saturating_add(count);
+        if end > len {
+            return Err(RgaError::IndexOutOfBounds { index: end, len });
+        }
+        let mut removed = Vec::new();
+        if count == 0 {
+            return Ok(removed);
+        }
+        let dot = self.next_id();
+        let mut left = count;
+        while left > 0 {
+            let (raw, offset) = self.visible_to_raw(start);
+            let node = &self.elements[raw];
+            let take = (node.len - offset).min(left);
+            let piece = node.slice(offset, offset + take);
+            self.remove_known(dot, piece.id, take);
+            removed.push((piece.id, piece.values));
+            left -= take;
+        }
+        Ok(removed)
+    }
+
+    /// Remove whatever is not yet deleted among the `len` elements from
+    /// `first` on, returning each stretch removed as its first id and
+    /// values.
+    pub(crate) fn remove_ids(
+        &mut self,
+        first: (NodeId, u64),
+        len: usize,
+    ) -> Vec<(ElementId, S)> {
+        let (actor, start) = first;
+        let end = start + len as u64;
+        let mut removed = Vec::new();
+        let mut counter = start;
+        while counter < end {
+            let Some((raw, offset)) = self.raw_position((actor, counter)) else {
+                match self.elements.first_id_after((actor, counter)) {
+                    Some((a, next)) if a == actor && next < end => counter = next,
+                    _ => break,
+                }
+                continue;
+            };
+            let node = &self.elements[raw];
+            let take = (node.len - offset).min((end - counter) as usize);
+            if !node.deleted {
+                let piece = node.slice(offset, offset + take);
+                removed.push((piece.id, piece.values));
+            }
+            counter += take as u64;
+        }
+        if !removed.is_empty() {
+            let dot = self.next_id();
+            for (id, values) in &removed {
+                self.remove_known(dot, *id, values.count());
+            }
+        }
+        removed
+    }
+
+    /// Move the element at index `from` so that it ends up at index `to`.
+    ///
+    /// The element is not copied: it gets a new position, and the old one
+    /// is left behind as an invisible placeholder. If replicas move the same
+    /// element concurrently, the move with the greatest `(counter, actor)`
+    /// id wins and the element still appears exactly once. A concurrent
+    /// [`remove`](Self::remove) wins over any move.
+    pub fn move_item(&mut self, from: usize, to: usize) -> Result<(), RgaError> {
+        let len = self.len();
+        if let Some(index) = [from, to].into_iter().find(|&i| i >= len) {
+            return Err(RgaError::IndexOutOfBounds { index, len });
         }
+        let (raw, offset) = self.visible_to_raw(from);
+        let node = &self.elements[raw];
+        let position = node.id_at(offset);
+        let value = node.values.values().nth(offset);
+        let item = self.moves.item_of(position);
+
+        self.set_flags(position, false, true, &mut Patches::off());
+        let value = value.expect("offset lies within the run");
+        let new_position = self.insert_unchecked(to, S::single(value), 1);
+        self.moves.insert(new_position, item);
+        Ok(())
     }
 
-    /// Insert a value at the given index in the visible sequence.
-    pub fn insert_at(&mut self, index: usize, value: T) -> Result<(), RgaError> {
-        if index > self.visible_len {
+    /// Replace the value of the element at the given visible index,
+    /// returning the old value.
+    ///
+    /// The element keeps its id and position, so concurrent edits around it
+    /// and moves of it are unaffected. If replicas set the same element
+    /// concurrently, the update with the greatest `(counter, actor)` id
+    /// wins.
+    pub fn set(&mut self, index: usize, value: T) -> Result<T, RgaError> {
+        if index >= self.len() {
             return Err(RgaError::IndexOutOfBounds {
                 index,
-                len: self.visible_len,
+                len: self.len(),
             });
         }
+        let (raw, offset) = self.visible_to_raw(index);
+        let node = &self.elements[raw];
+        let item = self.moves.item_of(node.id_at(offset));
+        let old = node.values.values().nth(offset);
+        let update = self.next_id();
+        self.updates.insert(update, item, value);
+        self.refresh_value(item, &mut Patches::off());
+        Ok(old.expect("offset lies within the run"))
+    }
 
-        self.counter += 1;
-        let id = (self.actor, self.counter);
-        self.version
-            .entry(self.actor)
-            .and_modify(|c| *c = (*c).max(self.counter))
-            .or_insert(self.counter);
+    /// [`merge`](Crdt::merge) `other` into this replica, returning how the
+    /// visible sequence changed as [`Splice`]s.
+    pub fn merge_with_patches(&mut self, other: &Self) -> Vec<Splice<S>>
+    where
+        S: Default,
+    {
+        let mut patches = Patches::on();
+        self.merge_recording(other, &mut patches);
+        patches.finish()
+    }
 
-        let node = RgaNode {
-            id,
-            value,
-            deleted: false,
+    /// [`apply_delta`](DeltaCrdt::apply_delta) `delta` to this replica,
+    /// returning how the visible sequence changed as [`Splice`]s.
+    pub fn apply_delta_with_patches(&mut self, delta: &RgaDelta<T, S>) -> Vec<Splice<S>>
+    where
+        S: Default,
+    {
+        let mut patches = Patches::on();
+        self.apply_delta_recording(delta, &mut patches);
+        patches.finish()
+    }
+
+    /// Allocate the next `(actor, counter)` id and record it in the version.
+    pub(crate) fn next_id(&mut self) -> (NodeId, u64) {
+        self.next_ids(1)
+    }
+
+    /// Whether the element `anchor` is bound to (if any) is known here.
+    pub(crate) fn knows(&self, anchor: Anchor) -> bool {
+        anchor
+            .target
+            .map_or(true, |id| self.raw_position(id).is_some())
+    }
+
+    /// Visible index of the value at which `offset` units of the metric
+    /// `key` begin, where `width` gives the units of each value (e.g. its
+    /// UTF-16 length). `None` if `offset` is past the end or falls inside
+    /// a value.
+    pub(crate) fn index_at_offset(
+        &self,
+        key: fn(&Metrics) -> usize,
+        width: fn(&T) -> usize,
+        offset: usize,
+    ) -> Option<usize> {
+        if offset == key(&self.elements.metrics()) {
+            return Some(self.len());
+        }
+        let (raw, mut within) = self.elements.find_by(key, offset)?;
+        let before = self.elements.metrics_before(raw).visible;
+        for (i, value) in self.elements[raw].values.values().enumerate() {
+            if within == 0 {
+                return Some(before + i);
+            }
+            within = within.checked_sub(width(&value))?;
+        }
+        unreachable!("offset lies within the run")
+    }
+
+    /// Visible index of the value holding unit `n` (counting from 0) of the
+    /// metric `key`, e.g. the `n`-th line break; `None` if there are not
+    /// that many units.
+    pub(crate) fn index_of_unit(
+        &self,
+        key: fn(&Metrics) -> usize,
+        width: fn(&T) -> usize,
+        n: usize,
+    ) -> Option<usize> {
+        let (raw, mut within) = self.elements.find_by(key, n)?;
+        let before = self.elements.metrics_before(raw).visible;
+        for (i, value) in self.elements[raw].values.values().enumerate() {
+            let units = width(&value);
+            if within < units {
+                return Some(before + i);
+            }
+            within -= units;
+        }
+        unreachable!("unit lies within the run")
+    }
+
+    /// Visible values from index `index` on.
+    pub(crate) fn values_from(&self, index: usize) -> impl Iterator<Item = T> + '_ {
+        let (raw, offset) = if index < self.len() {
+            self.visible_to_raw(index)
+        } else {
+            (self.elements.len(), 0)
         };
+        self.elements
+            .iter_from(raw)
+            .filter(|n| n.is_visible())
+            .flat_map(|n| n.values.values())
+            .skip(offset)
+    }
 
-        let raw_index = self.raw_index_for_insert(index);
-        self.elements.insert(raw_index, node);
-        self.visible_len += 1;
-        Ok(())
+    /// Units of the metric `key` before visible index `index`, where `width`
+    /// gives the units of each value; `None` if `index > len()`.
+    pub(crate) fn offset_at_index(
+        &self,
+        key: fn(&Metrics) -> usize,
+        width: fn(&T) -> usize,
+        index: usize,
+    ) -> Option<usize> {
+        match index.cmp(&self.len()) {
+            core::cmp::Ordering::Greater => None,
+            core::cmp::Ordering::Equal => Some(key(&self.elements.metrics())),
+            core::cmp::Ordering::Less => {
+                let (raw, offset) = self.visible_to_raw(index);
+                let before = key(&self.elements.metrics_before(raw));
+                let within: usize = self.elements[raw]
+                    .values
+                    .values()
+                    .take(offset)
+                    .map(|value| width(&value))
+                    .sum();
+                Some(before + within)
+            }
+        }
     }
 
-    /// Remove the element at the given index from the visible sequence.
-    pub fn remove(&mut self, index: usize) -> Result<T, RgaError> {
-        if index >= self.visible_len {
+    /// Metrics of the whole sequence.
+    pub(crate) fn metrics(&self) -> Metrics {
+        self.elements.metrics()
+    }
+
+    /// Visible runs in order, for wrappers that render them directly.
+    pub(crate) fn visible_runs(&self) -> impl Iterator<Item = &S> + '_ {
+        self.elements
+            .iter()
+            .filter(|n| n.is_visible())
+            .map(|n| &n.values)
+    }
+
+    /// Create an [`Anchor`] at the given position in the visible sequence
+    /// (`0..=len()`).
+    pub fn anchor_at(&self, index: usize, gravity: Gravity) -> Result<Anchor, RgaError> {
+        if index > self.len() {
             return Err(RgaError::IndexOutOfBounds {
                 index,
-                len: self.visible_len,
+                len: self.len(),
             });
         }
-        let raw = self.visible_to_raw(index);
-        self.elements[raw].deleted = true;
-        self.visible_len -= 1;
-        Ok(self.elements[raw].value.clone())
+        let bound = match gravity {
+            Gravity::Left => index.checked_sub(1),
+            Gravity::Right => Some(index).filter(|&i| i < self.len()),
+        };
+        Ok(Anchor {
+            target: bound.map(|i| {
+                let (raw, offset) = self.visible_to_raw(i);
+                self.elements[raw].id_at(offset)
+            }),
+            gravity,
+        })
     }
 
-    /// Get a reference to the element at the given index in the visible sequence.
+    /// Current position of `anchor` in the visible sequence.
+    ///
+    /// An anchor whose element is unknown to this replica (not merged yet,
+    /// or removed by [`compact_tombstones`](Self::compact_tombstones))
+    /// resolves like an unbound one: to the start for [`Gravity::Left`],
+    /// to the end for [`Gravity::Right`].
     #[must_use]
-    pub fn get(&self, index: usize) -> Option<&T> {
-        if index >= self.visible_len {
-            return None;
+    pub fn resolve(&self, anchor: Anchor) -> usize {
+        match anchor.target.and_then(|id| self.raw_position(id)) {
+            Some((raw, offset)) => {
+                let before = self.elements.metrics_before(raw).visible;
+                if !self.elements[raw].is_visible() {
+                    before
+                } else {
+                    before + offset + usize::from(anchor.gravity == Gravity::Left)
+                }
+            }
+            None => match anchor.gravity {
+                Gravity::Left => 0,
+                Gravity::Right => self.len(),
+            },
         }
-        let raw = self.visible_to_raw(index);
-        Some(&self.elements[raw].value)
     }
 
     /// Get the number of visible (non-tombstoned) elements.
     #[must_use]
     pub fn len(&self) -> usize {
-        self.visible_len
+        self.elements.metrics().visible
     }
 
     /// Check if the visible sequence is empty.
     #[must_use]
     pub fn is_empty(&self) -> bool {
-        self.visible_len == 0
-    }
-
-    /// Iterate over the visible elements in order.
-    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
-        self.elements
-            .iter()
-            .filter(|n| !n.deleted)
-            .map(|n| &n.value)
+        self.len() == 0
     }
 
     /// Get this replica's node ID.
@@ -187,29 +1494,70 @@ impl<T: Clone + Ord> Rga<T> {
     /// Collect visible elements into a `Vec`.
     #[must_use]
     pub fn to_vec(&self) -> Vec<T> {
-        self.iter().cloned().collect()
+        self.visible_runs().flat_map(Run::values).collect()
+    }
+
+    /// Who inserted the visible elements and when, as runs of consecutive
+    /// elements with the same author and timestamp.
+    ///
+    /// A moved element is attributed to whoever inserted it, not to whoever
+    /// moved it.
+    #[must_use]
+    pub fn blame(&self) -> Vec<BlameRun> {
+        let ids = self
+            .elements
+            .iter()
+            .filter(|n| n.is_visible())
+            .flat_map(RgaNode::ids);
+        let mut runs: Vec<BlameRun> = Vec::new();
+        for (index, id) in ids.enumerate() {
+            let (actor, counter) = self.moves.item_of(id);
+            let timestamp = self.stamps.get((actor, counter));
+            match runs.last_mut() {
+                Some(last) if last.actor == actor && last.timestamp == timestamp => {
+                    last.range.end = index + 1;
+                }
+                _ => runs.push(BlameRun {
+                    range: index..index + 1,
+                    actor,
+                    timestamp,
+                }),
+            }
+        }
+        runs
+    }
+
+    /// Get the version vector of this sequence.
+    #[must_use]
+    pub fn version_vector(&self) -> VersionVector {
+        VersionVector::from(self.version.clone())
     }
 
-    /// Returns the number of tombstoned (deleted) elements.
+    /// Returns the number of tombstoned elements: deleted ones, and the
+    /// placeholders moved elements leave behind.
     #[must_use]
     pub fn tombstone_count(&self) -> usize {
-        self.elements.iter().filter(|n| n.deleted).count()
+        self.raw_len() - self.len()
     }
 
     /// Returns the total number of elements (including tombstones).
     #[must_use]
     pub fn raw_len(&self) -> usize {
-        self.elements.len()
+        self.elements.metrics().units
     }
 
     /// Remove all tombstoned elements from the internal storage.
     ///
+    /// Placeholders left behind by [`move_item`](Self::move_item) are
+    /// kept, so moves that arrive later still find every position of their
+    /// item. Moves and updates of deleted items are forgotten with them.
+    ///
     /// # UNSAFE — Read before using
     ///
-    /// **This method can cause replica divergence.** The RGA insertion
-    /// algorithm (`find_insert_position`) depends on tombstoned elements
-    /// to determine correct causal ordering. Removing them changes raw
-    /// indices and can cause concurrent inserts to land at wrong positions.
+    /// **This method can cause replica divergence.** Remote inserts are
+    /// placed between their left and right origins, which are often
+    /// tombstoned elements. Once an origin is removed, concurrent inserts
+    /// referencing it can land at wrong positions.
     ///
     /// **Only safe when ALL of these conditions are met:**
     /// 1. All replicas have fully converged (identical state)
@@ -222,223 +1570,868 @@ impl<T: Clone + Ord> Rga<T> {
     ///
     /// Returns the number of tombstones removed.
     pub fn compact_tombstones(&mut self) -> usize {
-        let before = self.elements.len();
-        let mut kept = ChunkedVec::new();
-        for node in self.elements.iter() {
-            if !node.deleted {
-                kept.push(node.clone());
+        let before = self.raw_len();
+        let mut kept: Vec<RgaNode<T, S>> = Vec::new();
+        for node in self.elements.iter().filter(|n| !n.deleted) {
+            match kept.last_mut() {
+                Some(last) if last.continued_by(node) => last.absorb(node.clone()),
+                _ => kept.push(node.clone()),
             }
         }
-        let removed = before - kept.len();
-        self.elements = kept;
-        removed
+        self.elements = node_store(kept);
+        // A moved item's original element is a kept placeholder unless the
+        // item was deleted.
+        let elements = &self.elements;
+        self.moves
+            .retain_items(|item| elements.position_of(item).is_some());
+        self.updates
+            .retain_items(|item| elements.position_of(item).is_some());
+        // Every id a remove deleted is gone now.
+        self.removes.clear();
+        before - self.raw_len()
     }
 
     // ---- internal helpers ----
 
-    fn visible_to_raw(&self, visible: usize) -> usize {
-        let mut seen = 0;
-        for (raw, node) in self.elements.iter().enumerate() {
-            if !node.deleted {
-                if seen == visible {
-                    return raw;
+    /// Allocate `n` consecutive ids, returning the first.
+    fn next_ids(&mut self, n: usize) -> (NodeId, u64) {
+        let first = self.counter + 1;
+        self.counter += n as u64;
+        self.version
+            .entry(self.actor)
+            .and_modify(|c| *c = (*c).max(self.counter))
+            .or_insert(self.counter);
+        (self.actor, first)
+    }
+
+    /// Run holding the given visible element, and the element's offset in it.
+    fn visible_to_raw(&self, visible: usize) -> (usize, usize) {
+        self.elements
+            .find_by(|m| m.visible, visible)
+            .unwrap_or_else(|| {
+                panic!(
+                    "visible index {} not found (only {} visible elements)",
+                    visible,
+                    self.len()
+                )
+            })
+    }
+
+    fn raw_position(&self, id: (NodeId, u64)) -> Option<(usize, usize)> {
+        self.elements.position_of(id)
+    }
+
+    /// Split run `raw` before its element `at`, unless that is an end.
+    fn split(&mut self, raw: usize, at: usize) {
+        if at > 0 && at < self.elements[raw].len {
+            let rest = self.elements.update(raw, |node| node.split_off(at));
+            self.elements.insert(raw + 1, rest);
+        }
+    }
+
+    /// Split run `raw` so that its elements `from..to` form a run of their
+    /// own, returning that run's index.
+    fn isolate(&mut self, raw: usize, from: usize, to: usize) -> usize {
+        self.split(raw, to);
+        self.split(raw, from);
+        if from > 0 {
+            raw + 1
+        } else {
+            raw
+        }
+    }
+
+    /// Join run `raw` with the next one if it continues it.
+    fn join_next(&mut self, raw: usize) {
+        let continued = match (self.elements.get(raw), self.elements.get(raw + 1)) {
+            (Some(node), Some(next)) => node.continued_by(next),
+            _ => false,
+        };
+        if continued {
+            let next = self.elements.remove(raw + 1);
+            self.elements.update(raw, |node| node.absorb(next));
+        }
+    }
+
+    /// Join run `raw` with its neighbours where they line up.
+    fn squash_around(&mut self, raw: usize) {
+        self.join_next(raw);
+        if raw > 0 {
+            self.join_next(raw - 1);
+        }
+    }
+
+    /// Tombstone the `len` elements from `first` on as the remove `dot`,
+    /// recording it for deltas.
+    fn remove_known(&mut self, dot: ElementId, (actor, first): ElementId, len: usize) {
+        self.removes.insert(dot, actor, first..first + len as u64);
+        self.tombstone((actor, first), len, &mut Patches::off());
+    }
+
+    /// Tombstone the `len` elements from `first` on that are known here,
+    /// and every position of the moved items among them.
+    fn tombstone(&mut self, first: (NodeId, u64), len: usize, patches: &mut Patches<S>) {
+        self.mark_deleted(first, len, patches);
+        for item in self.moves.items_in(first, len) {
+            let positions: Vec<_> = self.moves.positions(item).collect();
+            for id in positions {
+                self.mark_deleted(id, 1, patches);
+            }
+        }
+    }
+
+    /// Set the deleted flag of the `len` elements from `first` on that are
+    /// known here.
+    fn mark_deleted(&mut self, first: (NodeId, u64), len: usize, patches: &mut Patches<S>) {
+        let (actor, start) = first;
+        let end = start + len as u64;
+        let mut counter = start;
+        while counter < end {
+            let Some((raw, offset)) = self.raw_position((actor, counter)) else {
+                match self.elements.first_id_after((actor, counter)) {
+                    Some((a, next)) if a == actor && next < end => counter = next,
+                    _ => break,
                 }
-                seen += 1;
+                continue;
+            };
+            let take = (self.elements[raw].len - offset).min((end - counter) as usize);
+            if !self.elements[raw].deleted {
+                let raw = self.isolate(raw, offset, offset + take);
+                if patches.is_on() && self.elements[raw].is_visible() {
+                    patches.delete(self.elements.metrics_before(raw).visible, take);
+                }
+                self.elements.update(raw, |node| node.deleted = true);
+                self.squash_around(raw);
             }
+            counter += take as u64;
+        }
+    }
+
+    /// Set the flags of element `id`, if it is known here.
+    fn set_flags(
+        &mut self,
+        id: (NodeId, u64),
+        deleted: bool,
+        moved: bool,
+        patches: &mut Patches<S>,
+    ) {
+        let Some((raw, offset)) = self.raw_position(id) else {
+            return;
+        };
+        let node = &self.elements[raw];
+        if node.deleted != deleted || node.moved != moved {
+            let raw = self.isolate(raw, offset, offset + 1);
+            let node = &self.elements[raw];
+            if patches.is_on() && node.is_visible() != (!deleted && !moved) {
+                let index = self.elements.metrics_before(raw).visible;
+                if node.is_visible() {
+                    patches.delete(index, 1);
+                } else {
+                    patches.insert(index, node.values.clone(), 1);
+                }
+            }
+            self.elements.update(raw, |node| {
+                node.deleted = deleted;
+                node.moved = moved;
+            });
+            self.squash_around(raw);
+        }
+    }
+
+    /// Bring the flags of `item`'s known positions in line with the move
+    /// table: only the newest position is visible, and once any position
+    /// is deleted they all are.
+    fn settle(&mut self, item: ElementId, patches: &mut Patches<S>) {
+        let positions: Vec<_> = self.moves.positions(item).collect();
+        let current = positions
+            .iter()
+            .copied()
+            .max_by_key(|&(actor, counter)| (counter, actor));
+        let deleted = positions.iter().any(|&id| {
+            self.raw_position(id)
+                .is_some_and(|(raw, _)| self.elements[raw].deleted)
+        });
+        for id in positions {
+            self.set_flags(id, deleted, Some(id) != current, patches);
         }
-        panic!(
-            "visible index {} not found (only {} visible elements)",
-            visible, seen
-        );
     }
 
-    fn raw_index_for_insert(&self, visible_index: usize) -> usize {
-        if visible_index == 0 {
-            return 0;
+    /// Record remote moves, then settle every item they or the `fresh`
+    /// runs of elements touch.
+    fn apply_moves(
+        &mut self,
+        moves: impl Iterator<Item = (ElementId, ElementId)>,
+        fresh: &[(ElementId, usize)],
+        patches: &mut Patches<S>,
+    ) {
+        let mut touched = BTreeSet::new();
+        for (position, item) in moves {
+            if self.moves.insert(position, item) {
+                touched.insert(item);
+            }
+        }
+        for &(first, len) in fresh {
+            touched.extend(self.moves.items_in(first, len));
+        }
+        for item in touched {
+            self.settle(item, patches);
+        }
+    }
+
+    /// Record remote updates, then write the winning value of every item
+    /// they or the `fresh` runs of elements touch into its positions.
+    fn apply_updates<'a>(
+        &mut self,
+        updates: impl Iterator<Item = (ElementId, ElementId, &'a T)>,
+        fresh: &[(ElementId, usize)],
+        patches: &mut Patches<S>,
+    ) where
+        T: 'a,
+    {
+        let mut touched = BTreeSet::new();
+        for (update, item, value) in updates {
+            if self.updates.insert(update, item, value.clone()) {
+                touched.insert(item);
+            }
+        }
+        for &(first, len) in fresh {
+            touched.extend(self.moves.items_in(first, len));
+            touched.extend(self.updates.items_in(first, len));
+        }
+        for item in touched {
+            self.refresh_value(item, patches);
+        }
+    }
+
+    /// Write the winning value of `item`, if it was ever updated, into each
+    /// of its known positions.
+    fn refresh_value(&mut self, item: ElementId, patches: &mut Patches<S>) {
+        let Some(value) = self.updates.get(item).cloned() else {
+            return;
+        };
+        let positions: Vec<_> = self.moves.positions(item).collect();
+        for id in positions {
+            let Some((raw, offset)) = self.raw_position(id) else {
+                continue;
+            };
+            if self.elements[raw].values.values().nth(offset).as_ref() == Some(&value) {
+                continue;
+            }
+            let raw = self.isolate(raw, offset, offset + 1);
+            if patches.is_on() && self.elements[raw].is_visible() {
+                let index = self.elements.metrics_before(raw).visible;
+                patches.delete(index, 1);
+                patches.insert(index, S::single(value.clone()), 1);
+            }
+            self.elements
+                .update(raw, |node| node.set_values(S::single(value.clone())));
+            self.squash_around(raw);
+        }
+    }
+
+    /// Parts of `node` whose elements are not known here yet.
+    fn unknown_parts(&self, node: &RgaNode<T, S>, out: &mut Vec<RgaNode<T, S>>) {
+        let (actor, start) = node.id;
+        let end = start + node.len as u64;
+        let mut counter = start;
+        while counter < end {
+            if let Some((raw, offset)) = self.raw_position((actor, counter)) {
+                counter += (self.elements[raw].len - offset) as u64;
+                continue;
+            }
+            let stop = match self.elements.first_id_after((actor, counter)) {
+                Some((a, next)) if a == actor => next.min(end),
+                _ => end,
+            };
+            out.push(node.slice((counter - start) as usize, (stop - start) as usize));
+            counter = stop;
+        }
+    }
+
+    /// Index where a remote `node` belongs among the runs `start..right`,
+    /// which lie strictly between its origins (YATA integration).
+    ///
+    /// Only the elements between the node's origins are concurrent with it.
+    /// Among those, elements with the same left origin are ordered by id,
+    /// and an element whose left origin lies inside the scanned range is
+    /// skipped together with the run it belongs to. The elements scanned
+    /// since `dest` last moved are the conflicting ones, so membership in
+    /// either set is a range check on the origin's index.
+    fn integrate_position(&self, node: &RgaNode<T, S>, start: usize, right: usize) -> usize {
+        let mut dest = start;
+        for i in start..right {
+            let o = &self.elements[i];
+            if o.origin_left == node.origin_left {
+                if o.id < node.id {
+                    dest = i + 1;
+                } else if o.origin_right == node.origin_right {
+                    break;
+                }
+                continue;
+            }
+            match o.origin_left.and_then(|id| self.raw_position(id)) {
+                Some((origin, _)) if (start..i).contains(&origin) => {
+                    if origin < dest {
+                        dest = i + 1;
+                    }
+                }
+                _ => break,
+            }
+        }
+        dest
+    }
+
+    /// Integrate one remote run whose elements are all unknown and whose
+    /// origins are present.
+    fn integrate_one(&mut self, node: RgaNode<T, S>, patches: &mut Patches<S>) {
+        // Split the runs holding the origins so that they become run
+        // boundaries; the scan then only sees whole concurrent runs.
+        let left = node.origin_left.and_then(|id| self.raw_position(id));
+        if let Some((raw, offset)) = left {
+            self.split(raw, offset + 1);
+        }
+        let right = node.origin_right.and_then(|id| self.raw_position(id));
+        if let Some((raw, offset)) = right {
+            self.split(raw, offset);
+        }
+        let left = left.map(|(raw, _)| raw);
+        let right = right.map(|(raw, offset)| raw + usize::from(offset > 0));
+
+        let start = left.map_or(0, |l| l + 1);
+        let dest = self.integrate_position(&node, start, right.unwrap_or(self.elements.len()));
+        if patches.is_on() && node.is_visible() {
+            let index = self.elements.metrics_before(dest).visible;
+            patches.insert(index, node.values.clone(), node.len);
+        }
+        self.elements.insert(dest, node);
+
+        // Re-join whatever the splits and the insert left joinable, from
+        // the back so that earlier indices stay valid.
+        if let Some(r) = right {
+            self.squash_around(r + 1);
+        }
+        self.squash_around(dest);
+        if let Some(l) = left {
+            self.squash_around(l);
+        }
+    }
+
+    /// Integrate remote runs, skipping elements already known, in causal
+    /// (counter) order. Returns the first id and length of each run that
+    /// was integrated.
+    fn integrate<'a>(
+        &mut self,
+        nodes: impl Iterator<Item = &'a RgaNode<T, S>>,
+        patches: &mut Patches<S>,
+    ) -> Vec<(ElementId, usize)>
+    where
+        T: 'a,
+        S: 'a,
+    {
+        let mut parts = Vec::new();
+        for node in nodes {
+            self.unknown_parts(node, &mut parts);
+        }
+        let mut fresh: Vec<_> = parts.into_iter().flat_map(RgaNode::into_capped).collect();
+        fresh.sort_by_key(|n| (n.id.1, n.id.0));
+        fresh.dedup_by_key(|n| n.id);
+
+        let spans = fresh.iter().map(|n| (n.id, n.len)).collect();
+        for node in fresh {
+            self.integrate_one(node, patches);
+        }
+        spans
+    }
+
+    fn merge_recording(&mut self, other: &Self, patches: &mut Patches<S>) {
+        // Phase 1: Apply tombstones, located through the id index.
+        for other_node in other.elements.iter() {
+            if other_node.deleted {
+                self.tombstone(other_node.id, other_node.len, patches);
+            }
+        }
+
+        // Phase 2: Integrate new elements between their origins.
+        let fresh = self.integrate(other.elements.iter(), patches);
+
+        // Phase 3: Let the newest position of each moved item win.
+        self.apply_moves(other.moves.iter(), &fresh, patches);
+
+        // Phase 4: Write the newest value of each updated item.
+        self.apply_updates(other.updates.iter(), &fresh, patches);
+        for (actor, span, timestamp) in other.stamps.iter() {
+            self.stamps.insert(actor, span, timestamp);
+        }
+        for (actor, span, dot) in other.removes.iter() {
+            self.removes.insert(dot, actor, span);
+        }
+        self.observe_version(&other.version);
+    }
+
+    fn apply_delta_recording(&mut self, delta: &RgaDelta<T, S>, patches: &mut Patches<S>) {
+        // Phase 1: Apply tombstones, a span of consecutive ids at a time.
+        for (actor, span, dot) in &delta.tombstoned_spans {
+            let len = usize::try_from(span.end.saturating_sub(span.start)).unwrap_or(usize::MAX);
+            self.tombstone((*actor, span.start), len, patches);
+            self.removes.insert(*dot, *actor, span.clone());
+        }
+
+        // Phase 2: Integrate new elements between their origins.
+        let fresh = self.integrate(delta.new_elements.iter(), patches);
+
+        // Phase 3: Let the newest position of each moved item win.
+        self.apply_moves(delta.moves.iter().copied(), &fresh, patches);
+
+        // Phase 4: Write the newest value of each updated item.
+        let updates = delta.updates.iter().map(|(update, item, value)| (*update, *item, value));
+        self.apply_updates(updates, &fresh, patches);
+        for (actor, span, timestamp) in &delta.timestamps {
+            self.stamps.insert(*actor, span.clone(), *timestamp);
+        }
+        self.observe_version(&delta.version);
+    }
+
+    fn observe_version(&mut self, version: &BTreeMap<NodeId, u64>) {
+        for (&actor, &cnt) in version {
+            let entry = self.version.entry(actor).or_insert(0);
+            *entry = (*entry).max(cnt);
+        }
+
+        if let Some(&max_cnt) = self.version.values().max() {
+            self.counter = self.counter.max(max_cnt);
+        }
+    }
+}
+
+/// Delta for [`Rga`]: elements and tombstones that the other replica is missing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RgaDelta<T: Clone + Ord, S: Run<T> = Vec<T>> {
+    /// Runs of elements that the other replica doesn't have yet, with their
+    /// origins.
+    pub new_elements: Vec<RgaNode<T, S>>,
+    /// Removes the other replica hasn't seen, as spans
+    /// `(actor, first counter..end counter, remove id)` of consecutive ids
+    /// that one remove deleted.
+    pub tombstoned_spans: Vec<(NodeId, Range<u64>, (NodeId, u64))>,
+    /// Moves the other replica hasn't seen: `(new position, moved item)`.
+    pub moves: Vec<((NodeId, u64), (NodeId, u64))>,
+    /// Winning [`Rga::set`] updates the other replica hasn't seen:
+    /// `(update id, item, value)`.
+    pub updates: Vec<(ElementId, ElementId, T)>,
+    /// Timestamps of inserts the other replica hasn't seen, as
+    /// `(actor, first counter..end counter, timestamp)`.
+    pub timestamps: Vec<(NodeId, Range<u64>, HybridTimestamp)>,
+    /// Version vector of the source.
+    pub version: BTreeMap<NodeId, u64>,
+}
+
+impl<T: Clone + Ord, S: Run<T>> DeltaCrdt for Rga<T, S> {
+    type Delta = RgaDelta<T, S>;
+
+    fn delta(&self, other: &Self) -> RgaDelta<T, S> {
+        self.delta_since(&other.version_vector())
+    }
+
+    fn apply_delta(&mut self, delta: &RgaDelta<T, S>) {
+        self.apply_delta_recording(delta, &mut Patches::off());
+    }
+}
+
+impl<T: Clone + Ord, S: Run<T>> DeltaSince for Rga<T, S> {
+    type Summary = VersionVector;
+
+    fn summary(&self) -> VersionVector {
+        self.version_vector()
+    }
+
+    fn delta_since(&self, since: &VersionVector) -> RgaDelta<T, S> {
+        // Origins travel with each run, so no context is needed. A run that
+        // `since` has seen part of is sent from its first unseen element.
+        let mut new_elements = Vec::new();
+        for node in self.elements.iter() {
+            let (actor, first) = node.id;
+            let seen = since.get(actor).saturating_add(1).saturating_sub(first);
+            let seen = usize::try_from(seen).map_or(node.len, |s| s.min(node.len));
+            if seen < node.len {
+                new_elements.push(node.slice(seen, node.len));
+            }
+        }
+        let tombstoned_spans = self
+            .removes
+            .iter()
+            .filter(|&(_, _, (actor, counter))| !since.contains(actor, counter))
+            .collect();
+
+        let moves = self
+            .moves
+            .iter()
+            .filter(|&((actor, counter), _)| !since.contains(actor, counter))
+            .collect();
+        let updates = self
+            .updates
+            .iter()
+            .filter(|&((actor, counter), _, _)| !since.contains(actor, counter))
+            .map(|(update, item, value)| (update, item, value.clone()))
+            .collect();
+        let timestamps = self
+            .stamps
+            .iter()
+            .filter(|(actor, span, _)| !since.contains(*actor, span.start))
+            .collect();
+
+        RgaDelta {
+            new_elements,
+            tombstoned_spans,
+            moves,
+            updates,
+            timestamps,
+            version: self.version.clone(),
+        }
+    }
+}
+
+impl<T: Clone + Ord, S: Run<T>> Crdt for Rga<T, S> {
+    fn merge(&mut self, other: &Self) {
+        self.merge_recording(other, &mut Patches::off());
+    }
+}
+
+impl Encode for Anchor {
+    fn encode(&self, w: &mut Writer) {
+        self.target.encode(w);
+        w.write_u8(match self.gravity {
+            Gravity::Left => 0,
+            Gravity::Right => 1,
+        });
+    }
+}
+
+impl Decode for Anchor {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        let target = Option::decode(r)?;
+        let gravity = match r.read_u8()? {
+            0 => Gravity::Left,
+            1 => Gravity::Right,
+            _ => return Err(CodecError::InvalidValue("gravity")),
+        };
+        Ok(Self { target, gravity })
+    }
+}
+
+fn node_actors<T, S>(node: &RgaNode<T, S>) -> impl Iterator<Item = NodeId>
+where
+    T: Clone + Ord,
+    S: Run<T>,
+{
+    [Some(node.id), node.origin_left, node.origin_right]
+        .into_iter()
+        .flatten()
+        .map(|id| id.0)
+}
+
+/// Runs as written to the wire: runs split only by [`MAX_RUN_LEN`] are
+/// joined again, so that long text costs one run header. Reading a state
+/// back caps them again.
+fn wire_runs<'a, T, S>(nodes: impl Iterator<Item = &'a RgaNode<T, S>>) -> Vec<RgaNode<T, S>>
+where
+    T: Clone + Ord + 'a,
+    S: Run<T> + 'a,
+{
+    let mut runs: Vec<RgaNode<T, S>> = Vec::new();
+    for node in nodes {
+        match runs.last_mut() {
+            Some(last) if last.continues_into(node) => last.absorb(node.clone()),
+            _ => runs.push(node.clone()),
         }
-        if visible_index >= self.visible_len {
-            return self.elements.len();
+    }
+    runs
+}
+
+fn write_node<T: Clone + Ord, S: Run<T> + Encode>(
+    w: &mut Writer,
+    table: &ActorTable,
+    node: &RgaNode<T, S>,
+) {
+    table.write_tag(w, node.id);
+    w.write_u8(u8::from(node.deleted) | u8::from(node.moved) << 1);
+    node.values.encode(w);
+    for origin in [node.origin_left, node.origin_right] {
+        match origin {
+            None => w.write_u8(0),
+            Some(id) => {
+                w.write_u8(1);
+                table.write_tag(w, id);
+            }
         }
-        self.visible_to_raw(visible_index)
     }
+}
+
+fn pair_actors(
+    moves: impl Iterator<Item = (ElementId, ElementId)>,
+) -> impl Iterator<Item = NodeId> {
+    moves.flat_map(|(position, item)| [position.0, item.0])
+}
+
+fn write_moves(
+    w: &mut Writer,
+    table: &ActorTable,
+    len: usize,
+    moves: impl Iterator<Item = (ElementId, ElementId)>,
+) {
+    w.write_len(len);
+    for (position, item) in moves {
+        table.write_tag(w, position);
+        table.write_tag(w, item);
+    }
+}
 
-    fn find_insert_position(&self, node: &RgaNode<T>, after_raw: Option<usize>) -> usize {
-        let start = match after_raw {
-            Some(idx) => idx + 1,
-            None => 0,
-        };
+fn read_moves(
+    r: &mut Reader<'_>,
+    table: &ActorTable,
+) -> Result<Vec<(ElementId, ElementId)>, CodecError> {
+    let len = r.read_len()?;
+    (0..len)
+        .map(|_| Ok((table.read_tag(r)?, table.read_tag(r)?)))
+        .collect()
+}
 
-        let new_key = (node.id.1, node.id.0); // (counter, actor)
+/// Updates are written as their two tags and the value as a one-element
+/// run, so they need no encoding of `T` beyond the run's.
+fn write_updates<'a, T: Clone + 'a, S: Run<T> + Encode>(
+    w: &mut Writer,
+    table: &ActorTable,
+    len: usize,
+    updates: impl Iterator<Item = (ElementId, ElementId, &'a T)>,
+) {
+    w.write_len(len);
+    for (update, item, value) in updates {
+        table.write_tag(w, update);
+        table.write_tag(w, item);
+        S::single(value.clone()).encode(w);
+    }
+}
 
-        for i in start..self.elements.len() {
-            let existing = &self.elements[i];
-            let existing_key = (existing.id.1, existing.id.0);
-            if existing_key < new_key {
-                return i;
+fn read_updates<T, S: Run<T> + Decode>(
+    r: &mut Reader<'_>,
+    table: &ActorTable,
+) -> Result<Vec<(ElementId, ElementId, T)>, CodecError> {
+    let len = r.read_len()?;
+    (0..len)
+        .map(|_| {
+            let update = table.read_tag(r)?;
+            let item = table.read_tag(r)?;
+            let run = S::decode(r)?;
+            let mut values = run.values();
+            match (values.next(), values.next()) {
+                (Some(value), None) => Ok((update, item, value)),
+                _ => Err(CodecError::InvalidValue("update")),
             }
-        }
+        })
+        .collect()
+}
 
-        self.elements.len()
+fn write_stamps(
+    w: &mut Writer,
+    table: &ActorTable,
+    len: usize,
+    stamps: impl Iterator<Item = (NodeId, Range<u64>, HybridTimestamp)>,
+) {
+    w.write_len(len);
+    for (actor, span, timestamp) in stamps {
+        table.write_tag(w, (actor, span.start));
+        w.write_varint(span.end - span.start);
+        timestamp.encode(w);
     }
 }
 
-/// Delta for [`Rga`]: elements and tombstones that the other replica is missing.
-#[derive(Debug, Clone, PartialEq, Eq)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-pub struct RgaDelta<T: Clone + Ord> {
-    /// Elements that the other replica doesn't have yet.
-    pub new_elements: Vec<RgaNode<T>>,
-    /// IDs of elements that are deleted in source but not in other.
-    pub tombstoned_ids: Vec<(NodeId, u64)>,
-    /// Version vector of the source.
-    pub version: BTreeMap<NodeId, u64>,
+fn read_stamps(
+    r: &mut Reader<'_>,
+    table: &ActorTable,
+) -> Result<Vec<(NodeId, Range<u64>, HybridTimestamp)>, CodecError> {
+    let len = r.read_len()?;
+    (0..len)
+        .map(|_| {
+            let (actor, start) = table.read_tag(r)?;
+            let len = r.read_varint()?;
+            let end = start.checked_add(len).filter(|_| len > 0);
+            let end = end.ok_or(CodecError::InvalidValue("insert span"))?;
+            Ok((actor, start..end, HybridTimestamp::decode(r)?))
+        })
+        .collect()
 }
 
-impl<T: Clone + Ord> DeltaCrdt for Rga<T> {
-    type Delta = RgaDelta<T>;
-
-    fn delta(&self, other: &Self) -> RgaDelta<T> {
-        let new_elements: Vec<_> = self
-            .elements
-            .iter()
-            .filter(|e| {
-                let actor_max = other.version.get(&e.id.0).copied().unwrap_or(0);
-                e.id.1 > actor_max
-            })
-            .cloned()
-            .collect();
+fn write_removes(
+    w: &mut Writer,
+    table: &ActorTable,
+    len: usize,
+    removes: impl Iterator<Item = (NodeId, Range<u64>, ElementId)>,
+) {
+    w.write_len(len);
+    for (actor, span, dot) in removes {
+        table.write_tag(w, (actor, span.start));
+        w.write_varint(span.end - span.start);
+        table.write_tag(w, dot);
+    }
+}
 
-        let tombstoned_ids: Vec<_> = self
-            .elements
-            .iter()
-            .filter(|e| {
-                e.deleted && {
-                    let actor_max = other.version.get(&e.id.0).copied().unwrap_or(0);
-                    e.id.1 <= actor_max
-                }
-            })
-            .map(|e| e.id)
-            .collect();
+fn read_removes(
+    r: &mut Reader<'_>,
+    table: &ActorTable,
+) -> Result<Vec<(NodeId, Range<u64>, ElementId)>, CodecError> {
+    let len = r.read_len()?;
+    (0..len)
+        .map(|_| {
+            let (actor, start) = table.read_tag(r)?;
+            let len = r.read_varint()?;
+            let end = start.checked_add(len).filter(|_| len > 0);
+            let end = end.ok_or(CodecError::InvalidValue("tombstone span"))?;
+            Ok((actor, start..end, table.read_tag(r)?))
+        })
+        .collect()
+}
 
-        RgaDelta {
-            new_elements,
-            tombstoned_ids,
-            version: self.version.clone(),
-        }
+fn read_origin(
+    r: &mut Reader<'_>,
+    table: &ActorTable,
+) -> Result<Option<(NodeId, u64)>, CodecError> {
+    match r.read_u8()? {
+        0 => Ok(None),
+        1 => table.read_tag(r).map(Some),
+        _ => Err(CodecError::InvalidValue("origin")),
     }
+}
 
-    fn apply_delta(&mut self, delta: &RgaDelta<T>) {
-        // Phase 1: Apply tombstones using a pre-built index (no shifts yet).
-        let id_index: BTreeMap<(NodeId, u64), usize> = self
-            .elements
-            .iter()
-            .enumerate()
-            .map(|(i, e)| (e.id, i))
-            .collect();
+fn read_node<T: Clone + Ord, S: Run<T> + Decode>(
+    r: &mut Reader<'_>,
+    table: &ActorTable,
+) -> Result<RgaNode<T, S>, CodecError> {
+    let id = table.read_tag(r)?;
+    let flags = r.read_u8()?;
+    if flags > 0b11 {
+        return Err(CodecError::InvalidValue("run flags"));
+    }
+    let values = S::decode(r)?;
+    let len = values.count() as u64;
+    if len == 0 || id.1.checked_add(len).is_none() {
+        return Err(CodecError::InvalidValue("run"));
+    }
+    let mut node = RgaNode::new(id, values, read_origin(r, table)?, read_origin(r, table)?);
+    node.deleted = flags & 1 != 0;
+    node.moved = flags & 0b10 != 0;
+    Ok(node)
+}
 
-        for &id in &delta.tombstoned_ids {
-            if let Some(&raw) = id_index.get(&id) {
-                if !self.elements[raw].deleted {
-                    self.elements[raw].deleted = true;
-                    self.visible_len -= 1;
-                }
-            }
+impl<T: Clone + Ord, S: Run<T> + Encode> Encode for Rga<T, S> {
+    fn encode(&self, w: &mut Writer) {
+        let table = ActorTable::new(
+            core::iter::once(self.actor)
+                .chain(self.version.keys().copied())
+                .chain(self.elements.iter().flat_map(node_actors))
+                .chain(pair_actors(self.moves.iter()))
+                .chain(pair_actors(self.updates.iter().map(|(update, item, _)| (update, item))))
+                .chain(self.stamps.iter().map(|(actor, _, _)| actor))
+                .chain(self.removes.iter().flat_map(|(actor, _, dot)| [actor, dot.0])),
+        );
+        table.encode(w);
+        table.write_actor(w, self.actor);
+        w.write_varint(self.counter);
+        table.write_counters(w, &self.version);
+        let runs = wire_runs(self.elements.iter());
+        w.write_len(runs.len());
+        for node in &runs {
+            write_node(w, &table, node);
         }
+        write_moves(w, &table, self.moves.len(), self.moves.iter());
+        write_updates::<T, S>(w, &table, self.updates.len(), self.updates.iter());
+        write_stamps(w, &table, self.stamps.len(), self.stamps.iter());
+        write_removes(w, &table, self.removes.len(), self.removes.iter());
+    }
+}
 
-        // Phase 2: Insert new elements. Use a set for dedup; find
-        // predecessor positions by scanning self.elements directly,
-        // avoiding the O(k) index-shift loop per insertion.
-        let mut known_ids: BTreeSet<(NodeId, u64)> =
-            self.elements.iter().map(|e| e.id).collect();
-
-        for (delta_idx, elem) in delta.new_elements.iter().enumerate() {
-            if !known_ids.contains(&elem.id) {
-                let predecessor_raw = if delta_idx == 0 {
-                    None
-                } else {
-                    (0..delta_idx).rev().find_map(|i| {
-                        self.elements
-                            .iter()
-                            .position(|e| e.id == delta.new_elements[i].id)
-                    })
-                };
-
-                let pos = self.find_insert_position(elem, predecessor_raw);
-                self.elements.insert(pos, elem.clone());
-                if !elem.deleted {
-                    self.visible_len += 1;
-                }
-                known_ids.insert(elem.id);
-            }
+impl<T: Clone + Ord, S: Run<T> + Decode> Decode for Rga<T, S> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        let table = ActorTable::decode(r)?;
+        let actor = table.read_actor(r)?;
+        let counter = r.read_varint()?;
+        let version = table.read_counters(r)?;
+        let len = r.read_len()?;
+        let nodes = (0..len)
+            .map(|_| read_node(r, &table))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut moves = MoveTable::default();
+        for (position, item) in read_moves(r, &table)? {
+            moves.insert(position, item);
         }
-
-        for (&actor, &cnt) in &delta.version {
-            let entry = self.version.entry(actor).or_insert(0);
-            *entry = (*entry).max(cnt);
+        let mut updates = UpdateTable::default();
+        for (update, item, value) in read_updates::<T, S>(r, &table)? {
+            updates.insert(update, item, value);
         }
-
-        if let Some(&max_cnt) = self.version.values().max() {
-            self.counter = self.counter.max(max_cnt);
+        let mut stamps = StampTable::default();
+        for (actor, span, timestamp) in read_stamps(r, &table)? {
+            stamps.insert(actor, span, timestamp);
+        }
+        let mut removes = RemoveTable::default();
+        for (actor, span, dot) in read_removes(r, &table)? {
+            removes.insert(dot, actor, span);
         }
+        Ok(Self {
+            actor,
+            counter,
+            elements: node_store(nodes),
+            moves,
+            updates,
+            stamps,
+            removes,
+            version,
+        })
     }
 }
 
-impl<T: Clone + Ord> Crdt for Rga<T> {
-    fn merge(&mut self, other: &Self) {
-        // Phase 1: Apply tombstones using a pre-built index (no shifts yet).
-        let id_index: BTreeMap<(NodeId, u64), usize> = self
-            .elements
-            .iter()
-            .enumerate()
-            .map(|(i, e)| (e.id, i))
-            .collect();
-
-        for other_elem in other.elements.iter() {
-            if other_elem.deleted {
-                if let Some(&raw) = id_index.get(&other_elem.id) {
-                    if !self.elements[raw].deleted {
-                        self.elements[raw].deleted = true;
-                        self.visible_len -= 1;
-                    }
-                }
-            }
-        }
-
-        // Phase 2: Insert new elements. Use a set for dedup; find
-        // predecessor positions by scanning self.elements directly,
-        // avoiding the O(k) index-shift loop per insertion.
-        let mut known_ids: BTreeSet<(NodeId, u64)> =
-            self.elements.iter().map(|e| e.id).collect();
-
-        for (other_idx, other_elem) in other.elements.iter().enumerate() {
-            if !known_ids.contains(&other_elem.id) {
-                let predecessor_raw = if other_idx == 0 {
-                    None
-                } else {
-                    (0..other_idx).rev().find_map(|i| {
-                        self.elements
-                            .iter()
-                            .position(|e| e.id == other.elements[i].id)
-                    })
-                };
-
-                let pos = self.find_insert_position(other_elem, predecessor_raw);
-                self.elements.insert(pos, other_elem.clone());
-                if !other_elem.deleted {
-                    self.visible_len += 1;
-                }
-                known_ids.insert(other_elem.id);
-            }
-        }
-
-        for (&actor, &cnt) in &other.version {
-            let entry = self.version.entry(actor).or_insert(0);
-            *entry = (*entry).max(cnt);
+impl<T: Clone + Ord, S: Run<T> + Encode> Encode for RgaDelta<T, S> {
+    fn encode(&self, w: &mut Writer) {
+        let table = ActorTable::new(
+            self.new_elements
+                .iter()
+                .flat_map(node_actors)
+                .chain(self.tombstoned_spans.iter().flat_map(|(actor, _, dot)| [*actor, dot.0]))
+                .chain(pair_actors(self.moves.iter().copied()))
+                .chain(pair_actors(self.updates.iter().map(|(update, item, _)| (*update, *item))))
+                .chain(self.timestamps.iter().map(|(actor, _, _)| *actor))
+                .chain(self.version.keys().copied()),
+        );
+        table.encode(w);
+        let runs = wire_runs(self.new_elements.iter());
+        w.write_len(runs.len());
+        for node in &runs {
+            write_node(w, &table, node);
         }
+        let removes = self.tombstoned_spans.iter().map(|(a, span, dot)| (*a, span.clone(), *dot));
+        write_removes(w, &table, self.tombstoned_spans.len(), removes);
+        write_moves(w, &table, self.moves.len(), self.moves.iter().copied());
+        let updates = self.updates.iter().map(|(update, item, value)| (*update, *item, value));
+        write_updates::<T, S>(w, &table, self.updates.len(), updates);
+        let stamps = self.timestamps.iter().map(|(actor, span, ts)| (*actor, span.clone(), *ts));
+        write_stamps(w, &table, self.timestamps.len(), stamps);
+        table.write_counters(w, &self.version);
+    }
+}
 
-        if let Some(&max_cnt) = self.version.values().max() {
-            self.counter = self.counter.max(max_cnt);
-        }
+impl<T: Clone + Ord, S: Run<T> + Decode> Decode for RgaDelta<T, S> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        let table = ActorTable::decode(r)?;
+        let len = r.read_len()?;
+        let new_elements = (0..len)
+            .map(|_| read_node(r, &table))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            new_elements,
+            tombstoned_spans: read_removes(r, &table)?,
+            moves: read_moves(r, &table)?,
+            updates: read_updates::<T, S>(r, &table)?,
+            timestamps: read_stamps(r, &table)?,
+            version: table.read_counters(r)?,
+        })
     }
 }
 
@@ -639,4 +2632,665 @@ mod tests {
         r1.merge(&r2);
         assert_eq!(r1.to_vec(), vec!['x', 'y', 'z']);
     }
+
+    #[test]
+    fn delta_since_matches_delta() {
+        let mut r1 = Rga::new(1);
+        r1.insert_at(0, 'a').unwrap();
+        r1.insert_at(1, 'b').unwrap();
+
+        let mut r2 = r1.fork(2);
+        r2.insert_at(2, 'c').unwrap();
+
+        r1.remove(0).unwrap();
+        r1.insert_at(1, 'd').unwrap();
+
+        let d = r1.delta_since(&r2.version_vector());
+        assert_eq!(d, r1.delta(&r2));
+        let ids: Vec<_> = d.new_elements.iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![(1, 4)]);
+        assert_eq!(d.tombstoned_spans, vec![(1, 1..2, (1, 3))]);
+
+        let mut via_merge = r2.clone();
+        via_merge.merge(&r1);
+        r2.apply_delta(&d);
+        assert_eq!(r2.to_vec(), via_merge.to_vec());
+    }
+
+    fn type_run(rga: &mut Rga<char>, mut index: usize, text: &str) {
+        for c in text.chars() {
+            rga.insert_at(index, c).unwrap();
+            index += 1;
+        }
+    }
+
+    fn string(rga: &Rga<char>) -> String {
+        rga.iter().collect()
+    }
+
+    #[test]
+    fn concurrent_runs_do_not_interleave() {
+        let mut base = Rga::new(1);
+        type_run(&mut base, 0, "[]");
+
+        let mut r1 = base.fork(1);
+        let mut r2 = base.fork(2);
+        type_run(&mut r1, 1, "abc");
+        type_run(&mut r2, 1, "xyz");
+
+        let mut m1 = r1.clone();
+        m1.merge(&r2);
+        let mut m2 = r2.clone();
+        m2.merge(&r1);
+        assert_eq!(string(&m1), string(&m2));
+        assert!(["[abcxyz]", "[xyzabc]"].contains(&string(&m1).as_str()));
+    }
+
+    #[test]
+    fn concurrent_runs_typed_backwards_do_not_interleave() {
+        let mut r1 = Rga::new(1);
+        let mut r2 = Rga::new(2);
+        for c in "cba".chars() {
+            r1.insert_at(0, c).unwrap();
+        }
+        for c in "zyx".chars() {
+            r2.insert_at(0, c).unwrap();
+        }
+
+        r1.merge(&r2);
+        assert!(["abcxyz", "xyzabc"].contains(&string(&r1).as_str()));
+    }
+
+    #[test]
+    fn three_concurrent_runs_stay_contiguous() {
+        let mut base = Rga::new(1);
+        type_run(&mut base, 0, "..");
+        let mut replicas: Vec<_> = (1..=3).map(|a| base.fork(a)).collect();
+        type_run(&mut replicas[0], 1, "aaa");
+        type_run(&mut replicas[1], 1, "bbb");
+        type_run(&mut replicas[2], 1, "ccc");
+
+        let mut merged = replicas[2].clone();
+        merged.merge(&replicas[0]);
+        merged.merge(&replicas[1]);
+        let text = string(&merged);
+        for run in ["aaa", "bbb", "ccc"] {
+            assert!(text.contains(run), "{run} split in {text}");
+        }
+
+        let mut other = replicas[0].clone();
+        other.merge(&replicas[1]);
+        other.merge(&replicas[2]);
+        assert_eq!(string(&other), text);
+    }
+
+    #[test]
+    fn runs_stay_contiguous_through_deltas() {
+        let mut base = Rga::new(1);
+        type_run(&mut base, 0, "ab");
+        let mut r1 = base.fork(1);
+        let mut r2 = base.fork(2);
+        type_run(&mut r1, 1, "hello");
+        type_run(&mut r2, 1, "world");
+        // A run that continues after a deletion inside it.
+        r2.remove(3).unwrap();
+        type_run(&mut r2, 5, "!!");
+
+        let d1 = r1.delta(&r2);
+        let d2 = r2.delta(&r1);
+        r1.apply_delta(&d2);
+        r2.apply_delta(&d1);
+        assert_eq!(string(&r1), string(&r2));
+        assert!(["ahellowold!!b", "awold!!hellob"].contains(&string(&r1).as_str()));
+    }
+
+    #[test]
+    fn visible_lookup_skips_tombstones_across_chunks() {
+        let mut r = Rga::new(1);
+        for i in 0..2000u32 {
+            r.insert_at(r.len(), i).unwrap();
+        }
+        for i in (0..2000).step_by(3) {
+            r.remove(i - i / 3).unwrap();
+        }
+        let expected: Vec<u32> = (0..2000).filter(|i| i % 3 != 0).collect();
+        assert_eq!(r.len(), expected.len());
+        for (i, v) in expected.iter().enumerate() {
+            assert_eq!(r.get(i), Some(v));
+        }
+        assert_eq!(r.get(expected.len()), None);
+    }
+
+    #[test]
+    fn large_delta_merges_into_large_document() {
+        let mut base = Rga::new(1);
+        for i in 0..5000u32 {
+            base.insert_at(base.len(), i).unwrap();
+        }
+        let mut r1 = base.fork(1);
+        let mut r2 = base.fork(2);
+        for i in 0..2000u32 {
+            let at = (i as usize * 7919) % (r2.len() + 1);
+            r2.insert_at(at, 10_000 + i).unwrap();
+            if i % 4 == 0 {
+                r2.remove((i as usize * 31) % r2.len()).unwrap();
+            }
+        }
+        r1.remove(0).unwrap();
+
+        let d2 = r2.delta(&r1);
+        let d1 = r1.delta(&r2);
+        r1.apply_delta(&d2);
+        r2.apply_delta(&d1);
+        assert_eq!(r1.to_vec(), r2.to_vec());
+        assert_eq!(r1.len(), 5000 + 2000 - 500 - 1);
+
+        let mut via_merge = base.clone();
+        via_merge.merge(&r2);
+        assert_eq!(via_merge.to_vec(), r2.to_vec());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_roundtrip_restores_visible_counts() {
+        let mut r = Rga::new(1);
+        type_run(&mut r, 0, "hello");
+        r.remove(0).unwrap();
+        let json = serde_json::to_string(&r).unwrap();
+        let mut back: Rga<char> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, r);
+        assert_eq!(back.len(), 4);
+        back.insert_at(4, '!').unwrap();
+        assert_eq!(string(&back), "ello!");
+    }
+
+    #[test]
+    fn anchors_follow_their_elements() {
+        let mut r1 = Rga::new(1);
+        type_run(&mut r1, 0, "abcd");
+        let mut r2 = r1.fork(2);
+
+        let left = r1.anchor_at(2, Gravity::Left).unwrap();
+        let right = r1.anchor_at(2, Gravity::Right).unwrap();
+        assert_eq!(left.target(), Some((1, 2)));
+        assert_eq!(right.target(), Some((1, 3)));
+
+        // Remote insert before the anchors shifts both.
+        r2.insert_at(0, 'x').unwrap();
+        r1.merge(&r2);
+        assert_eq!((r1.resolve(left), r1.resolve(right)), (3, 3));
+
+        // Insert exactly at the anchored position splits them by gravity.
+        r1.insert_at(3, 'y').unwrap();
+        assert_eq!(string(&r1), "xabycd");
+        assert_eq!((r1.resolve(left), r1.resolve(right)), (3, 4));
+    }
+
+    #[test]
+    fn anchor_on_tombstone_resolves_to_its_old_place() {
+        let mut r = Rga::new(1);
+        type_run(&mut r, 0, "abcde");
+        let left = r.anchor_at(3, Gravity::Left).unwrap(); // after 'c'
+        let right = r.anchor_at(2, Gravity::Right).unwrap(); // before 'c'
+
+        r.remove(2).unwrap();
+        assert_eq!(string(&r), "abde");
+        assert_eq!(r.resolve(left), 2);
+        assert_eq!(r.resolve(right), 2);
+
+        // Everything around the anchors goes; they collapse to the start.
+        for _ in 0..2 {
+            r.remove(0).unwrap();
+        }
+        assert_eq!((r.resolve(left), r.resolve(right)), (0, 0));
+    }
+
+    #[test]
+    fn unbound_and_unknown_anchors() {
+        let mut r = Rga::new(1);
+        type_run(&mut r, 0, "ab");
+        let start = r.anchor_at(0, Gravity::Left).unwrap();
+        let end = r.anchor_at(2, Gravity::Right).unwrap();
+        assert_eq!((start.target(), end.target()), (None, None));
+        r.insert_at(0, 'x').unwrap();
+        r.insert_at(3, 'y').unwrap();
+        assert_eq!((r.resolve(start), r.resolve(end)), (0, 4));
+
+        assert!(r.anchor_at(5, Gravity::Left).is_err());
+
+        let mut other = Rga::new(2);
+        other.insert_at(0, 'z').unwrap();
+        let foreign = other.anchor_at(0, Gravity::Right).unwrap();
+        assert_eq!(r.resolve(foreign), r.len());
+    }
+
+    #[test]
+    fn insert_records_origins() {
+        let mut r = Rga::new(1);
+        type_run(&mut r, 0, "ac");
+        r.insert_at(1, 'b').unwrap();
+        let b = r.elements.iter().find(|n| n.values == ['b']).unwrap();
+        assert_eq!(b.origin_left, Some((1, 1)));
+        assert_eq!(b.origin_right, Some((1, 2)));
+    }
+
+    #[test]
+    fn typed_runs_share_one_node() {
+        let mut r = Rga::new(1);
+        type_run(&mut r, 0, "hello");
+        assert_eq!(r.elements.len(), 1);
+
+        // An insert inside the run splits it around the new element.
+        r.insert_at(2, '-').unwrap();
+        assert_eq!(string(&r), "he-llo");
+        assert_eq!(r.elements.len(), 3);
+
+        // Deleting splits off a tombstoned run; neighbouring deletions join.
+        r.remove(4).unwrap();
+        r.remove(3).unwrap();
+        assert_eq!(string(&r), "he-o");
+        assert_eq!(r.elements.len(), 4);
+        assert_eq!(r.tombstone_count(), 2);
+        assert_eq!(r.raw_len(), 6);
+        assert_eq!(r.remove(2), Ok('-'));
+        assert_eq!(r.get(2), Some(&'o'));
+    }
+
+    #[test]
+    fn insert_run_matches_single_inserts() {
+        let mut one_by_one = Rga::new(1);
+        type_run(&mut one_by_one, 0, "ad");
+        let mut runs = one_by_one.clone();
+        type_run(&mut one_by_one, 1, "bc");
+        runs.insert_run(1, vec!['b', 'c']).unwrap();
+        assert_eq!(runs, one_by_one);
+        assert!(runs.insert_run(9, Vec::new()).is_err());
+    }
+
+    #[test]
+    fn delta_sends_unseen_tail_of_a_run() {
+        let mut r1 = Rga::new(1);
+        type_run(&mut r1, 0, "abc");
+        let mut r2 = r1.fork(2);
+        type_run(&mut r1, 3, "de");
+        r1.remove(1).unwrap();
+
+        let d = r1.delta(&r2);
+        let ids: Vec<_> = d.new_elements.iter().flat_map(RgaNode::ids).collect();
+        assert_eq!(ids, vec![(1, 4), (1, 5)]);
+        assert_eq!(d.tombstoned_spans, vec![(1, 2..3, (1, 6))]);
+
+        r2.apply_delta(&d);
+        assert_eq!(string(&r2), "acde");
+        assert_eq!(r2.elements.len(), 3);
+        assert_eq!(r2.fork(1), r1);
+    }
+
+    #[test]
+    fn delta_since_skips_seen_removes() {
+        let mut r1 = Rga::new(1);
+        type_run(&mut r1, 0, "abc");
+        let mut r2 = r1.fork(2);
+        r1.remove(1).unwrap();
+        assert_eq!(r1.version_vector().get(1), 4);
+
+        r2.apply_delta(&r1.delta_since(&r2.version_vector()));
+        assert_eq!(string(&r2), "ac");
+        assert!(r1.delta_since(&r2.version_vector()).tombstoned_spans.is_empty());
+
+        // Concurrent removes of the same element are both kept.
+        let mut r3 = r2.fork(3);
+        r2.remove(0).unwrap();
+        r3.remove(0).unwrap();
+        let d = r2.delta_since(&r3.version_vector());
+        assert_eq!(d.tombstoned_spans, vec![(1, 1..2, (2, 5))]);
+        r3.apply_delta(&d);
+        r2.merge(&r3);
+        assert_eq!(r2.fork(3), r3);
+        assert!(r2.delta_since(&r3.version_vector()).tombstoned_spans.is_empty());
+    }
+
+    #[test]
+    fn remote_runs_split_local_runs_and_rejoin() {
+        let mut r1 = Rga::new(1);
+        type_run(&mut r1, 0, "abcdef");
+        let mut r2 = r1.fork(2);
+        type_run(&mut r2, 3, "XY");
+        r1.remove(0).unwrap();
+
+        let mut merged = r1.clone();
+        merged.merge(&r2);
+        assert_eq!(string(&merged), "bcXYdef");
+        let mut other = r2.clone();
+        other.merge(&r1);
+        assert_eq!(other.fork(1), merged);
+
+        // Once the insert is deleted, the halves around it stay split: the
+        // second half's left origin is still the tombstoned 'Y'.
+        merged.remove(2).unwrap();
+        merged.remove(2).unwrap();
+        assert_eq!(string(&merged), "bcdef");
+        assert_eq!(merged.elements.len(), 4);
+    }
+
+    #[test]
+    fn move_item_reorders_without_copies() {
+        let mut r = Rga::new(1);
+        type_run(&mut r, 0, "abcd");
+        r.move_item(0, 3).unwrap();
+        assert_eq!(string(&r), "bcda");
+        assert_eq!(r.tombstone_count(), 1);
+        r.move_item(3, 0).unwrap();
+        assert_eq!(string(&r), "abcd");
+        r.move_item(1, 1).unwrap();
+        assert_eq!(string(&r), "abcd");
+        assert_eq!(
+            r.move_item(4, 0),
+            Err(RgaError::IndexOutOfBounds { index: 4, len: 4 })
+        );
+        assert_eq!(
+            r.move_item(0, 4),
+            Err(RgaError::IndexOutOfBounds { index: 4, len: 4 })
+        );
+    }
+
+    #[test]
+    fn concurrent_moves_of_one_item_keep_one_copy() {
+        let mut base = Rga::new(1);
+        type_run(&mut base, 0, "abcd");
+        let mut r2 = base.fork(2);
+        let mut r3 = base.fork(3);
+        let mut r4 = base.fork(4);
+        r2.move_item(0, 3).unwrap(); // bcda
+        r3.move_item(0, 1).unwrap(); // bacd
+        r3.move_item(1, 2).unwrap(); // bcad, the later move wins
+        r4.move_item(0, 2).unwrap(); // bcad
+
+        let mut forward = r2.clone();
+        forward.merge(&r3);
+        forward.merge(&r4);
+        assert_eq!(string(&forward), "bcad");
+
+        let mut backward = r4.clone();
+        backward.merge(&r3);
+        backward.merge(&r2);
+        assert_eq!(backward.fork(2), forward);
+
+        // On a counter tie the greater actor wins.
+        let mut r5 = base.fork(5);
+        r5.move_item(0, 1).unwrap();
+        r2.merge(&r5);
+        assert_eq!(string(&r2), "bacd");
+    }
+
+    #[test]
+    fn delete_wins_over_concurrent_move() {
+        let mut r1 = Rga::new(1);
+        type_run(&mut r1, 0, "abc");
+        let mut r2 = r1.fork(2);
+        r1.remove(0).unwrap();
+        r2.move_item(0, 2).unwrap();
+        r2.move_item(2, 1).unwrap();
+
+        let mut merged = r1.clone();
+        merged.merge(&r2);
+        assert_eq!(string(&merged), "bc");
+        r2.merge(&r1);
+        assert_eq!(r2.fork(1), merged);
+
+        // Deleting a moved item removes it wherever it was moved to.
+        let mut r3 = Rga::new(3);
+        type_run(&mut r3, 0, "xyz");
+        let mut r4 = r3.fork(4);
+        r3.move_item(0, 2).unwrap();
+        r4.remove(0).unwrap();
+        r4.merge(&r3);
+        assert_eq!(string(&r4), "yz");
+    }
+
+    #[test]
+    fn long_runs_break_at_fixed_counters() {
+        let values: Vec<u32> = (0..1000).collect();
+        let mut r = Rga::new(1);
+        r.insert_run(0, values.clone()).unwrap();
+        assert_eq!(r.to_vec(), values);
+        let lens: Vec<_> = r.elements.iter().map(|n| n.len).collect();
+        assert_eq!(lens, vec![255, 256, 256, 233]);
+
+        // The same elements typed one by one, or received, are split alike.
+        let mut typed = Rga::new(1);
+        for (i, &v) in values.iter().enumerate() {
+            typed.insert_at(i, v).unwrap();
+        }
+        assert_eq!(typed, r);
+        let mut other = Rga::new(2);
+        other.merge(&r);
+        assert_eq!(other.fork(1), r);
+        let bytes = crate::codec::to_bytes(&r);
+        assert_eq!(crate::codec::from_bytes::<Rga<u32>>(&bytes), Ok(r));
+    }
+
+    #[test]
+    fn compaction_keeps_placeholders_that_moves_refer_to() {
+        let mut r1 = Rga::new(1);
+        type_run(&mut r1, 0, "abcd");
+        let mut r2 = r1.fork(2);
+        let before = r1.summary();
+        r1.move_item(0, 3).unwrap(); // bcda
+        let stale = r1.delta_since(&before);
+        r1.move_item(3, 1).unwrap(); // bacd
+        r1.remove(3).unwrap();
+        r2.merge(&r1);
+
+        assert_eq!(r1.compact_tombstones(), 1);
+        assert_eq!(r2.compact_tombstones(), 1);
+        assert_eq!(r1.tombstone_count(), 2);
+
+        // A late copy of the first move must not bring its position back.
+        r2.apply_delta(&stale);
+        assert_eq!(string(&r2), "bac");
+        r2.move_item(0, 2).unwrap();
+        r1.merge(&r2);
+        assert_eq!(string(&r1), "acb");
+        assert_eq!(r1.fork(2), r2);
+    }
+
+    #[test]
+    fn remove_range_tombstones_whole_runs() {
+        let mut r1 = Rga::new(1);
+        type_run(&mut r1, 0, "abcdef");
+        let mut r2 = r1.fork(2);
+        r1.remove(2).unwrap();
+        r1.remove_range(1, 3).unwrap();
+        assert_eq!(string(&r1), "af");
+        assert!(r1.remove_range(1, 2).is_err());
+        assert_eq!(
+            r1.remove_range(1, usize::MAX),
+            Err(RgaError::IndexOutOfBounds {
+                index: usize::MAX,
+                len: 2
+            })
+        );
+
+        let delta = r1.delta(&r2);
+        // 'c' was removed by (1, 7), the ids around it by (1, 8).
+        let spans = vec![(1, 3..4, (1, 7)), (1, 2..3, (1, 8)), (1, 4..6, (1, 8))];
+        assert_eq!(delta.tombstoned_spans, spans);
+        r2.apply_delta(&delta);
+        assert_eq!(string(&r2), "af");
+        assert_eq!(r2.elements.len(), 3);
+
+        let bytes = crate::codec::to_bytes(&delta);
+        assert_eq!(crate::codec::from_bytes::<RgaDelta<char>>(&bytes), Ok(delta.clone()));
+        // A span is its first id's tag and a length, which must not be 0.
+        let empty = RgaDelta {
+            tombstoned_spans: vec![(1, 2..2, (1, 8))],
+            ..delta
+        };
+        let bytes = crate::codec::to_bytes(&empty);
+        assert_eq!(
+            crate::codec::from_bytes::<RgaDelta<char>>(&bytes),
+            Err(CodecError::InvalidValue("tombstone span"))
+        );
+    }
+
+    #[test]
+    fn runs_past_the_last_counter_are_rejected() {
+        let run = RgaNode::new((1, u64::MAX - 3), "abcdefghij".chars().collect(), None, None);
+        let delta = RgaDelta::<char> {
+            new_elements: vec![run],
+            tombstoned_spans: Vec::new(),
+            moves: Vec::new(),
+            updates: Vec::new(),
+            timestamps: Vec::new(),
+            version: BTreeMap::new(),
+        };
+        let bytes = crate::codec::to_bytes(&delta);
+        assert_eq!(
+            crate::codec::from_bytes::<RgaDelta<char>>(&bytes),
+            Err(CodecError::InvalidValue("run"))
+        );
+    }
+
+    #[test]
+    fn patches_describe_remote_changes() {
+        let mut r1 = Rga::new(1);
+        type_run(&mut r1, 0, "abcdef");
+        let mut r2 = r1.fork(2);
+        type_run(&mut r2, 6, "gh");
+        r2.remove_range(1, 2).unwrap();
+        r2.move_item(0, 5).unwrap();
+
+        let mut via_delta = r1.clone();
+        let patches = r1.merge_with_patches(&r2);
+        let splice = |index, deleted, inserted: &str| Splice {
+            index,
+            deleted,
+            inserted: inserted.chars().collect::<Vec<_>>(),
+        };
+        assert_eq!(patches, vec![splice(1, 2, ""), splice(4, 0, "gha"), splice(0, 1, "")]);
+        assert_eq!(string(&r1), "defgha");
+
+        let delta = r2.delta(&via_delta);
+        assert_eq!(via_delta.apply_delta_with_patches(&delta), patches);
+        assert!(r1.merge_with_patches(&r2).is_empty());
+    }
+
+    #[test]
+    fn set_keeps_identity_and_last_writer_wins() {
+        let mut r1 = Rga::new(1);
+        type_run(&mut r1, 0, "abc");
+        let mut r2 = r1.fork(2);
+        assert_eq!(r1.set(1, 'B'), Ok('b'));
+        assert_eq!(string(&r1), "aBc");
+        assert_eq!(r1.elements.len(), 1);
+        assert!(r1.set(3, 'x').is_err());
+
+        // r2 edits around the element and updates it concurrently; its
+        // update has the greater `(counter, actor)` id.
+        r2.insert_at(1, 'x').unwrap();
+        r2.set(2, 'Y').unwrap();
+        let mut via_delta = r1.clone();
+        via_delta.apply_delta(&r2.delta(&r1));
+        r1.merge(&r2);
+        r2.merge(&via_delta);
+        assert_eq!(string(&r1), "axYc");
+        assert_eq!(r1, r2.fork(1));
+        assert_eq!(via_delta, r1);
+
+        let ids: Vec<_> = r1.elements.iter().map(|n| n.id).collect();
+        assert_eq!(ids, vec![(1, 1), (2, 4), (1, 2)]);
+    }
+
+    #[test]
+    fn set_follows_concurrent_moves() {
+        let mut r1 = Rga::new(1);
+        type_run(&mut r1, 0, "abc");
+        let mut r2 = r1.fork(2);
+        r1.set(0, 'A').unwrap();
+        r2.move_item(0, 2).unwrap();
+
+        let patches = r2.merge_with_patches(&r1);
+        let inserted = alloc::vec!['A'];
+        assert_eq!(patches, vec![Splice { index: 2, deleted: 1, inserted }]);
+        r1.merge(&r2);
+        assert_eq!(string(&r1), "bcA");
+        assert_eq!(string(&r2), "bcA");
+
+        let delta = r1.delta_since(&VersionVector::new());
+        assert_eq!(delta.updates, vec![((1, 4), (1, 1), 'A')]);
+        let bytes = crate::codec::to_bytes(&delta);
+        let mut fresh = Rga::new(3);
+        fresh.apply_delta(&crate::codec::from_bytes(&bytes).unwrap());
+        assert_eq!(string(&fresh), "bcA");
+        let back: Rga<char> = crate::codec::from_bytes(&crate::codec::to_bytes(&r1)).unwrap();
+        assert_eq!(back, r1);
+    }
+
+    fn ts(physical: u64) -> HybridTimestamp {
+        HybridTimestamp {
+            physical,
+            logical: 0,
+            node_id: 0,
+        }
+    }
+
+    #[test]
+    fn blame_attributes_inserts_and_their_timestamps() {
+        let mut r1 = Rga::new(1);
+        r1.insert_run_with_timestamp(0, vec!['a', 'b', 'c'], ts(10)).unwrap();
+        type_run(&mut r1, 3, "de");
+        let mut r2 = r1.fork(2);
+        r2.insert_run_with_timestamp(1, vec!['x'], ts(20)).unwrap();
+        r2.remove(4).unwrap();
+        r2.move_item(0, 4).unwrap();
+        assert_eq!(string(&r2), "xbcea");
+
+        let run = |range, actor, timestamp| BlameRun {
+            range,
+            actor,
+            timestamp,
+        };
+        let expected = vec![
+            run(0..1, 2, Some(ts(20))),
+            run(1..3, 1, Some(ts(10))),
+            run(3..4, 1, None),
+            // Moved, but still inserted by replica 1.
+            run(4..5, 1, Some(ts(10))),
+        ];
+        assert_eq!(r2.blame(), expected);
+
+        let delta = r2.delta(&r1);
+        assert_eq!(delta.timestamps, vec![(2, 6..7, ts(20))]);
+        let bytes = crate::codec::to_bytes(&delta);
+        r1.apply_delta(&crate::codec::from_bytes(&bytes).unwrap());
+        assert_eq!(r1.blame(), expected);
+        let back: Rga<char> = crate::codec::from_bytes(&crate::codec::to_bytes(&r1)).unwrap();
+        assert_eq!(back.blame(), expected);
+
+        let mut merged = Rga::new(3);
+        merged.merge(&r2);
+        assert_eq!(merged.blame(), expected);
+        assert!(Rga::<char>::new(4).blame().is_empty());
+    }
+
+    #[test]
+    fn moves_travel_in_deltas_and_encodings() {
+        let mut r1 = Rga::new(1);
+        type_run(&mut r1, 0, "abcd");
+        let mut r2 = r1.fork(2);
+        r1.move_item(3, 0).unwrap();
+        r1.move_item(1, 3).unwrap();
+
+        let delta = r1.delta_since(&r2.summary());
+        assert_eq!(delta.moves, vec![((1, 5), (1, 4)), ((1, 6), (1, 1))]);
+        let bytes = crate::codec::to_bytes(&delta);
+        r2.apply_delta(&crate::codec::from_bytes(&bytes).unwrap());
+        assert_eq!(string(&r2), "dbca");
+        assert_eq!(r2.fork(1), r1);
+
+        let back: Rga<char> = crate::codec::from_bytes(&crate::codec::to_bytes(&r1)).unwrap();
+        assert_eq!(back, r1);
+        assert!(r1.delta_since(&r2.summary()).moves.is_empty());
+    }
 }
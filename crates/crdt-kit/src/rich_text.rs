@@ -0,0 +1,556 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::codec::{CodecError, Decode, Encode, Reader, Writer};
+use crate::rga::{Anchor, Gravity};
+use crate::text::{TextCrdt, TextDelta, TextError};
+use crate::{Crdt, DeltaCrdt, DeltaSince, NodeId, VersionVector};
+
+/// How a mark grows when text is inserted exactly at one of its boundaries.
+///
+/// Bold or italic usually expand [`After`](Expand::After), so typing at the
+/// end of a bold word stays bold; links usually expand [`None`](Expand::None).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Expand {
+    /// Text inserted at either boundary stays outside the mark.
+    None,
+    /// Text inserted at the start joins the mark.
+    Before,
+    /// Text inserted at the end joins the mark.
+    After,
+    /// Text inserted at either boundary joins the mark.
+    Both,
+}
+
+impl Expand {
+    fn start_gravity(self) -> Gravity {
+        match self {
+            Self::Before | Self::Both => Gravity::Left,
+            Self::None | Self::After => Gravity::Right,
+        }
+    }
+
+    fn end_gravity(self) -> Gravity {
+        match self {
+            Self::After | Self::Both => Gravity::Right,
+            Self::None | Self::Before => Gravity::Left,
+        }
+    }
+}
+
+/// Mark names mapped to their values, e.g. `{"bold": "", "link": "https://…"}`.
+pub type Format = BTreeMap<String, String>;
+
+/// An operation adding or removing a mark over a span of characters.
+///
+/// Both ends are [`Anchor`]s, so the span follows its characters through
+/// concurrent edits. Where several ops for the same mark name cover a
+/// character, the one with the greatest id (Lamport counter, then actor)
+/// wins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MarkOp {
+    /// Unique identifier: (actor, counter), drawn from the text's counter.
+    pub id: (NodeId, u64),
+    /// Mark name, e.g. `"bold"`.
+    pub name: String,
+    /// Mark value, or `None` if this op removes the mark.
+    pub value: Option<String>,
+    /// Start of the span.
+    pub start: Anchor,
+    /// End of the span (exclusive).
+    pub end: Anchor,
+}
+
+impl MarkOp {
+    fn stamp(&self) -> (u64, NodeId) {
+        (self.id.1, self.id.0)
+    }
+}
+
+/// A maximal run of characters sharing the same format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FormattedRun {
+    /// The characters of the run.
+    pub text: String,
+    /// Marks active on every character of the run.
+    pub format: Format,
+}
+
+/// Collaborative rich text: a [`TextCrdt`] plus Peritext-style formatting
+/// marks.
+///
+/// Marks are add/remove operations over spans anchored to character ids,
+/// with an [`Expand`] rule for inserts at the span boundaries. The mark
+/// set only grows, so merging is a union, and every replica resolves it to
+/// the same formatting.
+///
+/// # Example
+///
+/// ```
+/// use crdt_kit::prelude::*;
+///
+/// let mut alice = RichText::new(1);
+/// alice.insert_str(0, "hello world").unwrap();
+/// let mut bob = alice.fork(2);
+///
+/// alice.add_mark(0..5, "bold", "", Expand::After).unwrap();
+/// bob.insert_str(5, "!").unwrap(); // at the end of the bold span
+///
+/// alice.merge(&bob);
+/// bob.merge(&alice);
+/// assert_eq!(alice.runs(), bob.runs());
+///
+/// let runs = alice.runs();
+/// assert_eq!(runs[0].text, "hello!");
+/// assert!(runs[0].format.contains_key("bold"));
+/// assert_eq!(runs[1].text, " world");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RichText {
+    text: TextCrdt,
+    /// Mark ops, sorted by stamp (counter, actor).
+    marks: Vec<MarkOp>,
+}
+
+/// Delta for [`RichText`]: text changes and mark ops the other replica is missing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RichTextDelta {
+    /// Changes to the underlying text.
+    pub text: TextDelta,
+    /// Mark ops the other replica doesn't have yet.
+    pub marks: Vec<MarkOp>,
+}
+
+impl RichText {
+    /// Create a new empty rich text for the given node.
+    pub fn new(actor: NodeId) -> Self {
+        Self {
+            text: TextCrdt::new(actor),
+            marks: Vec::new(),
+        }
+    }
+
+    /// Create a fork of this replica with a different node ID.
+    pub fn fork(&self, new_actor: NodeId) -> Self {
+        Self {
+            text: self.text.fork(new_actor),
+            marks: self.marks.clone(),
+        }
+    }
+
+    /// The plain text.
+    #[must_use]
+    pub fn text(&self) -> &TextCrdt {
+        &self.text
+    }
+
+    /// Insert a character at the given visible index.
+    pub fn insert(&mut self, index: usize, ch: char) -> Result<(), TextError> {
+        self.text.insert(index, ch)
+    }
+
+    /// Insert a string at the given visible index.
+    pub fn insert_str(&mut self, index: usize, s: &str) -> Result<(), TextError> {
+        self.text.insert_str(index, s)
+    }
+
+    /// Remove the character at the given visible index.
+    pub fn remove(&mut self, index: usize) -> Result<(), TextError> {
+        self.text.remove(index)
+    }
+
+    /// Remove `count` characters starting at `start`.
+    pub fn remove_range(&mut self, start: usize, count: usize) -> Result<(), TextError> {
+        self.text.remove_range(start, count)
+    }
+
+    /// Return the number of visible characters.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.text.len()
+    }
+
+    /// Check whether the visible text is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Apply mark `name` with `value` to the characters in `range`.
+    pub fn add_mark(
+        &mut self,
+        range: Range<usize>,
+        name: &str,
+        value: &str,
+        expand: Expand,
+    ) -> Result<&MarkOp, TextError> {
+        self.push_mark(range, name, Some(value.to_string()), expand)
+    }
+
+    /// Remove mark `name` from the characters in `range`.
+    ///
+    /// `expand` controls whether text later inserted at the boundaries is
+    /// also unmarked.
+    pub fn remove_mark(
+        &mut self,
+        range: Range<usize>,
+        name: &str,
+        expand: Expand,
+    ) -> Result<&MarkOp, TextError> {
+        self.push_mark(range, name, None, expand)
+    }
+
+    /// All mark ops, in the order they take effect.
+    pub fn marks(&self) -> impl Iterator<Item = &MarkOp> + '_ {
+        self.marks.iter()
+    }
+
+    /// Marks active on the character at `index`.
+    #[must_use]
+    pub fn format_at(&self, index: usize) -> Format {
+        self.format_of(index..index + 1, &self.resolved_marks())
+    }
+
+    /// The text split into maximal runs of identical formatting.
+    #[must_use]
+    pub fn runs(&self) -> Vec<FormattedRun> {
+        let spans = self.resolved_marks();
+        let mut bounds: BTreeSet<usize> = spans
+            .iter()
+            .flat_map(|(_, r)| [r.start, r.end])
+            .collect();
+        bounds.insert(0);
+        bounds.insert(self.len());
+
+        let text = self.text.to_string();
+        let mut chars = text.chars();
+        let mut runs: Vec<FormattedRun> = Vec::new();
+        let bounds: Vec<usize> = bounds.into_iter().collect();
+        for w in bounds.windows(2) {
+            let format = self.format_of(w[0]..w[1], &spans);
+            let piece: String = chars.by_ref().take(w[1] - w[0]).collect();
+            match runs.last_mut() {
+                Some(last) if last.format == format => last.text.push_str(&piece),
+                _ => runs.push(FormattedRun {
+                    text: piece,
+                    format,
+                }),
+            }
+        }
+        runs
+    }
+
+    /// Get the version vector of this rich text (text and mark ops).
+    #[must_use]
+    pub fn version_vector(&self) -> VersionVector {
+        self.text.version_vector()
+    }
+
+    // ---- internal helpers ----
+
+    fn push_mark(
+        &mut self,
+        range: Range<usize>,
+        name: &str,
+        value: Option<String>,
+        expand: Expand,
+    ) -> Result<&MarkOp, TextError> {
+        let len = self.len();
+        if range.start > range.end || range.end > len {
+            return Err(TextError::RangeOutOfBounds {
+                start: range.start,
+                end: range.end,
+                len,
+            });
+        }
+        let start = self.text.anchor_at(range.start, expand.start_gravity())?;
+        let end = self.text.anchor_at(range.end, expand.end_gravity())?;
+        let op = MarkOp {
+            id: self.text.next_id(),
+            name: name.to_string(),
+            value,
+            start,
+            end,
+        };
+        // A fresh id is the greatest stamp this replica has seen.
+        self.marks.push(op);
+        Ok(self.marks.last().expect("just pushed"))
+    }
+
+    fn insert_mark(&mut self, op: &MarkOp) {
+        if let Err(pos) = self
+            .marks
+            .binary_search_by_key(&op.stamp(), MarkOp::stamp)
+        {
+            self.marks.insert(pos, op.clone());
+        }
+    }
+
+    /// Non-empty visible ranges of the mark ops whose anchors are known.
+    fn resolved_marks(&self) -> Vec<(&MarkOp, Range<usize>)> {
+        self.marks
+            .iter()
+            .filter(|op| self.text.knows(op.start) && self.text.knows(op.end))
+            .map(|op| (op, self.text.resolve(op.start)..self.text.resolve(op.end)))
+            .filter(|(_, r)| r.start < r.end)
+            .collect()
+    }
+
+    /// Format of `range`, which must not straddle any span boundary.
+    fn format_of(&self, range: Range<usize>, spans: &[(&MarkOp, Range<usize>)]) -> Format {
+        let mut format = Format::new();
+        for (op, r) in spans {
+            if r.start <= range.start && range.end <= r.end {
+                match &op.value {
+                    Some(value) => format.insert(op.name.clone(), value.clone()),
+                    None => format.remove(&op.name),
+                };
+            }
+        }
+        format
+    }
+}
+
+impl Crdt for RichText {
+    fn merge(&mut self, other: &Self) {
+        self.text.merge(&other.text);
+        for op in &other.marks {
+            self.insert_mark(op);
+        }
+    }
+}
+
+impl DeltaCrdt for RichText {
+    type Delta = RichTextDelta;
+
+    fn delta(&self, other: &Self) -> RichTextDelta {
+        self.delta_since(&other.version_vector())
+    }
+
+    fn apply_delta(&mut self, delta: &RichTextDelta) {
+        self.text.apply_delta(&delta.text);
+        for op in &delta.marks {
+            self.insert_mark(op);
+        }
+    }
+}
+
+impl DeltaSince for RichText {
+    type Summary = VersionVector;
+
+    fn summary(&self) -> VersionVector {
+        self.version_vector()
+    }
+
+    fn delta_since(&self, since: &VersionVector) -> RichTextDelta {
+        RichTextDelta {
+            text: self.text.delta_since(since),
+            marks: self
+                .marks
+                .iter()
+                .filter(|op| !since.contains(op.id.0, op.id.1))
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+impl Encode for MarkOp {
+    fn encode(&self, w: &mut Writer) {
+        self.id.encode(w);
+        self.name.encode(w);
+        self.value.encode(w);
+        self.start.encode(w);
+        self.end.encode(w);
+    }
+}
+
+impl Decode for MarkOp {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Ok(Self {
+            id: Decode::decode(r)?,
+            name: String::decode(r)?,
+            value: Option::decode(r)?,
+            start: Anchor::decode(r)?,
+            end: Anchor::decode(r)?,
+        })
+    }
+}
+
+impl Encode for RichText {
+    fn encode(&self, w: &mut Writer) {
+        self.text.encode(w);
+        self.marks.encode(w);
+    }
+}
+
+impl Decode for RichText {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        let text = TextCrdt::decode(r)?;
+        let mut marks = Vec::<MarkOp>::decode(r)?;
+        marks.sort_by_key(MarkOp::stamp);
+        marks.dedup_by_key(|op| op.stamp());
+        Ok(Self { text, marks })
+    }
+}
+
+impl Encode for RichTextDelta {
+    fn encode(&self, w: &mut Writer) {
+        self.text.encode(w);
+        self.marks.encode(w);
+    }
+}
+
+impl Decode for RichTextDelta {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Ok(Self {
+            text: TextDelta::decode(r)?,
+            marks: Vec::decode(r)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bold() -> Format {
+        Format::from([("bold".to_string(), String::new())])
+    }
+
+    fn run_texts(t: &RichText) -> Vec<(String, Format)> {
+        t.runs().into_iter().map(|r| (r.text, r.format)).collect()
+    }
+
+    #[test]
+    fn add_and_remove_marks() {
+        let mut t = RichText::new(1);
+        t.insert_str(0, "one two three").unwrap();
+        t.add_mark(0..7, "bold", "", Expand::After).unwrap();
+        t.remove_mark(4..7, "bold", Expand::None).unwrap();
+
+        assert_eq!(
+            run_texts(&t),
+            vec![
+                ("one ".into(), bold()),
+                ("two three".into(), Format::new()),
+            ]
+        );
+        assert_eq!(t.format_at(0), bold());
+        assert_eq!(t.format_at(5), Format::new());
+    }
+
+    #[test]
+    fn expand_controls_boundary_inserts() {
+        let mut t = RichText::new(1);
+        t.insert_str(0, "[ab]").unwrap();
+        t.add_mark(1..3, "bold", "", Expand::After).unwrap();
+        t.add_mark(1..3, "link", "x", Expand::None).unwrap();
+
+        t.insert(3, '+').unwrap(); // end boundary
+        t.insert(1, '-').unwrap(); // start boundary
+        assert_eq!(t.text().to_string(), "[-ab+]");
+
+        assert_eq!(t.format_at(1), Format::new());
+        assert_eq!(t.format_at(4), bold());
+        let mut both = bold();
+        both.insert("link".into(), "x".into());
+        assert_eq!(t.format_at(2), both);
+    }
+
+    #[test]
+    fn expand_before_and_both() {
+        let mut t = RichText::new(1);
+        t.insert_str(0, "abcd").unwrap();
+        t.add_mark(1..3, "b", "", Expand::Before).unwrap();
+        t.add_mark(1..3, "c", "", Expand::Both).unwrap();
+        t.insert(3, 'y').unwrap();
+        t.insert(1, 'x').unwrap();
+        assert_eq!(t.text().to_string(), "axbcyd");
+
+        assert!(t.format_at(1).contains_key("b"));
+        assert!(t.format_at(1).contains_key("c"));
+        assert!(!t.format_at(4).contains_key("b"));
+        assert!(t.format_at(4).contains_key("c"));
+    }
+
+    #[test]
+    fn concurrent_marks_merge_deterministically() {
+        let mut a = RichText::new(1);
+        a.insert_str(0, "hello world").unwrap();
+        let mut b = a.fork(2);
+
+        a.add_mark(0..11, "color", "red", Expand::None).unwrap();
+        b.add_mark(3..8, "color", "blue", Expand::None).unwrap();
+        b.remove_mark(0..2, "color", Expand::None).unwrap();
+        a.insert_str(5, ",").unwrap();
+
+        let mut ab = a.clone();
+        ab.merge(&b);
+        let mut ba = b.clone();
+        ba.merge(&a);
+        assert_eq!(ab.runs(), ba.runs());
+        assert!(ab.marks().eq(ba.marks()));
+
+        // Both replicas' ops have the same counter; actor 2 wins the tie.
+        let texts: Vec<String> = ab.runs().into_iter().map(|r| r.text).collect();
+        assert_eq!(texts, vec!["he", "l", "lo, wo", "rld"]);
+        assert_eq!(ab.format_at(4)["color"], "blue");
+        assert_eq!(ab.format_at(9)["color"], "red");
+    }
+
+    #[test]
+    fn marks_follow_deleted_boundaries() {
+        let mut t = RichText::new(1);
+        t.insert_str(0, "abcdef").unwrap();
+        t.add_mark(2..4, "bold", "", Expand::None).unwrap();
+        t.remove(2).unwrap();
+        t.remove(2).unwrap();
+        assert_eq!(run_texts(&t), vec![("abef".into(), Format::new())]);
+
+        t.insert_str(2, "XY").unwrap();
+        assert_eq!(run_texts(&t), vec![("abXYef".into(), Format::new())]);
+    }
+
+    #[test]
+    fn delta_since_carries_marks() {
+        let mut a = RichText::new(1);
+        a.insert_str(0, "text").unwrap();
+        let mut b = a.fork(2);
+        a.add_mark(1..3, "em", "", Expand::After).unwrap();
+
+        let d = a.delta_since(&b.summary());
+        assert_eq!(d.marks.len(), 1);
+        b.apply_delta(&d);
+        assert_eq!(b.runs(), a.runs());
+        assert!(a.delta_since(&b.summary()).marks.is_empty());
+    }
+
+    #[test]
+    fn out_of_range_mark_is_rejected() {
+        let mut t = RichText::new(1);
+        t.insert_str(0, "ab").unwrap();
+        assert!(t.add_mark(1..3, "bold", "", Expand::None).is_err());
+        #[allow(clippy::reversed_empty_ranges)]
+        let reversed = 2..1;
+        assert!(t.remove_mark(reversed, "bold", Expand::None).is_err());
+        assert_eq!(t.marks().count(), 0);
+    }
+
+    #[test]
+    fn codec_roundtrip() {
+        let mut t = RichText::new(1);
+        t.insert_str(0, "hello").unwrap();
+        t.add_mark(0..5, "link", "https://example.com", Expand::None)
+            .unwrap();
+        let back: RichText = crate::codec::from_bytes(&crate::codec::to_bytes(&t)).unwrap();
+        assert_eq!(back, t);
+    }
+}
@@ -1,30 +1,163 @@
 //! A chunked vector providing O(sqrt(n)) insert/remove for sequence CRDTs.
-
+//!
+//! Each chunk caches its [`Metrics`], and a Fenwick tree over the chunks
+//! finds the chunk holding a given position (raw or visible) in O(log n).
+//! An optional id index maps each element to its chunk, so an element can
+//! be found by id in O(log n) as well. An element may span a range of ids
+//! (e.g. a run of characters), in which case lookups by any id in the range
+//! find it.
+
+use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
+use core::ops::{Add, Bound, Sub};
+
+use crate::NodeId;
 
 const CHUNK_SIZE: usize = 256;
 
+/// Counts a [`ChunkedVec`] maintains per chunk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Metrics {
+    /// Number of elements.
+    pub count: usize,
+    /// Number of units held, where an element may hold several (e.g. the
+    /// characters of a run).
+    pub units: usize,
+    /// Number of visible (e.g. non-tombstoned) units.
+    pub visible: usize,
+    /// Length of the visible units in UTF-8 bytes, for text.
+    pub utf8: usize,
+    /// Length of the visible units in UTF-16 code units, for text.
+    pub utf16: usize,
+    /// Number of visible line breaks, for text.
+    pub lines: usize,
+}
+
+impl Add for Metrics {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            count: self.count + rhs.count,
+            units: self.units + rhs.units,
+            visible: self.visible + rhs.visible,
+            utf8: self.utf8 + rhs.utf8,
+            utf16: self.utf16 + rhs.utf16,
+            lines: self.lines + rhs.lines,
+        }
+    }
+}
+
+impl Sub for Metrics {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            count: self.count - rhs.count,
+            units: self.units - rhs.units,
+            visible: self.visible - rhs.visible,
+            utf8: self.utf8 - rhs.utf8,
+            utf16: self.utf16 - rhs.utf16,
+            lines: self.lines - rhs.lines,
+        }
+    }
+}
+
+/// Measures a single element. `count` is filled in by the container.
+pub(crate) type Measure<T> = fn(&T) -> Metrics;
+
+#[cfg(test)]
+fn measure_nothing<T>(_: &T) -> Metrics {
+    Metrics::default()
+}
+
+/// Unique id of an element, as used by sequence CRDTs.
+pub(crate) type ElementId = (NodeId, u64);
+
+/// Extracts an element's first id and the number of ids it spans: an
+/// element `((actor, c), n)` covers `(actor, c)..(actor, c + n)`. The first
+/// id must not change while the element is stored; the span may.
+pub(crate) type IdOf<T> = fn(&T) -> (ElementId, u64);
+
+/// Maps the first id of each element to the (stable) id of the chunk
+/// holding it.
+struct IdIndex<T> {
+    id_of: IdOf<T>,
+    chunk_of: BTreeMap<ElementId, u64>,
+}
+
+impl<T> Clone for IdIndex<T> {
+    fn clone(&self) -> Self {
+        Self {
+            id_of: self.id_of,
+            chunk_of: self.chunk_of.clone(),
+        }
+    }
+}
+
 /// A chunked sequence that provides O(sqrt(n)) insert/remove.
 /// Each chunk holds up to CHUNK_SIZE elements. When a chunk exceeds
 /// 2*CHUNK_SIZE it splits; when adjacent chunks are both below CHUNK_SIZE/2
 /// they merge.
+///
+/// Elements are only mutable through [`ChunkedVec::update`], which keeps
+/// the cached metrics in step.
 pub(crate) struct ChunkedVec<T> {
     chunks: Vec<Vec<T>>,
-    total_len: usize,
+    /// Metrics of each chunk.
+    metrics: Vec<Metrics>,
+    /// Fenwick tree over `metrics` (1-based; `tree[0]` is unused).
+    tree: Vec<Metrics>,
+    total: Metrics,
+    measure: Measure<T>,
+    /// Stable id of each chunk, which survives chunks shifting position.
+    chunk_ids: Vec<u64>,
+    /// Current position of each chunk id.
+    chunk_slots: BTreeMap<u64, usize>,
+    next_chunk_id: u64,
+    index: Option<IdIndex<T>>,
 }
 
 impl<T> ChunkedVec<T> {
-    /// Create a new empty `ChunkedVec`.
+    /// Create a new empty `ChunkedVec` that only counts elements.
+    #[cfg(test)]
     pub fn new() -> Self {
+        Self::with_measure(measure_nothing)
+    }
+
+    /// Create a new empty `ChunkedVec` whose metrics come from `measure`.
+    pub fn with_measure(measure: Measure<T>) -> Self {
         Self {
             chunks: Vec::new(),
-            total_len: 0,
+            metrics: Vec::new(),
+            tree: Vec::new(),
+            total: Metrics::default(),
+            measure,
+            chunk_ids: Vec::new(),
+            chunk_slots: BTreeMap::new(),
+            next_chunk_id: 0,
+            index: None,
         }
     }
 
+    /// Index elements by `id_of`, enabling [`ChunkedVec::position_of`].
+    pub fn indexed_by(mut self, id_of: IdOf<T>) -> Self {
+        let mut chunk_of = BTreeMap::new();
+        for (chunk, &chunk_id) in self.chunks.iter().zip(&self.chunk_ids) {
+            for item in chunk {
+                chunk_of.insert(id_of(item).0, chunk_id);
+            }
+        }
+        self.index = Some(IdIndex { id_of, chunk_of });
+        self
+    }
+
     /// Total number of elements.
     pub fn len(&self) -> usize {
-        self.total_len
+        self.total.count
+    }
+
+    /// Metrics summed over all elements.
+    pub fn metrics(&self) -> Metrics {
+        self.total
     }
 
     /// Insert `value` at the given global `index`.
@@ -32,15 +165,25 @@ impl<T> ChunkedVec<T> {
     /// # Panics
     /// Panics if `index > self.len()`.
     pub fn insert(&mut self, index: usize, value: T) {
-        assert!(index <= self.total_len, "index out of bounds");
+        assert!(index <= self.total.count, "index out of bounds");
 
         if self.chunks.is_empty() {
+            let chunk_id = self.new_chunk_id();
             self.chunks.push(Vec::with_capacity(CHUNK_SIZE));
+            self.metrics.push(Metrics::default());
+            self.chunk_ids.push(chunk_id);
+            self.rebuild_tree();
         }
 
         let (chunk_idx, local_idx) = self.locate_for_insert(index);
+        let m = self.measure_one(&value);
+        if let Some(index) = &mut self.index {
+            index
+                .chunk_of
+                .insert((index.id_of)(&value).0, self.chunk_ids[chunk_idx]);
+        }
         self.chunks[chunk_idx].insert(local_idx, value);
-        self.total_len += 1;
+        self.add_metrics(chunk_idx, m);
 
         if self.chunks[chunk_idx].len() > 2 * CHUNK_SIZE {
             self.split(chunk_idx);
@@ -52,15 +195,22 @@ impl<T> ChunkedVec<T> {
     /// # Panics
     /// Panics if `index >= self.len()`.
     pub fn remove(&mut self, index: usize) -> T {
-        assert!(index < self.total_len, "index out of bounds");
+        assert!(index < self.total.count, "index out of bounds");
 
         let (chunk_idx, local_idx) = self.locate(index);
         let val = self.chunks[chunk_idx].remove(local_idx);
-        self.total_len -= 1;
+        let m = self.measure_one(&val);
+        self.sub_metrics(chunk_idx, m);
+        if let Some(index) = &mut self.index {
+            index.chunk_of.remove(&(index.id_of)(&val).0);
+        }
 
         // Remove empty chunks
         if self.chunks[chunk_idx].is_empty() {
             self.chunks.remove(chunk_idx);
+            self.metrics.remove(chunk_idx);
+            self.chunk_ids.remove(chunk_idx);
+            self.rebuild_tree();
         } else {
             self.try_merge(chunk_idx);
         }
@@ -70,20 +220,29 @@ impl<T> ChunkedVec<T> {
 
     /// Get a reference to the element at `index`.
     pub fn get(&self, index: usize) -> Option<&T> {
-        if index >= self.total_len {
+        if index >= self.total.count {
             return None;
         }
         let (chunk_idx, local_idx) = self.locate(index);
         Some(&self.chunks[chunk_idx][local_idx])
     }
 
-    /// Get a mutable reference to the element at `index`.
-    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
-        if index >= self.total_len {
-            return None;
-        }
+    /// Mutate the element at `index` in place, re-measuring it afterwards.
+    ///
+    /// # Panics
+    /// Panics if `index >= self.len()`.
+    pub fn update<R>(&mut self, index: usize, f: impl FnOnce(&mut T) -> R) -> R {
+        assert!(index < self.total.count, "index out of bounds");
         let (chunk_idx, local_idx) = self.locate(index);
-        Some(&mut self.chunks[chunk_idx][local_idx])
+        let item = &mut self.chunks[chunk_idx][local_idx];
+        let before = (self.measure)(item);
+        let result = f(item);
+        let after = (self.measure)(item);
+        if before != after {
+            self.sub_metrics(chunk_idx, before);
+            self.add_metrics(chunk_idx, after);
+        }
+        result
     }
 
     /// Iterate over all elements in order.
@@ -91,33 +250,55 @@ impl<T> ChunkedVec<T> {
         self.chunks.iter().flat_map(|c| c.iter())
     }
 
+    /// Iterate over the elements from global `index` on, in O(log n) to
+    /// start.
+    pub fn iter_from(&self, index: usize) -> impl Iterator<Item = &T> {
+        let (chunk_idx, local_idx) = if index < self.total.count {
+            self.locate(index)
+        } else {
+            (self.chunks.len(), 0)
+        };
+        self.chunks[chunk_idx..]
+            .iter()
+            .enumerate()
+            .flat_map(move |(i, c)| c[if i == 0 { local_idx } else { 0 }..].iter())
+    }
+
     /// Append a value to the end.
+    #[cfg(test)]
     pub fn push(&mut self, value: T) {
-        let len = self.total_len;
+        let len = self.total.count;
         self.insert(len, value);
     }
 
-    /// Build from a flat `Vec`.
+    /// Build from a flat `Vec` that only counts elements.
+    #[cfg(test)]
     pub fn from_vec(v: Vec<T>) -> Self {
-        let total_len = v.len();
-        if total_len == 0 {
-            return Self::new();
-        }
-        let mut chunks = Vec::new();
+        Self::from_vec_with_measure(v, measure_nothing)
+    }
+
+    /// Build from a flat `Vec`, measuring elements with `measure`.
+    pub fn from_vec_with_measure(v: Vec<T>, measure: Measure<T>) -> Self {
+        let mut cv = Self::with_measure(measure);
         let mut iter = v.into_iter();
         loop {
             let chunk: Vec<T> = iter.by_ref().take(CHUNK_SIZE).collect();
             if chunk.is_empty() {
                 break;
             }
-            chunks.push(chunk);
+            let chunk_id = cv.new_chunk_id();
+            cv.metrics.push(cv.measure_chunk(&chunk));
+            cv.chunks.push(chunk);
+            cv.chunk_ids.push(chunk_id);
         }
-        Self { chunks, total_len }
+        cv.rebuild_tree();
+        cv
     }
 
     /// Convert into a flat `Vec`.
+    #[cfg(test)]
     pub fn into_vec(self) -> Vec<T> {
-        let mut result = Vec::with_capacity(self.total_len);
+        let mut result = Vec::with_capacity(self.total.count);
         for chunk in self.chunks {
             result.extend(chunk);
         }
@@ -125,6 +306,7 @@ impl<T> ChunkedVec<T> {
     }
 
     /// Find the first element matching the predicate, returning its global index.
+    #[cfg(test)]
     pub fn position<F: Fn(&T) -> bool>(&self, predicate: F) -> Option<usize> {
         let mut global = 0;
         for chunk in &self.chunks {
@@ -138,38 +320,225 @@ impl<T> ChunkedVec<T> {
         None
     }
 
+    /// Whether an element spanning the given id is stored.
+    ///
+    /// # Panics
+    /// Panics if the vector is not [indexed](ChunkedVec::indexed_by).
+    #[cfg(test)]
+    pub fn contains_id(&self, id: ElementId) -> bool {
+        self.position_of(id).is_some()
+    }
+
+    /// Global index of the element spanning the given id, and the id's
+    /// offset within that element, in O(log n).
+    ///
+    /// # Panics
+    /// Panics if the vector is not [indexed](ChunkedVec::indexed_by).
+    pub fn position_of(&self, id: ElementId) -> Option<(usize, usize)> {
+        let index = self.id_index();
+        let (&first, chunk_id) = index.chunk_of.range(..=id).next_back()?;
+        if first.0 != id.0 {
+            return None;
+        }
+        let chunk_idx = self.chunk_slots[chunk_id];
+        let (local_idx, span) = self.chunks[chunk_idx]
+            .iter()
+            .enumerate()
+            .find_map(|(i, item)| {
+                let (start, span) = (index.id_of)(item);
+                (start == first).then_some((i, span))
+            })
+            .expect("id index out of sync with its chunk");
+        let offset = id.1 - first.1;
+        (offset < span).then(|| {
+            let offset = usize::try_from(offset).expect("span fits in memory");
+            (self.chunk_start(chunk_idx).count + local_idx, offset)
+        })
+    }
+
+    /// Smallest first id of a stored element that is greater than `id`.
+    ///
+    /// # Panics
+    /// Panics if the vector is not [indexed](ChunkedVec::indexed_by).
+    pub fn first_id_after(&self, id: ElementId) -> Option<ElementId> {
+        self.id_index()
+            .chunk_of
+            .range((Bound::Excluded(id), Bound::Unbounded))
+            .next()
+            .map(|(&first, _)| first)
+    }
+
+    /// Metrics summed over the elements before global `index`.
+    ///
+    /// # Panics
+    /// Panics if `index > self.len()`.
+    pub fn metrics_before(&self, index: usize) -> Metrics {
+        assert!(index <= self.total.count, "index out of bounds");
+        if index == self.total.count {
+            return self.total;
+        }
+        let (chunk_idx, local_idx) = self.locate(index);
+        self.chunks[chunk_idx][..local_idx]
+            .iter()
+            .fold(self.chunk_start(chunk_idx), |acc, item| {
+                acc + self.measure_one(item)
+            })
+    }
+
+    /// Global index of the element covering unit `target` of the metric
+    /// selected by `key` (e.g. the `target`-th visible unit), together with
+    /// the unit's offset within that element, in O(log n).
+    ///
+    /// Returns `None` if `target` is past the metric's total.
+    pub fn find_by(&self, key: fn(&Metrics) -> usize, target: usize) -> Option<(usize, usize)> {
+        let (chunk_idx, before) = self.seek(key, target)?;
+        let mut remaining = target - key(&before);
+        for (local_idx, item) in self.chunks[chunk_idx].iter().enumerate() {
+            let units = key(&self.measure_one(item));
+            if remaining < units {
+                return Some((before.count + local_idx, remaining));
+            }
+            remaining -= units;
+        }
+        unreachable!("chunk metrics out of sync with its elements")
+    }
+
     // --- internal helpers ---
 
-    /// Locate the chunk and local index for a given global index (for access/remove).
-    fn locate(&self, index: usize) -> (usize, usize) {
-        let mut remaining = index;
-        for (ci, chunk) in self.chunks.iter().enumerate() {
-            if remaining < chunk.len() {
-                return (ci, remaining);
+    fn id_index(&self) -> &IdIndex<T> {
+        self.index
+            .as_ref()
+            .expect("ChunkedVec is not indexed by id")
+    }
+
+    fn new_chunk_id(&mut self) -> u64 {
+        self.next_chunk_id += 1;
+        self.next_chunk_id
+    }
+
+    /// Point every element of chunk `chunk_idx` at that chunk in the id index.
+    fn reindex_chunk(&mut self, chunk_idx: usize) {
+        if let Some(index) = &mut self.index {
+            let chunk_id = self.chunk_ids[chunk_idx];
+            for item in &self.chunks[chunk_idx] {
+                index.chunk_of.insert((index.id_of)(item).0, chunk_id);
             }
-            remaining -= chunk.len();
         }
-        panic!("index out of bounds");
     }
 
-    /// Locate for insertion (allows index == chunk.len() for end-of-chunk).
-    fn locate_for_insert(&self, index: usize) -> (usize, usize) {
-        let mut remaining = index;
-        for (ci, chunk) in self.chunks.iter().enumerate() {
-            if remaining <= chunk.len() {
-                return (ci, remaining);
+    /// Metrics of all chunks before `chunk_idx`.
+    fn chunk_start(&self, chunk_idx: usize) -> Metrics {
+        let mut acc = Metrics::default();
+        let mut i = chunk_idx;
+        while i > 0 {
+            acc = acc + self.tree[i];
+            i -= i & i.wrapping_neg();
+        }
+        acc
+    }
+
+    fn measure_one(&self, item: &T) -> Metrics {
+        Metrics {
+            count: 1,
+            ..(self.measure)(item)
+        }
+    }
+
+    fn measure_chunk(&self, chunk: &[T]) -> Metrics {
+        chunk
+            .iter()
+            .fold(Metrics::default(), |acc, item| acc + self.measure_one(item))
+    }
+
+    fn add_metrics(&mut self, chunk_idx: usize, m: Metrics) {
+        self.metrics[chunk_idx] = self.metrics[chunk_idx] + m;
+        self.total = self.total + m;
+        let mut i = chunk_idx + 1;
+        while i < self.tree.len() {
+            self.tree[i] = self.tree[i] + m;
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    fn sub_metrics(&mut self, chunk_idx: usize, m: Metrics) {
+        self.metrics[chunk_idx] = self.metrics[chunk_idx] - m;
+        self.total = self.total - m;
+        let mut i = chunk_idx + 1;
+        while i < self.tree.len() {
+            self.tree[i] = self.tree[i] - m;
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    /// Rebuild the Fenwick tree and chunk slots after chunks were added,
+    /// removed or split.
+    fn rebuild_tree(&mut self) {
+        self.chunk_slots = self
+            .chunk_ids
+            .iter()
+            .enumerate()
+            .map(|(slot, &id)| (id, slot))
+            .collect();
+        let n = self.metrics.len();
+        self.tree.clear();
+        self.tree.push(Metrics::default());
+        self.tree.extend_from_slice(&self.metrics);
+        for i in 1..=n {
+            let parent = i + (i & i.wrapping_neg());
+            if parent <= n {
+                self.tree[parent] = self.tree[parent] + self.tree[i];
             }
-            remaining -= chunk.len();
         }
-        // Append to last chunk
-        let last = self.chunks.len() - 1;
-        (last, self.chunks[last].len())
+        self.total = self.metrics.iter().fold(Metrics::default(), |a, &m| a + m);
+    }
+
+    /// Find the chunk covering unit `target` of `key`, together with the
+    /// metrics of all chunks before it.
+    fn seek(&self, key: fn(&Metrics) -> usize, target: usize) -> Option<(usize, Metrics)> {
+        let n = self.chunks.len();
+        if n == 0 {
+            return None;
+        }
+        let mut pos = 0;
+        let mut before = Metrics::default();
+        let mut step = 1 << (usize::BITS - 1 - n.leading_zeros());
+        while step > 0 {
+            let next = pos + step;
+            if next <= n && key(&before) + key(&self.tree[next]) <= target {
+                pos = next;
+                before = before + self.tree[next];
+            }
+            step >>= 1;
+        }
+        (pos < n).then_some((pos, before))
+    }
+
+    /// Locate the chunk and local index for a given global index (for access/remove).
+    fn locate(&self, index: usize) -> (usize, usize) {
+        let (chunk_idx, before) = self.seek(|m| m.count, index).expect("index out of bounds");
+        (chunk_idx, index - before.count)
+    }
+
+    /// Locate for insertion (allows index == len() to append to the last chunk).
+    fn locate_for_insert(&self, index: usize) -> (usize, usize) {
+        if index == self.total.count {
+            let last = self.chunks.len() - 1;
+            return (last, self.chunks[last].len());
+        }
+        self.locate(index)
     }
 
     fn split(&mut self, chunk_idx: usize) {
         let mid = self.chunks[chunk_idx].len() / 2;
         let second_half = self.chunks[chunk_idx].split_off(mid);
+        let second_metrics = self.measure_chunk(&second_half);
+        self.metrics[chunk_idx] = self.metrics[chunk_idx] - second_metrics;
+        let chunk_id = self.new_chunk_id();
         self.chunks.insert(chunk_idx + 1, second_half);
+        self.metrics.insert(chunk_idx + 1, second_metrics);
+        self.chunk_ids.insert(chunk_idx + 1, chunk_id);
+        self.reindex_chunk(chunk_idx + 1);
+        self.rebuild_tree();
     }
 
     fn try_merge(&mut self, chunk_idx: usize) {
@@ -181,14 +550,24 @@ impl<T> ChunkedVec<T> {
             && self.chunks[chunk_idx].len() + self.chunks[chunk_idx + 1].len() <= CHUNK_SIZE
         {
             let next = self.chunks.remove(chunk_idx + 1);
+            let next_metrics = self.metrics.remove(chunk_idx + 1);
+            self.chunk_ids.remove(chunk_idx + 1);
             self.chunks[chunk_idx].extend(next);
+            self.metrics[chunk_idx] = self.metrics[chunk_idx] + next_metrics;
+            self.reindex_chunk(chunk_idx);
+            self.rebuild_tree();
         }
         // Try merging with previous chunk
         else if chunk_idx > 0
             && self.chunks[chunk_idx - 1].len() + self.chunks[chunk_idx].len() <= CHUNK_SIZE
         {
             let current = self.chunks.remove(chunk_idx);
+            let current_metrics = self.metrics.remove(chunk_idx);
+            self.chunk_ids.remove(chunk_idx);
             self.chunks[chunk_idx - 1].extend(current);
+            self.metrics[chunk_idx - 1] = self.metrics[chunk_idx - 1] + current_metrics;
+            self.reindex_chunk(chunk_idx - 1);
+            self.rebuild_tree();
         }
     }
 }
@@ -197,7 +576,14 @@ impl<T: Clone> Clone for ChunkedVec<T> {
     fn clone(&self) -> Self {
         Self {
             chunks: self.chunks.clone(),
-            total_len: self.total_len,
+            metrics: self.metrics.clone(),
+            tree: self.tree.clone(),
+            total: self.total,
+            measure: self.measure,
+            chunk_ids: self.chunk_ids.clone(),
+            chunk_slots: self.chunk_slots.clone(),
+            next_chunk_id: self.next_chunk_id,
+            index: self.index.clone(),
         }
     }
 }
@@ -205,7 +591,7 @@ impl<T: Clone> Clone for ChunkedVec<T> {
 impl<T: core::fmt::Debug> core::fmt::Debug for ChunkedVec<T> {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("ChunkedVec")
-            .field("total_len", &self.total_len)
+            .field("total", &self.total)
             .field("num_chunks", &self.chunks.len())
             .finish()
     }
@@ -213,7 +599,7 @@ impl<T: core::fmt::Debug> core::fmt::Debug for ChunkedVec<T> {
 
 impl<T: PartialEq> PartialEq for ChunkedVec<T> {
     fn eq(&self, other: &Self) -> bool {
-        if self.total_len != other.total_len {
+        if self.total.count != other.total.count {
             return false;
         }
         self.iter().zip(other.iter()).all(|(a, b)| a == b)
@@ -229,17 +615,11 @@ impl<T> core::ops::Index<usize> for ChunkedVec<T> {
     }
 }
 
-impl<T> core::ops::IndexMut<usize> for ChunkedVec<T> {
-    fn index_mut(&mut self, index: usize) -> &mut T {
-        self.get_mut(index).expect("index out of bounds")
-    }
-}
-
 #[cfg(feature = "serde")]
 impl<T: serde::Serialize> serde::Serialize for ChunkedVec<T> {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         use serde::ser::SerializeSeq;
-        let mut seq = serializer.serialize_seq(Some(self.total_len))?;
+        let mut seq = serializer.serialize_seq(Some(self.total.count))?;
         for chunk in &self.chunks {
             for item in chunk {
                 seq.serialize_element(item)?;
@@ -249,14 +629,6 @@ impl<T: serde::Serialize> serde::Serialize for ChunkedVec<T> {
     }
 }
 
-#[cfg(feature = "serde")]
-impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for ChunkedVec<T> {
-    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
-        let v: Vec<T> = Vec::deserialize(deserializer)?;
-        Ok(Self::from_vec(v))
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -333,6 +705,15 @@ mod tests {
         assert_eq!(collected, expected);
     }
 
+    #[test]
+    fn iter_from_starts_mid_chunk() {
+        let cv = ChunkedVec::from_vec((0..600).collect::<Vec<i32>>());
+        for start in [0, 1, 255, 256, 300, 599, 600] {
+            let collected: Vec<i32> = cv.iter_from(start).copied().collect();
+            assert_eq!(collected, (start as i32..600).collect::<Vec<_>>());
+        }
+    }
+
     #[test]
     fn chunks_split_when_large() {
         let mut cv = ChunkedVec::new();
@@ -346,4 +727,149 @@ mod tests {
             assert_eq!(cv.get(i), Some(&i));
         }
     }
+
+    fn even(x: &i32) -> Metrics {
+        Metrics {
+            visible: usize::from(x % 2 == 0),
+            ..Metrics::default()
+        }
+    }
+
+    fn assert_tree_consistent(cv: &ChunkedVec<i32>) {
+        let items: Vec<i32> = cv.iter().copied().collect();
+        let evens: Vec<usize> = (0..items.len()).filter(|&i| items[i] % 2 == 0).collect();
+        assert_eq!(cv.metrics().visible, evens.len());
+        for (n, &raw) in evens.iter().enumerate() {
+            assert_eq!(cv.find_by(|m| m.visible, n), Some((raw, 0)));
+        }
+        assert_eq!(cv.find_by(|m| m.visible, evens.len()), None);
+        for (i, item) in items.iter().enumerate() {
+            assert_eq!(cv.get(i), Some(item));
+        }
+    }
+
+    #[test]
+    fn find_by_counts_measured_elements() {
+        let mut cv = ChunkedVec::from_vec_with_measure((0..2000).collect(), even);
+        assert_eq!(cv.metrics(), Metrics { count: 2000, visible: 1000, ..Metrics::default() });
+        assert_eq!(cv.find_by(|m| m.visible, 0), Some((0, 0)));
+        assert_eq!(cv.find_by(|m| m.visible, 700), Some((1400, 0)));
+        assert_eq!(cv.find_by(|m| m.count, 1999), Some((1999, 0)));
+        assert_tree_consistent(&cv);
+
+        // Splits, merges and chunk removal keep the tree in step.
+        for i in 0..700 {
+            cv.insert((i as usize * 7) % (cv.len() + 1), i);
+        }
+        assert_tree_consistent(&cv);
+        while cv.len() > 10 {
+            cv.remove((cv.len() * 3) / 5);
+        }
+        assert_tree_consistent(&cv);
+    }
+
+    #[test]
+    fn update_remeasures_element() {
+        let mut cv = ChunkedVec::from_vec_with_measure((0..600).collect(), even);
+        cv.update(10, |x| *x = 11);
+        cv.update(599, |x| *x = 0);
+        assert_eq!(cv.metrics().visible, 300);
+        assert_eq!(cv.find_by(|m| m.visible, 5), Some((12, 0)));
+        assert_tree_consistent(&cv);
+    }
+
+    #[test]
+    fn empty_find_by() {
+        let cv = ChunkedVec::<i32>::with_measure(even);
+        assert_eq!(cv.find_by(|m| m.visible, 0), None);
+    }
+
+    fn id(x: &i32) -> ElementId {
+        (0, *x as u64)
+    }
+
+    fn id_of(x: &i32) -> (ElementId, u64) {
+        (id(x), 1)
+    }
+
+    fn assert_index_consistent(cv: &ChunkedVec<i32>) {
+        for (i, x) in cv.iter().enumerate() {
+            assert_eq!(cv.position_of(id(x)), Some((i, 0)));
+        }
+    }
+
+    #[test]
+    fn position_of_follows_splits_and_merges() {
+        let mut cv = ChunkedVec::from_vec((0..300).collect()).indexed_by(id_of);
+        assert_index_consistent(&cv);
+
+        for i in 300..1500 {
+            cv.insert(((i as usize) * 13) % (cv.len() + 1), i);
+        }
+        assert!(cv.chunks.len() > 3);
+        assert_index_consistent(&cv);
+
+        for i in (0..1500).step_by(2) {
+            let (at, _) = cv.position_of(id(&i)).unwrap();
+            assert_eq!(cv.remove(at), i);
+        }
+        assert_eq!(cv.position_of(id(&0)), None);
+        assert!(!cv.contains_id(id(&0)));
+        assert!(cv.contains_id(id(&1)));
+        assert_index_consistent(&cv);
+
+        let cloned = cv.clone();
+        assert_index_consistent(&cloned);
+    }
+
+    #[test]
+    fn metrics_before_sums_prefix() {
+        let cv = ChunkedVec::from_vec_with_measure((0..1000).collect(), even);
+        assert_eq!(cv.metrics_before(0), Metrics::default());
+        assert_eq!(
+            cv.metrics_before(301),
+            Metrics { count: 301, visible: 151, ..Metrics::default() }
+        );
+        assert_eq!(cv.metrics_before(1000), cv.metrics());
+    }
+
+    /// Runs `(actor, first, len)`, each spanning `len` consecutive ids.
+    type Span = (NodeId, u64, u64);
+
+    fn span_of(run: &Span) -> (ElementId, u64) {
+        ((run.0, run.1), run.2)
+    }
+
+    fn units(run: &Span) -> Metrics {
+        Metrics {
+            units: run.2 as usize,
+            visible: run.2 as usize,
+            ..Metrics::default()
+        }
+    }
+
+    #[test]
+    fn spans_are_found_by_any_of_their_ids() {
+        let runs: Vec<Span> = (0..600).map(|i| (i % 3, i * 10, 4)).collect();
+        let mut cv = ChunkedVec::from_vec_with_measure(runs, units).indexed_by(span_of);
+        assert_eq!(cv.metrics().units, 2400);
+        assert_eq!(cv.position_of((1, 13)), Some((1, 3)));
+        assert_eq!(cv.position_of((1, 14)), None);
+        assert_eq!(cv.position_of((2, 10)), None);
+        assert_eq!(cv.first_id_after((1, 11)), Some((1, 40)));
+        assert_eq!(cv.first_id_after((2, 5990)), None);
+        assert_eq!(cv.find_by(|m| m.visible, 2001), Some((500, 1)));
+
+        // Growing a run in place extends the ids it covers.
+        cv.update(1, |run| run.2 = 6);
+        assert_eq!(cv.position_of((1, 15)), Some((1, 5)));
+        assert_eq!(cv.metrics().units, 2402);
+        for i in 0..300 {
+            cv.remove(cv.len() / 2);
+            cv.insert(i, (7, i as u64, 1));
+        }
+        for (i, run) in cv.iter().enumerate() {
+            assert_eq!(cv.position_of((run.0, run.1 + run.2 - 1)), Some((i, run.2 as usize - 1)));
+        }
+    }
 }
@@ -0,0 +1,579 @@
+//! Deterministic network simulator for multi-replica testing.
+//!
+//! A [`Simulation`] runs N replicas of any [`DeltaCrdt`] over a simulated
+//! network that drops, duplicates, delays and reorders messages, and can be
+//! split into partitions. All randomness comes from a single `u64` seed, so
+//! a failing run is reproduced exactly by rerunning with the same seed.
+//!
+//! Time advances in ticks. [`Simulation::gossip`] makes every replica send a
+//! delta to every peer it can reach; [`Simulation::step`] advances one tick
+//! and delivers the messages that are due. [`Simulation::quiesce`] heals all
+//! partitions, lets the network recover, and keeps gossiping until a round
+//! completes with no replica changed, after which
+//! [`Simulation::assert_converged`] checks that every replica agrees.
+//!
+//! # Example
+//!
+//! ```
+//! use crdt_kit::prelude::*;
+//! use crdt_kit::sim::{SimConfig, Simulation};
+//!
+//! let replicas = (1..=3).map(GCounter::new).collect();
+//! let mut sim = Simulation::new(replicas, SimConfig::chaotic(), 42);
+//!
+//! sim.partition(&[&[0], &[1, 2]]);
+//! sim.update(0, |c| c.increment_by(5));
+//! sim.update(2, |c| c.increment());
+//! sim.gossip();
+//! sim.run(10);
+//!
+//! sim.quiesce(100).unwrap();
+//! sim.assert_converged(|c| c.value());
+//! assert_eq!(sim.replica(1).value(), 6);
+//! ```
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::DeltaCrdt;
+
+/// Fault model for the simulated network.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimConfig {
+    /// Probability that a message is lost, in `[0.0, 1.0)`.
+    pub drop_probability: f64,
+    /// Probability that a message is delivered twice, in `[0.0, 1.0]`.
+    pub duplicate_probability: f64,
+    /// Minimum delivery delay in ticks.
+    pub min_delay: u64,
+    /// Maximum delivery delay in ticks.
+    pub max_delay: u64,
+    /// Allow messages on the same link to overtake each other.
+    ///
+    /// When `false`, each sender→receiver link is FIFO.
+    pub reorder: bool,
+}
+
+impl SimConfig {
+    /// A perfect network: every message arrives once, in order, next tick.
+    #[must_use]
+    pub fn reliable() -> Self {
+        Self {
+            drop_probability: 0.0,
+            duplicate_probability: 0.0,
+            min_delay: 1,
+            max_delay: 1,
+            reorder: false,
+        }
+    }
+
+    /// A hostile network: 20% loss, 10% duplication, 1–10 tick delays with
+    /// reordering.
+    #[must_use]
+    pub fn chaotic() -> Self {
+        Self {
+            drop_probability: 0.2,
+            duplicate_probability: 0.1,
+            min_delay: 1,
+            max_delay: 10,
+            reorder: true,
+        }
+    }
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self::reliable()
+    }
+}
+
+/// Counters describing what the network did during a simulation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SimStats {
+    /// Messages handed to the network.
+    pub sent: u64,
+    /// Messages delivered to a replica (duplicates count twice).
+    pub delivered: u64,
+    /// Messages lost to the fault model or to a partition.
+    pub dropped: u64,
+    /// Extra copies injected by the fault model.
+    pub duplicated: u64,
+}
+
+/// Error returned when a simulation fails to settle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimError {
+    /// Replicas were still changing after the allotted gossip rounds.
+    NotQuiescent {
+        /// Seed of the failing run.
+        seed: u64,
+        /// Rounds attempted.
+        rounds: usize,
+    },
+}
+
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotQuiescent { seed, rounds } => {
+                write!(f, "not quiescent after {rounds} rounds (seed {seed})")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SimError {}
+
+/// SplitMix64: tiny, fast, and good enough to drive a fault model.
+#[derive(Debug, Clone)]
+pub(crate) struct Rng(pub(crate) u64);
+
+impl Rng {
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0.0, 1.0)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in `[lo, hi]`.
+    pub(crate) fn range(&mut self, lo: u64, hi: u64) -> u64 {
+        if hi <= lo {
+            return lo;
+        }
+        lo + self.next_u64() % (hi - lo + 1)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct InFlight<D> {
+    from: usize,
+    to: usize,
+    deliver_at: u64,
+    /// Tie-breaker among messages due on the same tick.
+    order: u64,
+    delta: D,
+}
+
+/// N replicas of a CRDT connected by a seeded, faulty network.
+#[derive(Debug, Clone)]
+pub struct Simulation<C: DeltaCrdt> {
+    replicas: Vec<C>,
+    config: SimConfig,
+    seed: u64,
+    rng: Rng,
+    now: u64,
+    next_order: u64,
+    in_flight: Vec<InFlight<C::Delta>>,
+    /// Partition group of each replica; replicas talk only within a group.
+    groups: Vec<usize>,
+    /// Latest delivery time per `(from, to)` link, for FIFO links.
+    link_clock: Vec<u64>,
+    changed: bool,
+    stats: SimStats,
+}
+
+impl<C> Simulation<C>
+where
+    C: DeltaCrdt + Clone + PartialEq,
+    C::Delta: Clone,
+{
+    /// Create a simulation over `replicas`, driven by `seed`.
+    pub fn new(replicas: Vec<C>, config: SimConfig, seed: u64) -> Self {
+        let n = replicas.len();
+        Self {
+            replicas,
+            config,
+            seed,
+            rng: Rng(seed),
+            now: 0,
+            next_order: 0,
+            in_flight: Vec::new(),
+            groups: alloc::vec![0; n],
+            link_clock: alloc::vec![0; n * n],
+            changed: false,
+            stats: SimStats::default(),
+        }
+    }
+
+    /// The seed this simulation was created with.
+    #[must_use]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Current simulated time in ticks.
+    #[must_use]
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    /// Network counters so far.
+    #[must_use]
+    pub fn stats(&self) -> SimStats {
+        self.stats
+    }
+
+    /// Number of replicas.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.replicas.len()
+    }
+
+    /// Check whether the simulation has no replicas.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.replicas.is_empty()
+    }
+
+    /// Get replica `i`.
+    #[must_use]
+    pub fn replica(&self, i: usize) -> &C {
+        &self.replicas[i]
+    }
+
+    /// All replicas, in index order.
+    #[must_use]
+    pub fn replicas(&self) -> &[C] {
+        &self.replicas
+    }
+
+    /// Number of messages currently in the network.
+    #[must_use]
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Perform a local operation on replica `i`.
+    pub fn update<R>(&mut self, i: usize, op: impl FnOnce(&mut C) -> R) -> R {
+        op(&mut self.replicas[i])
+    }
+
+    /// A pseudo-random number in `[0, n)` from the simulation's seed.
+    ///
+    /// Lets test drivers pick replicas and operations reproducibly.
+    pub fn random_below(&mut self, n: u64) -> u64 {
+        if n == 0 {
+            return 0;
+        }
+        self.rng.next_u64() % n
+    }
+
+    /// Split the replicas into isolated groups.
+    ///
+    /// Replicas not listed end up together in one extra group. Messages
+    /// already in flight between groups are lost when they arrive.
+    pub fn partition(&mut self, groups: &[&[usize]]) {
+        let rest = groups.len();
+        for g in &mut self.groups {
+            *g = rest;
+        }
+        for (gi, members) in groups.iter().enumerate() {
+            for &i in *members {
+                self.groups[i] = gi;
+            }
+        }
+    }
+
+    /// Remove all partitions.
+    pub fn heal(&mut self) {
+        for g in &mut self.groups {
+            *g = 0;
+        }
+    }
+
+    /// Check whether replicas `a` and `b` can currently talk.
+    #[must_use]
+    pub fn connected(&self, a: usize, b: usize) -> bool {
+        self.groups[a] == self.groups[b]
+    }
+
+    /// Send a delta from replica `from` to replica `to`.
+    ///
+    /// The delta is computed against `to`'s state at send time, which is
+    /// what a peer that had advertised its state would receive.
+    pub fn send(&mut self, from: usize, to: usize) {
+        let delta = self.replicas[from].delta(&self.replicas[to]);
+        self.stats.sent += 1;
+        if !self.connected(from, to) || self.rng.next_f64() < self.config.drop_probability {
+            self.stats.dropped += 1;
+            return;
+        }
+        if self.rng.next_f64() < self.config.duplicate_probability {
+            self.stats.duplicated += 1;
+            self.enqueue(from, to, delta.clone());
+        }
+        self.enqueue(from, to, delta);
+    }
+
+    /// Every replica sends a delta to every peer it can reach.
+    pub fn gossip(&mut self) {
+        let n = self.replicas.len();
+        for from in 0..n {
+            for to in 0..n {
+                if from != to && self.connected(from, to) {
+                    self.send(from, to);
+                }
+            }
+        }
+    }
+
+    /// Advance time by one tick and deliver every message now due.
+    ///
+    /// Returns the number of messages delivered.
+    pub fn step(&mut self) -> usize {
+        self.now += 1;
+        let now = self.now;
+        let (mut due, pending): (Vec<_>, Vec<_>) = core::mem::take(&mut self.in_flight)
+            .into_iter()
+            .partition(|m| m.deliver_at <= now);
+        self.in_flight = pending;
+        due.sort_by_key(|m| (m.deliver_at, m.order));
+
+        let mut delivered = 0;
+        for msg in due {
+            if !self.connected(msg.from, msg.to) {
+                self.stats.dropped += 1;
+                continue;
+            }
+            let replica = &mut self.replicas[msg.to];
+            let before = replica.clone();
+            replica.apply_delta(&msg.delta);
+            self.changed |= *replica != before;
+            self.stats.delivered += 1;
+            delivered += 1;
+        }
+        delivered
+    }
+
+    /// Advance `ticks` ticks, delivering messages as they fall due.
+    pub fn run(&mut self, ticks: u64) -> usize {
+        (0..ticks).map(|_| self.step()).sum()
+    }
+
+    /// Step until the network is empty.
+    pub fn drain(&mut self) -> usize {
+        let mut delivered = 0;
+        while !self.in_flight.is_empty() {
+            delivered += self.step();
+        }
+        delivered
+    }
+
+    /// Heal partitions and gossip until the replicas stop changing.
+    ///
+    /// Messages already in flight are delivered under the configured fault
+    /// model. The network then recovers: each round is one
+    /// [`gossip`](Self::gossip) followed by [`drain`](Self::drain) over a
+    /// [`reliable`](SimConfig::reliable) network, so every message of a
+    /// round arrives. The system is quiescent once a round changes no
+    /// replica: every replica then holds everything every other replica
+    /// has. Returns the number of rounds it took.
+    ///
+    /// With faults left on, a round of `n·(n-1)` messages loses none only
+    /// with vanishing probability as `n` grows, so quiescence could never be
+    /// observed.
+    pub fn quiesce(&mut self, max_rounds: usize) -> Result<usize, SimError> {
+        self.heal();
+        self.drain();
+        let config = self.config;
+        self.config = SimConfig::reliable();
+        let result = (1..=max_rounds).find(|_| {
+            self.changed = false;
+            self.gossip();
+            self.drain();
+            !self.changed
+        });
+        self.config = config;
+        result.ok_or(SimError::NotQuiescent {
+            seed: self.seed,
+            rounds: max_rounds,
+        })
+    }
+
+    /// Panic unless every replica shows the same `view`.
+    ///
+    /// The panic message includes the seed, so the run can be replayed.
+    pub fn assert_converged<V, F>(&self, view: F)
+    where
+        V: PartialEq + fmt::Debug,
+        F: Fn(&C) -> V,
+    {
+        let Some(first) = self.replicas.first() else {
+            return;
+        };
+        let expected = view(first);
+        for (i, replica) in self.replicas.iter().enumerate().skip(1) {
+            let actual = view(replica);
+            assert!(
+                actual == expected,
+                "replica {i} diverged (seed {}): {actual:?} != {expected:?}",
+                self.seed,
+            );
+        }
+    }
+
+    fn enqueue(&mut self, from: usize, to: usize, delta: C::Delta) {
+        let mut deliver_at =
+            self.now + self.rng.range(self.config.min_delay, self.config.max_delay);
+        let order = if self.config.reorder {
+            self.rng.next_u64()
+        } else {
+            let link = &mut self.link_clock[from * self.groups.len() + to];
+            deliver_at = deliver_at.max(*link);
+            *link = deliver_at;
+            self.next_order
+        };
+        self.next_order += 1;
+        self.in_flight.push(InFlight {
+            from,
+            to,
+            deliver_at: deliver_at.max(self.now + 1),
+            order,
+            delta,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Crdt, GCounter, ORSet};
+
+    fn counters(n: u64, config: SimConfig, seed: u64) -> Simulation<GCounter> {
+        Simulation::new((1..=n).map(GCounter::new).collect(), config, seed)
+    }
+
+    #[test]
+    fn reliable_network_converges_in_one_round() {
+        let mut sim = counters(3, SimConfig::reliable(), 1);
+        sim.update(0, |c| c.increment_by(3));
+        sim.update(1, |c| c.increment());
+        sim.gossip();
+        sim.drain();
+        sim.assert_converged(|c| c.value());
+        assert_eq!(sim.stats().dropped, 0);
+        assert_eq!(sim.stats().sent, 6);
+    }
+
+    #[test]
+    fn same_seed_same_run() {
+        let run = |seed| {
+            let mut sim = counters(4, SimConfig::chaotic(), seed);
+            for i in 0..4 {
+                sim.update(i, |c| c.increment_by(i as u64 + 1));
+                sim.gossip();
+                sim.run(3);
+            }
+            (sim.stats(), sim.replicas().to_vec())
+        };
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7).0, run(8).0);
+    }
+
+    #[test]
+    fn partition_blocks_messages() {
+        let mut sim = counters(3, SimConfig::reliable(), 3);
+        sim.partition(&[&[0], &[1, 2]]);
+        sim.update(0, |c| c.increment());
+        sim.gossip();
+        sim.drain();
+        assert_eq!(sim.replica(1).value(), 0);
+        assert!(!sim.connected(0, 2));
+
+        sim.quiesce(10).unwrap();
+        assert_eq!(sim.replica(1).value(), 1);
+    }
+
+    #[test]
+    fn in_flight_messages_lost_when_partitioned() {
+        let mut sim = counters(2, SimConfig::reliable(), 4);
+        sim.update(0, |c| c.increment());
+        sim.send(0, 1);
+        sim.partition(&[&[0]]);
+        sim.drain();
+        assert_eq!(sim.replica(1).value(), 0);
+        assert_eq!(sim.stats().dropped, 1);
+    }
+
+    #[test]
+    fn fifo_links_preserve_order() {
+        let config = SimConfig {
+            max_delay: 20,
+            ..SimConfig::reliable()
+        };
+        let mut sim = counters(2, config, 5);
+        for _ in 0..20 {
+            sim.send(0, 1);
+        }
+        let times: Vec<_> = sim.in_flight.iter().map(|m| m.deliver_at).collect();
+        assert!(times.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn chaotic_orset_converges() {
+        for seed in 0..20 {
+            let replicas = (1..=4).map(ORSet::new).collect();
+            let mut sim = Simulation::new(replicas, SimConfig::chaotic(), seed);
+            for step in 0..30u32 {
+                let i = sim.random_below(4) as usize;
+                let x = sim.random_below(5) as u32;
+                if sim.random_below(3) == 0 {
+                    sim.update(i, |s| s.remove(&x));
+                } else {
+                    sim.update(i, |s| s.insert(x));
+                }
+                if step % 10 == 0 {
+                    sim.partition(&[&[0, 1]]);
+                } else if step % 10 == 5 {
+                    sim.heal();
+                }
+                sim.gossip();
+                sim.step();
+            }
+            sim.quiesce(200).unwrap();
+            sim.assert_converged(|s| s.iter().copied().collect::<Vec<_>>());
+
+            // Quiescence means nothing is left to learn from any peer.
+            let mut merged = sim.replica(0).clone();
+            for r in sim.replicas() {
+                merged.merge(r);
+            }
+            assert_eq!(
+                merged.iter().collect::<Vec<_>>(),
+                sim.replica(0).iter().collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn chaotic_network_quiesces_with_many_replicas() {
+        for seed in 0..8 {
+            let mut sim = counters(8, SimConfig::chaotic(), seed);
+            for i in 0..8 {
+                sim.update(i, |c| c.increment_by(i as u64 + 1));
+                sim.gossip();
+                sim.run(2);
+            }
+            let rounds = sim.quiesce(10).unwrap();
+            assert!(rounds <= 2, "seed {seed}: {rounds} rounds");
+            sim.assert_converged(|c| c.value());
+            assert_eq!(sim.replica(0).value(), 36);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "seed 9")]
+    fn divergence_reports_seed() {
+        let mut sim = counters(2, SimConfig::reliable(), 9);
+        sim.update(0, |c| c.increment());
+        sim.assert_converged(|c| c.value());
+    }
+}
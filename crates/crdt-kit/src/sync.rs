@@ -0,0 +1,514 @@
+//! Transport-agnostic anti-entropy sync protocol.
+//!
+//! A [`SyncSession`] is a sans-IO state machine that brings two replicas of
+//! the same CRDT up to date with each other. It never touches a socket:
+//! feed it incoming [`SyncMessage`]s with [`SyncSession::handle`] and drain
+//! the messages it wants to send with [`SyncSession::poll_outgoing`]. This
+//! makes it usable over any transport — and testable entirely in memory.
+//!
+//! The protocol is symmetric. Each side runs one session:
+//!
+//! ```text
+//!   A                                   B
+//!   |--- Hello(summary_a) ------------->|
+//!   |<------------- Hello(summary_b) ---|
+//!   |--- Delta(a.delta_since(b)) ------>|   B applies, acks
+//!   |<------ Delta(b.delta_since(a)) ---|   A applies, acks
+//!   |<------------------------- Ack ----|
+//!   |--- Ack -------------------------->|
+//!   |--- Done ------------------------->|
+//!   |<------------------------- Done ---|
+//! ```
+//!
+//! Summaries are produced by [`DeltaSince`], so neither side needs a copy of
+//! the other's state. The `Hello` message also carries the
+//! [`Versioned`] type and schema version, and mismatched peers are rejected.
+//!
+//! # Example
+//!
+//! ```
+//! use crdt_kit::prelude::*;
+//! use crdt_kit::sync::SyncSession;
+//!
+//! let mut a = GCounter::new(1);
+//! a.increment_by(3);
+//! let mut b = GCounter::new(2);
+//! b.increment();
+//!
+//! let mut session_a = SyncSession::new();
+//! let mut session_b = SyncSession::new();
+//! session_a.start(&a);
+//! session_b.start(&b);
+//!
+//! // Pump messages until both sides are done.
+//! while !(session_a.is_done() && session_b.is_done()) {
+//!     while let Some(msg) = session_a.poll_outgoing() {
+//!         session_b.handle(&mut b, msg).unwrap();
+//!     }
+//!     while let Some(msg) = session_b.poll_outgoing() {
+//!         session_a.handle(&mut a, msg).unwrap();
+//!     }
+//! }
+//!
+//! assert_eq!(a.value(), 4);
+//! assert_eq!(b.value(), 4);
+//! ```
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::version::{CrdtType, VersionError, Versioned};
+use crate::DeltaSince;
+
+/// A message exchanged by two [`SyncSession`]s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "C::Summary: serde::Serialize, C::Delta: serde::Serialize",
+        deserialize = "C::Summary: serde::Deserialize<'de>, C::Delta: serde::Deserialize<'de>"
+    ))
+)]
+pub enum SyncMessage<C: DeltaSince> {
+    /// Opens the session with the sender's causal summary.
+    Hello {
+        /// CRDT type of the sender's replica.
+        crdt_type: CrdtType,
+        /// Schema version of the sender's replica.
+        version: u8,
+        /// Summary of the operations the sender has observed.
+        summary: C::Summary,
+    },
+    /// Everything the receiver is missing, according to its summary.
+    Delta(C::Delta),
+    /// Acknowledges that the peer's delta has been applied.
+    Ack,
+    /// The sender has nothing left to exchange.
+    Done,
+}
+
+/// Progress of a [`SyncSession`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyncState {
+    /// Created, no message sent or received yet.
+    Idle,
+    /// Our `Hello` is queued; waiting for the peer's summary.
+    AwaitingSummary,
+    /// Deltas are in flight in one or both directions.
+    Exchanging,
+    /// Our `Done` is queued; waiting for the peer's `Done`.
+    Finishing,
+    /// Both sides have finished.
+    Done,
+}
+
+/// Error raised while handling a [`SyncMessage`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncError {
+    /// The peer replicates a different CRDT type.
+    CrdtTypeMismatch {
+        /// Our CRDT type.
+        expected: CrdtType,
+        /// The peer's CRDT type.
+        found: CrdtType,
+    },
+    /// The peer uses a different schema version.
+    VersionMismatch {
+        /// Our schema version.
+        expected: u8,
+        /// The peer's schema version.
+        found: u8,
+    },
+    /// A message arrived that is not valid in the current state.
+    UnexpectedMessage {
+        /// State of the session when the message arrived.
+        state: SyncState,
+    },
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CrdtTypeMismatch { expected, found } => {
+                write!(f, "peer replicates {found:?}, expected {expected:?}")
+            }
+            Self::VersionMismatch { expected, found } => {
+                write!(f, "peer uses schema version {found}, expected {expected}")
+            }
+            Self::UnexpectedMessage { state } => {
+                write!(f, "unexpected sync message in state {state:?}")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SyncError {}
+
+/// One side of a two-party anti-entropy exchange.
+///
+/// The session does not own the replica; pass it to [`start`](Self::start)
+/// and [`handle`](Self::handle) so it stays usable between messages.
+pub struct SyncSession<C: DeltaSince> {
+    state: SyncState,
+    outbox: VecDeque<SyncMessage<C>>,
+    delta_acked: bool,
+    peer_delta_applied: bool,
+    peer_done: bool,
+}
+
+impl<C: DeltaSince + Versioned> SyncSession<C> {
+    /// Create an idle session.
+    pub fn new() -> Self {
+        Self {
+            state: SyncState::Idle,
+            outbox: VecDeque::new(),
+            delta_acked: false,
+            peer_delta_applied: false,
+            peer_done: false,
+        }
+    }
+
+    /// Open the session by queueing our `Hello`.
+    ///
+    /// Calling this is optional for the responder: a session that receives
+    /// a `Hello` while idle answers with its own automatically.
+    pub fn start(&mut self, replica: &C) {
+        if self.state == SyncState::Idle {
+            self.outbox.push_back(SyncMessage::Hello {
+                crdt_type: C::CRDT_TYPE,
+                version: C::CURRENT_VERSION,
+                summary: replica.summary(),
+            });
+            self.state = SyncState::AwaitingSummary;
+        }
+    }
+
+    /// Handle a message from the peer, applying any delta to `replica`.
+    pub fn handle(&mut self, replica: &mut C, msg: SyncMessage<C>) -> Result<(), SyncError> {
+        match msg {
+            SyncMessage::Hello {
+                crdt_type,
+                version,
+                summary,
+            } => {
+                if crdt_type != C::CRDT_TYPE {
+                    return Err(SyncError::CrdtTypeMismatch {
+                        expected: C::CRDT_TYPE,
+                        found: crdt_type,
+                    });
+                }
+                if version != C::CURRENT_VERSION {
+                    return Err(SyncError::VersionMismatch {
+                        expected: C::CURRENT_VERSION,
+                        found: version,
+                    });
+                }
+                self.start(replica);
+                if self.state != SyncState::AwaitingSummary {
+                    return Err(self.unexpected());
+                }
+                self.outbox
+                    .push_back(SyncMessage::Delta(replica.delta_since(&summary)));
+                self.state = SyncState::Exchanging;
+            }
+            SyncMessage::Delta(delta) => {
+                // A retransmitted delta is harmless: applying it is idempotent.
+                // Once done, the peer has already had our `Ack`.
+                if self.state == SyncState::Idle {
+                    return Err(self.unexpected());
+                }
+                replica.apply_delta(&delta);
+                self.peer_delta_applied = true;
+                if self.state != SyncState::Done {
+                    self.outbox.push_back(SyncMessage::Ack);
+                }
+            }
+            SyncMessage::Ack => {
+                // Likewise a retransmitted `Ack` after both sides finished.
+                match self.state {
+                    SyncState::Exchanging | SyncState::Finishing => self.delta_acked = true,
+                    SyncState::Done => {}
+                    _ => return Err(self.unexpected()),
+                }
+            }
+            SyncMessage::Done => {
+                if self.state == SyncState::Idle {
+                    return Err(self.unexpected());
+                }
+                self.peer_done = true;
+            }
+        }
+        self.advance();
+        Ok(())
+    }
+
+    /// Take the next message to send to the peer, if any.
+    pub fn poll_outgoing(&mut self) -> Option<SyncMessage<C>> {
+        self.outbox.pop_front()
+    }
+
+    /// Current state of the session.
+    #[must_use]
+    pub fn state(&self) -> SyncState {
+        self.state
+    }
+
+    /// Check whether both sides have finished and nothing is left to send.
+    #[must_use]
+    pub fn is_done(&self) -> bool {
+        self.state == SyncState::Done && self.outbox.is_empty()
+    }
+
+    fn advance(&mut self) {
+        if self.state == SyncState::Exchanging && self.delta_acked && self.peer_delta_applied {
+            self.outbox.push_back(SyncMessage::Done);
+            self.state = SyncState::Finishing;
+        }
+        if self.state == SyncState::Finishing && self.peer_done {
+            self.state = SyncState::Done;
+        }
+    }
+
+    fn unexpected(&self) -> SyncError {
+        SyncError::UnexpectedMessage { state: self.state }
+    }
+}
+
+impl<C: DeltaSince> fmt::Debug for SyncSession<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncSession")
+            .field("state", &self.state)
+            .field("queued", &self.outbox.len())
+            .field("delta_acked", &self.delta_acked)
+            .field("peer_delta_applied", &self.peer_delta_applied)
+            .field("peer_done", &self.peer_done)
+            .finish()
+    }
+}
+
+impl<C: DeltaSince + Versioned> Default for SyncSession<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Converts [`SyncMessage`]s to and from envelope payloads.
+///
+/// The sync protocol is independent of how deltas are serialized. Implement
+/// this trait with the encoding of your choice (for example `serde_json` or
+/// `bincode` with the `serde` feature) to run sessions over a
+/// [`Transport`](crate::transport::Transport).
+pub trait SyncCodec<C: DeltaSince> {
+    /// Encode a message into bytes.
+    fn encode(&self, msg: &SyncMessage<C>) -> Result<Vec<u8>, VersionError>;
+
+    /// Decode a message from bytes.
+    fn decode(&self, payload: &[u8]) -> Result<SyncMessage<C>, VersionError>;
+}
+
+/// Synchronize two in-memory replicas by running a session on each side.
+///
+/// Returns the number of messages exchanged. Useful for tests and for
+/// replicas that live in the same process.
+pub fn sync_in_memory<C: DeltaSince + Versioned>(a: &mut C, b: &mut C) -> Result<usize, SyncError> {
+    let mut session_a = SyncSession::new();
+    let mut session_b = SyncSession::new();
+    session_a.start(a);
+
+    let mut messages = 0;
+    while !(session_a.is_done() && session_b.is_done()) {
+        while let Some(msg) = session_a.poll_outgoing() {
+            session_b.handle(b, msg)?;
+            messages += 1;
+        }
+        while let Some(msg) = session_b.poll_outgoing() {
+            session_a.handle(a, msg)?;
+            messages += 1;
+        }
+    }
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GCounter, ORSet, TextCrdt};
+
+    fn pump<C: DeltaSince + Versioned>(
+        a: &mut C,
+        session_a: &mut SyncSession<C>,
+        b: &mut C,
+        session_b: &mut SyncSession<C>,
+    ) {
+        while !(session_a.is_done() && session_b.is_done()) {
+            while let Some(msg) = session_a.poll_outgoing() {
+                session_b.handle(b, msg).unwrap();
+            }
+            while let Some(msg) = session_b.poll_outgoing() {
+                session_a.handle(a, msg).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn both_sides_start() {
+        let mut a = GCounter::new(1);
+        a.increment_by(5);
+        let mut b = GCounter::new(2);
+        b.increment_by(7);
+
+        let mut sa = SyncSession::new();
+        let mut sb = SyncSession::new();
+        sa.start(&a);
+        sb.start(&b);
+        pump(&mut a, &mut sa, &mut b, &mut sb);
+
+        assert_eq!(a.value(), 12);
+        assert_eq!(b.value(), 12);
+    }
+
+    #[test]
+    fn responder_answers_hello() {
+        let mut a = ORSet::new(1);
+        a.insert("x");
+        let mut b = ORSet::new(2);
+        b.insert("y");
+        b.remove(&"y");
+        b.insert("z");
+
+        let messages = sync_in_memory(&mut a, &mut b).unwrap();
+        // Hello x2, Delta x2, Ack x2, Done x2.
+        assert_eq!(messages, 8);
+        assert!(a.contains(&"x") && a.contains(&"z") && !a.contains(&"y"));
+        assert_eq!(a.iter().collect::<Vec<_>>(), b.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn text_converges() {
+        let mut a = TextCrdt::new(1);
+        a.insert_str(0, "hello").unwrap();
+        let mut b = a.fork(2);
+        a.insert_str(5, " world").unwrap();
+        b.remove(0).unwrap();
+        b.insert(0, 'H').unwrap();
+
+        sync_in_memory(&mut a, &mut b).unwrap();
+        assert_eq!(a.to_string(), b.to_string());
+        assert_eq!(a.to_string(), "Hello world");
+    }
+
+    #[test]
+    fn state_progression() {
+        let mut a = GCounter::new(1);
+        let mut b = GCounter::new(2);
+        let mut sa = SyncSession::new();
+        let mut sb = SyncSession::new();
+        assert_eq!(sa.state(), SyncState::Idle);
+
+        sa.start(&a);
+        assert_eq!(sa.state(), SyncState::AwaitingSummary);
+
+        let hello = sa.poll_outgoing().unwrap();
+        sb.handle(&mut b, hello).unwrap();
+        assert_eq!(sb.state(), SyncState::Exchanging);
+
+        pump(&mut a, &mut sa, &mut b, &mut sb);
+        assert_eq!(sa.state(), SyncState::Done);
+        assert_eq!(sb.state(), SyncState::Done);
+    }
+
+    #[test]
+    fn duplicate_delta_is_idempotent() {
+        let mut a = GCounter::new(1);
+        a.increment_by(2);
+        let mut b = GCounter::new(2);
+        let mut sb = SyncSession::new();
+        sb.start(&b);
+
+        let delta = a.delta_since(&b.summary());
+        sb.handle(&mut b, SyncMessage::Delta(delta.clone()))
+            .unwrap();
+        sb.handle(&mut b, SyncMessage::Delta(delta)).unwrap();
+        assert_eq!(b.value(), 2);
+    }
+
+    #[test]
+    fn duplicate_delta_after_done_is_ignored() {
+        let mut a = GCounter::new(1);
+        a.increment_by(3);
+        let mut b = GCounter::new(2);
+        let mut sa = SyncSession::new();
+        let mut sb = SyncSession::new();
+        sa.start(&a);
+
+        // Keep a copy of a's delta as the network might redeliver it.
+        let hello = sa.poll_outgoing().unwrap();
+        sb.handle(&mut b, hello).unwrap();
+        let hello = sb.poll_outgoing().unwrap();
+        sa.handle(&mut a, hello).unwrap();
+        let delta = sa.poll_outgoing().unwrap();
+        let duplicate = match &delta {
+            SyncMessage::Delta(d) => SyncMessage::Delta(d.clone()),
+            _ => panic!("expected a delta"),
+        };
+        sb.handle(&mut b, delta).unwrap();
+        pump(&mut a, &mut sa, &mut b, &mut sb);
+
+        sb.handle(&mut b, duplicate).unwrap();
+        assert!(sb.is_done());
+        assert_eq!(b.value(), 3);
+        sa.handle(&mut a, SyncMessage::Ack).unwrap();
+        assert!(sa.is_done());
+    }
+
+    #[test]
+    fn rejects_version_mismatch() {
+        let mut b = GCounter::new(2);
+        let mut sb = SyncSession::new();
+        let err = sb.handle(
+            &mut b,
+            SyncMessage::Hello {
+                crdt_type: CrdtType::GCounter,
+                version: 99,
+                summary: crate::VersionVector::new(),
+            },
+        );
+        assert_eq!(
+            err,
+            Err(SyncError::VersionMismatch {
+                expected: 1,
+                found: 99
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_type_mismatch() {
+        let mut b = GCounter::new(2);
+        let mut sb = SyncSession::new();
+        let err = sb.handle(
+            &mut b,
+            SyncMessage::Hello {
+                crdt_type: CrdtType::ORSet,
+                version: 1,
+                summary: crate::VersionVector::new(),
+            },
+        );
+        assert!(matches!(err, Err(SyncError::CrdtTypeMismatch { .. })));
+    }
+
+    #[test]
+    fn rejects_ack_before_hello() {
+        let mut b = GCounter::new(2);
+        let mut sb = SyncSession::new();
+        assert_eq!(
+            sb.handle(&mut b, SyncMessage::Ack),
+            Err(SyncError::UnexpectedMessage {
+                state: SyncState::Idle
+            })
+        );
+    }
+}
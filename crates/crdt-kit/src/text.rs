@@ -1,7 +1,14 @@
+use alloc::string::String;
+use alloc::vec::Vec;
 use core::fmt;
+use core::ops::Range;
 
-use crate::rga::{Rga, RgaDelta, RgaError};
-use crate::{Crdt, DeltaCrdt, NodeId};
+use crate::clock::HybridTimestamp;
+use crate::codec::{CodecError, Decode, Encode, Reader, Writer};
+use crate::diff;
+use crate::rga::{Anchor, BlameRun, Gravity, Rga, RgaDelta, RgaError, Splice};
+use crate::rope::Metrics;
+use crate::{Crdt, DeltaCrdt, DeltaSince, NodeId, VersionVector};
 
 /// Error type for TextCrdt operations.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -22,6 +29,18 @@ pub enum TextError {
         /// The current length of the visible text.
         len: usize,
     },
+    /// A UTF-8 or UTF-16 offset falls inside a character.
+    NotCharBoundary {
+        /// The offset that was requested.
+        offset: usize,
+    },
+    /// Line is past the last line of the text.
+    LineOutOfBounds {
+        /// The line that was requested.
+        line: usize,
+        /// The current number of lines.
+        lines: usize,
+    },
 }
 
 impl fmt::Display for TextError {
@@ -33,6 +52,12 @@ impl fmt::Display for TextError {
             Self::RangeOutOfBounds { start, end, len } => {
                 write!(f, "range {start}..{end} out of bounds for text of length {len}")
             }
+            Self::NotCharBoundary { offset } => {
+                write!(f, "offset {offset} is not on a character boundary")
+            }
+            Self::LineOutOfBounds { line, lines } => {
+                write!(f, "line {line} out of bounds for text of {lines} lines")
+            }
         }
     }
 }
@@ -40,10 +65,20 @@ impl fmt::Display for TextError {
 #[cfg(feature = "std")]
 impl std::error::Error for TextError {}
 
+impl From<RgaError> for TextError {
+    fn from(e: RgaError) -> Self {
+        match e {
+            RgaError::IndexOutOfBounds { index, len } => Self::IndexOutOfBounds { index, len },
+        }
+    }
+}
+
 /// A collaborative text CRDT based on RGA (Replicated Growable Array).
 ///
-/// This is a thin wrapper around [`Rga<char>`] that provides text-specific
-/// convenience methods like `insert_str`, `remove_range`, and `Display`.
+/// This is a thin wrapper around an [`Rga`] of characters that provides
+/// text-specific convenience methods like `insert_str`, `remove_range`, and
+/// `Display`. Characters typed in a row are stored together as one `String`
+/// run, so a document takes little more memory than its text.
 ///
 /// # Example
 ///
@@ -64,15 +99,15 @@ impl std::error::Error for TextError {}
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-pub struct TextCrdt(Rga<char>);
+pub struct TextCrdt(Rga<char, String>);
 
-/// Delta for [`TextCrdt`] — delegates to [`RgaDelta<char>`].
-pub type TextDelta = RgaDelta<char>;
+/// Delta for [`TextCrdt`] — an [`RgaDelta`] over `String` runs.
+pub type TextDelta = RgaDelta<char, String>;
 
 impl TextCrdt {
     /// Create a new empty text CRDT for the given node.
     pub fn new(actor: NodeId) -> Self {
-        Self(Rga::new(actor))
+        Self(Rga::with_runs(actor))
     }
 
     /// Create a fork of this replica with a different node ID.
@@ -82,51 +117,286 @@ impl TextCrdt {
 
     /// Insert a character at the given visible index.
     pub fn insert(&mut self, index: usize, ch: char) -> Result<(), TextError> {
-        self.0.insert_at(index, ch).map_err(|e| match e {
-            RgaError::IndexOutOfBounds { index, len } => {
-                TextError::IndexOutOfBounds { index, len }
-            }
-        })
+        self.0.insert_at(index, ch)?;
+        Ok(())
     }
 
     /// Insert a string at the given visible index.
     pub fn insert_str(&mut self, index: usize, s: &str) -> Result<(), TextError> {
-        if index > self.0.len() {
-            return Err(TextError::IndexOutOfBounds {
-                index,
-                len: self.0.len(),
-            });
-        }
-        for (i, ch) in s.chars().enumerate() {
-            self.insert(index + i, ch)?;
-        }
+        self.0.insert_run(index, s.into())?;
+        Ok(())
+    }
+
+    /// Insert a string at the given visible index, recording `timestamp`
+    /// (e.g. from a [`HybridClock`](crate::clock::HybridClock)) as the time
+    /// of the insert for [`blame`](Self::blame).
+    ///
+    /// Only inserts made this way carry a timestamp, so text that never
+    /// needs one stores none.
+    pub fn insert_str_with_timestamp(
+        &mut self,
+        index: usize,
+        s: &str,
+        timestamp: HybridTimestamp,
+    ) -> Result<(), TextError> {
+        self.0.insert_run_with_timestamp(index, s.into(), timestamp)?;
         Ok(())
     }
 
     /// Remove (tombstone) the character at the given visible index.
     pub fn remove(&mut self, index: usize) -> Result<(), TextError> {
-        self.0.remove(index).map(|_| ()).map_err(|e| match e {
-            RgaError::IndexOutOfBounds { index, len } => {
-                TextError::IndexOutOfBounds { index, len }
-            }
-        })
+        self.0.remove(index)?;
+        Ok(())
     }
 
     /// Remove a range of characters starting at `start` with the given `count`.
     pub fn remove_range(&mut self, start: usize, count: usize) -> Result<(), TextError> {
         let len = self.0.len();
-        if start + count > len {
-            return Err(TextError::RangeOutOfBounds {
-                start,
-                end: start + count,
-                len,
+        match start.checked_add(count) {
+            Some(end) if end <= len => {}
+            end => {
+                return Err(TextError::RangeOutOfBounds {
+                    start,
+                    end: end.unwrap_or(usize::MAX),
+                    len,
+                })
+            }
+        }
+        self.0.remove_range(start, count)?;
+        Ok(())
+    }
+
+    /// Length of the visible text in UTF-8 bytes.
+    #[must_use]
+    pub fn len_utf8(&self) -> usize {
+        self.0.metrics().utf8
+    }
+
+    /// Length of the visible text in UTF-16 code units.
+    #[must_use]
+    pub fn len_utf16(&self) -> usize {
+        self.0.metrics().utf16
+    }
+
+    /// Character index at UTF-8 byte `offset` (`0..=len_utf8()`). Only the
+    /// run holding the offset is scanned.
+    pub fn utf8_to_index(&self, offset: usize) -> Result<usize, TextError> {
+        self.index_at(offset, Self::len_utf8, |m| m.utf8, |c| c.len_utf8())
+    }
+
+    /// UTF-8 byte offset of character `index` (`0..=len()`). Only the run
+    /// holding the character is scanned.
+    pub fn index_to_utf8(&self, index: usize) -> Result<usize, TextError> {
+        self.0
+            .offset_at_index(|m| m.utf8, |c| c.len_utf8(), index)
+            .ok_or(TextError::IndexOutOfBounds {
+                index,
+                len: self.len(),
+            })
+    }
+
+    /// Character index at UTF-16 code unit `offset` (`0..=len_utf16()`).
+    /// Only the run holding the offset is scanned.
+    pub fn utf16_to_index(&self, offset: usize) -> Result<usize, TextError> {
+        self.index_at(offset, Self::len_utf16, |m| m.utf16, |c| c.len_utf16())
+    }
+
+    /// UTF-16 code unit offset of character `index` (`0..=len()`). Only the
+    /// run holding the character is scanned.
+    pub fn index_to_utf16(&self, index: usize) -> Result<usize, TextError> {
+        self.0
+            .offset_at_index(|m| m.utf16, |c| c.len_utf16(), index)
+            .ok_or(TextError::IndexOutOfBounds {
+                index,
+                len: self.len(),
+            })
+    }
+
+    /// Insert a string at UTF-8 byte `offset`.
+    pub fn insert_str_utf8(&mut self, offset: usize, s: &str) -> Result<(), TextError> {
+        self.insert_str(self.utf8_to_index(offset)?, s)
+    }
+
+    /// Insert a string at UTF-16 code unit `offset`.
+    pub fn insert_str_utf16(&mut self, offset: usize, s: &str) -> Result<(), TextError> {
+        self.insert_str(self.utf16_to_index(offset)?, s)
+    }
+
+    /// Remove the characters in UTF-8 bytes `start..start + len`.
+    pub fn remove_range_utf8(&mut self, start: usize, len: usize) -> Result<(), TextError> {
+        let (from, to) = self.range_at(start, len, Self::utf8_to_index, Self::len_utf8)?;
+        self.remove_range(from, to - from)
+    }
+
+    /// Remove the characters in UTF-16 code units `start..start + len`.
+    pub fn remove_range_utf16(&mut self, start: usize, len: usize) -> Result<(), TextError> {
+        let (from, to) = self.range_at(start, len, Self::utf16_to_index, Self::len_utf16)?;
+        self.remove_range(from, to - from)
+    }
+
+    /// Number of lines: one more than the number of line breaks (`'\n'`),
+    /// so an empty text has one empty line.
+    #[must_use]
+    pub fn line_count(&self) -> usize {
+        self.0.metrics().lines + 1
+    }
+
+    /// Zero-based line and column (in characters) of character `index`
+    /// (`0..=len()`). Only the runs holding the character and the start of
+    /// its line are scanned.
+    pub fn index_to_line_col(&self, index: usize) -> Result<(usize, usize), TextError> {
+        let line = self
+            .0
+            .offset_at_index(|m| m.lines, line_breaks, index)
+            .ok_or(TextError::IndexOutOfBounds {
+                index,
+                len: self.len(),
+            })?;
+        Ok((line, index - self.line_start(line)?))
+    }
+
+    /// Character index of zero-based `line` and `col` (in characters).
+    /// `col` may be at most the length of the line.
+    pub fn line_col_to_index(&self, line: usize, col: usize) -> Result<usize, TextError> {
+        let range = self.line_range(line)?;
+        if col > range.len() {
+            return Err(TextError::IndexOutOfBounds {
+                index: col,
+                len: range.len(),
             });
         }
-        // Remove from back to front so indices stay valid
-        for _ in 0..count {
-            self.remove(start)?;
+        Ok(range.start + col)
+    }
+
+    /// Character range of zero-based `line`, without its line break.
+    pub fn line_range(&self, line: usize) -> Result<Range<usize>, TextError> {
+        let start = self.line_start(line)?;
+        let end = self
+            .0
+            .index_of_unit(|m| m.lines, line_breaks, line)
+            .unwrap_or(self.len());
+        Ok(start..end)
+    }
+
+    /// Text of zero-based `line`, without its line break.
+    pub fn line(&self, line: usize) -> Result<String, TextError> {
+        let range = self.line_range(line)?;
+        Ok(self.0.values_from(range.start).take(range.len()).collect())
+    }
+
+    /// Text of each line in `lines` that exists, without line breaks. Each
+    /// line is found from per-run line counts, without rendering the rest
+    /// of the text.
+    pub fn lines(&self, lines: Range<usize>) -> impl Iterator<Item = String> + '_ {
+        let end = lines.end.min(self.line_count());
+        (lines.start..end).map(|line| self.line(line).expect("line exists"))
+    }
+
+    /// Character index where zero-based `line` starts.
+    fn line_start(&self, line: usize) -> Result<usize, TextError> {
+        let Some(before) = line.checked_sub(1) else {
+            return Ok(0);
+        };
+        self.0
+            .index_of_unit(|m| m.lines, line_breaks, before)
+            .map(|index| index + 1)
+            .ok_or(TextError::LineOutOfBounds {
+                line,
+                lines: self.line_count(),
+            })
+    }
+
+    /// Character index at `offset` in the encoding measured by `total`,
+    /// `key` and `width`.
+    fn index_at(
+        &self,
+        offset: usize,
+        total: fn(&Self) -> usize,
+        key: fn(&Metrics) -> usize,
+        width: fn(&char) -> usize,
+    ) -> Result<usize, TextError> {
+        let len = total(self);
+        if offset > len {
+            return Err(TextError::IndexOutOfBounds { index: offset, len });
         }
-        Ok(())
+        self.0
+            .index_at_offset(key, width, offset)
+            .ok_or(TextError::NotCharBoundary { offset })
+    }
+
+    /// Character indices of the code unit range `start..start + len`.
+    fn range_at(
+        &self,
+        start: usize,
+        len: usize,
+        to_index: fn(&Self, usize) -> Result<usize, TextError>,
+        total: fn(&Self) -> usize,
+    ) -> Result<(usize, usize), TextError> {
+        let total = total(self);
+        let end = match start.checked_add(len) {
+            Some(end) if end <= total => end,
+            end => {
+                return Err(TextError::RangeOutOfBounds {
+                    start,
+                    end: end.unwrap_or(usize::MAX),
+                    len: total,
+                })
+            }
+        };
+        Ok((to_index(self, start)?, to_index(self, end)?))
+    }
+
+    /// Make the visible text equal to `text` with as few character
+    /// inserts and removals as possible, found with a Myers diff.
+    ///
+    /// For editors that report only their full new content: characters the
+    /// edit left in place keep their ids, so anchors on them stay put and
+    /// concurrent edits around them merge as if the edit had been typed.
+    /// Between long, largely unrelated texts the diff bounds its search and
+    /// may replace more than strictly necessary.
+    pub fn update_from(&mut self, text: &str) {
+        let old: Vec<char> = self.0.visible_runs().flat_map(|run| run.chars()).collect();
+        let new: Vec<char> = text.chars().collect();
+        // Back to front, so the indices of earlier hunks stay valid.
+        for hunk in diff::diff(&old, &new).into_iter().rev() {
+            if !hunk.old.is_empty() {
+                self.0
+                    .remove_span(hunk.old.start, hunk.old.len())
+                    .expect("hunk lies within the text");
+            }
+            let inserted: String = new[hunk.new].iter().collect();
+            self.0
+                .insert_run(hunk.old.start, inserted)
+                .expect("hunk lies within the text");
+        }
+    }
+
+    /// Create an [`Anchor`] at the given position (`0..=len()`), e.g. for a
+    /// cursor that should stay on the same characters across merges.
+    pub fn anchor_at(&self, index: usize, gravity: Gravity) -> Result<Anchor, TextError> {
+        Ok(self.0.anchor_at(index, gravity)?)
+    }
+
+    /// Current position of `anchor`; see [`Rga::resolve`].
+    #[must_use]
+    pub fn resolve(&self, anchor: Anchor) -> usize {
+        self.0.resolve(anchor)
+    }
+
+    /// Allocate an id from this replica's counter, for ops layered on the text.
+    pub(crate) fn next_id(&mut self) -> (NodeId, u64) {
+        self.0.next_id()
+    }
+
+    /// Whether `anchor`'s character is known to this replica.
+    pub(crate) fn knows(&self, anchor: Anchor) -> bool {
+        self.0.knows(anchor)
+    }
+
+    /// The underlying sequence, for edits recorded by an
+    /// [`UndoManager`](crate::undo::UndoManager).
+    pub(crate) fn rga_mut(&mut self) -> &mut Rga<char, String> {
+        &mut self.0
     }
 
     /// Return the number of visible (non-deleted) characters.
@@ -146,6 +416,61 @@ impl TextCrdt {
     pub fn actor(&self) -> NodeId {
         self.0.actor()
     }
+
+    /// Get the version vector of this text.
+    #[must_use]
+    pub fn version_vector(&self) -> VersionVector {
+        self.0.version_vector()
+    }
+
+    /// Who inserted each visible character and when, as runs of
+    /// consecutive characters with the same author and timestamp; ranges
+    /// are character indices.
+    ///
+    /// ```
+    /// use crdt_kit::clock::HybridClock;
+    /// use crdt_kit::prelude::*;
+    ///
+    /// let mut clock = HybridClock::with_time_source(1, || 1_000);
+    /// let mut alice = TextCrdt::new(1);
+    /// let written = clock.now();
+    /// alice.insert_str_with_timestamp(0, "hello world", written).unwrap();
+    /// let mut bob = alice.fork(2);
+    /// bob.insert_str(5, ",").unwrap();
+    /// alice.merge(&bob);
+    ///
+    /// let authors: Vec<_> = alice
+    ///     .blame()
+    ///     .into_iter()
+    ///     .map(|run| (run.range, run.actor, run.timestamp))
+    ///     .collect();
+    /// assert_eq!(
+    ///     authors,
+    ///     vec![(0..5, 1, Some(written)), (5..6, 2, None), (6..12, 1, Some(written))]
+    /// );
+    /// ```
+    #[must_use]
+    pub fn blame(&self) -> Vec<BlameRun> {
+        self.0.blame()
+    }
+
+    /// [`merge`](Crdt::merge) `other` into this text, returning how the
+    /// visible text changed as [`Splice`]s in character indices.
+    pub fn merge_with_patches(&mut self, other: &Self) -> Vec<Splice<String>> {
+        self.0.merge_with_patches(&other.0)
+    }
+
+    /// [`apply_delta`](DeltaCrdt::apply_delta) `delta` to this text,
+    /// returning how the visible text changed as [`Splice`]s in character
+    /// indices.
+    pub fn apply_delta_with_patches(&mut self, delta: &TextDelta) -> Vec<Splice<String>> {
+        self.0.apply_delta_with_patches(delta)
+    }
+}
+
+/// Line breaks a character contributes to the line count.
+fn line_breaks(c: &char) -> usize {
+    usize::from(*c == '\n')
 }
 
 impl Crdt for TextCrdt {
@@ -166,15 +491,39 @@ impl DeltaCrdt for TextCrdt {
     }
 }
 
+impl DeltaSince for TextCrdt {
+    type Summary = VersionVector;
+
+    fn summary(&self) -> VersionVector {
+        self.version_vector()
+    }
+
+    fn delta_since(&self, since: &VersionVector) -> TextDelta {
+        self.0.delta_since(since)
+    }
+}
+
 impl fmt::Display for TextCrdt {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for ch in self.0.iter() {
-            write!(f, "{}", ch)?;
+        for run in self.0.visible_runs() {
+            f.write_str(run)?;
         }
         Ok(())
     }
 }
 
+impl Encode for TextCrdt {
+    fn encode(&self, w: &mut Writer) {
+        self.0.encode(w);
+    }
+}
+
+impl Decode for TextCrdt {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Ok(Self(Rga::decode(r)?))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -370,4 +719,249 @@ mod tests {
         t.insert_str(0, "hello").unwrap();
         assert_eq!(format!("{t}"), "hello");
     }
+
+    #[test]
+    fn delta_since_equivalent_to_merge() {
+        let mut t1 = TextCrdt::new(1);
+        t1.insert_str(0, "hello").unwrap();
+
+        let mut t2 = t1.fork(2);
+        t2.insert_str(5, " world").unwrap();
+        t1.remove(0).unwrap();
+
+        let mut via_merge = t2.clone();
+        via_merge.merge(&t1);
+
+        let d = t1.delta_since(&t2.version_vector());
+        t2.apply_delta(&d);
+        assert_eq!(t2.to_string(), via_merge.to_string());
+        assert_eq!(t2.to_string(), "ello world");
+    }
+
+    #[test]
+    fn selection_survives_remote_edits() {
+        let mut t1 = TextCrdt::new(1);
+        t1.insert_str(0, "select me please").unwrap();
+        let mut t2 = t1.fork(2);
+
+        // Select "me" so that typing at either edge stays outside.
+        let start = t1.anchor_at(7, Gravity::Right).unwrap();
+        let end = t1.anchor_at(9, Gravity::Left).unwrap();
+
+        t2.insert_str(0, ">> ").unwrap();
+        t2.insert_str(10, "[").unwrap();
+        t2.insert_str(13, "]").unwrap();
+        t2.remove(8).unwrap();
+        t1.merge(&t2);
+
+        assert_eq!(t1.to_string(), ">> selec [me] please");
+        let (s, e) = (t1.resolve(start), t1.resolve(end));
+        assert_eq!(&t1.to_string()[s..e], "me");
+    }
+
+    #[test]
+    fn concurrent_words_do_not_interleave() {
+        let mut t1 = TextCrdt::new(1);
+        t1.insert_str(0, "Hi !").unwrap();
+        let mut t2 = t1.fork(2);
+
+        t1.insert_str(3, "Alice").unwrap();
+        t2.insert_str(3, "Bob").unwrap();
+
+        t1.merge(&t2);
+        t2.merge(&t1);
+        assert_eq!(t1.to_string(), t2.to_string());
+        assert!(["Hi AliceBob!", "Hi BobAlice!"].contains(&t1.to_string().as_str()));
+    }
+
+    #[test]
+    fn typed_text_encodes_close_to_its_length() {
+        let mut t = TextCrdt::new(1);
+        for (i, ch) in "the quick brown fox ".repeat(500).chars().enumerate() {
+            t.insert(i, ch).unwrap();
+        }
+        t.insert_str(t.len(), &"jumps ".repeat(500)).unwrap();
+        let bytes = crate::codec::to_bytes(&t);
+        assert!(bytes.len() < t.len() + 64, "{} bytes", bytes.len());
+        assert_eq!(TextCrdt::decode(&mut Reader::new(&bytes)).unwrap(), t);
+
+        let mut other = TextCrdt::new(2);
+        other.apply_delta(&t.delta_since(&VersionVector::new()));
+        assert_eq!(other.to_string(), t.to_string());
+        assert!(crate::codec::to_bytes(&other).len() < t.len() + 64);
+    }
+
+    #[test]
+    fn range_delete_travels_as_spans() {
+        let mut t1 = TextCrdt::new(1);
+        t1.insert_str(0, &"lorem ipsum ".repeat(5000)).unwrap();
+        let mut t2 = t1.fork(2);
+        t2.insert_str(30_000, "kept").unwrap();
+        t1.merge(&t2);
+
+        let summary = t2.summary();
+        t1.remove_range(10, 50_000).unwrap();
+        let delta = t1.delta_since(&summary);
+        let dot = (1, 60_005);
+        let spans = vec![(1, 11..50_007, dot), (2, 60_001..60_005, dot)];
+        assert_eq!(delta.tombstoned_spans, spans);
+        assert!(crate::codec::to_bytes(&delta).len() < 64);
+
+        t2.apply_delta(&delta);
+        assert_eq!(t2.len(), 10_004);
+        assert!(!t2.to_string().contains("kept"));
+        assert_eq!(t2.to_string(), t1.to_string());
+    }
+
+    #[test]
+    fn update_from_keeps_unchanged_characters() {
+        let mut alice = TextCrdt::new(1);
+        alice.insert_str(0, "hello world").unwrap();
+        let mut bob = alice.fork(2);
+        let cursor = alice.anchor_at(6, Gravity::Right).unwrap();
+
+        alice.update_from("hello brave world");
+        assert_eq!(alice.to_string(), "hello brave world");
+        assert_eq!(alice.resolve(cursor), 12);
+        assert_eq!(alice.version_vector().get(1), 11 + 6);
+
+        bob.insert_str(11, "!").unwrap();
+        bob.remove(0).unwrap();
+        alice.merge(&bob);
+        assert_eq!(alice.to_string(), "ello brave world!");
+
+        alice.update_from("yellow");
+        assert_eq!(alice.to_string(), "yellow");
+        alice.update_from("");
+        assert!(alice.is_empty());
+    }
+
+    #[test]
+    fn utf8_and_utf16_offsets() {
+        let mut t = TextCrdt::new(1);
+        t.insert_str(0, "a\u{e9}\u{1f600}b").unwrap(); // 1 + 2 + 4 + 1 bytes
+        assert_eq!((t.len(), t.len_utf8(), t.len_utf16()), (4, 8, 5));
+        assert_eq!(t.index_to_utf8(3), Ok(7));
+        assert_eq!(t.index_to_utf16(3), Ok(4));
+        assert_eq!(t.utf16_to_index(4), Ok(3));
+        assert_eq!(t.utf8_to_index(8), Ok(4));
+        assert_eq!(t.utf16_to_index(3), Err(TextError::NotCharBoundary { offset: 3 }));
+        assert_eq!(t.utf8_to_index(2), Err(TextError::NotCharBoundary { offset: 2 }));
+        assert_eq!(
+            t.utf16_to_index(6),
+            Err(TextError::IndexOutOfBounds { index: 6, len: 5 })
+        );
+        assert_eq!(
+            t.index_to_utf8(5),
+            Err(TextError::IndexOutOfBounds { index: 5, len: 4 })
+        );
+
+        t.insert_str_utf16(4, "!").unwrap();
+        assert_eq!(t.to_string(), "a\u{e9}\u{1f600}!b");
+        t.remove_range_utf8(1, 6).unwrap();
+        assert_eq!(t.to_string(), "a!b");
+        assert_eq!((t.len_utf8(), t.len_utf16()), (3, 3));
+        assert_eq!(
+            t.remove_range_utf16(2, 2),
+            Err(TextError::RangeOutOfBounds { start: 2, end: 4, len: 3 })
+        );
+    }
+
+    #[test]
+    fn overflowing_ranges_are_out_of_bounds() {
+        let mut t = TextCrdt::new(1);
+        t.insert_str(0, "abc").unwrap();
+        let err = TextError::RangeOutOfBounds {
+            start: 1,
+            end: usize::MAX,
+            len: 3,
+        };
+        assert_eq!(t.remove_range(1, usize::MAX), Err(err.clone()));
+        assert_eq!(t.remove_range_utf8(1, usize::MAX), Err(err.clone()));
+        assert_eq!(t.remove_range_utf16(1, usize::MAX), Err(err));
+        assert_eq!(t.to_string(), "abc");
+    }
+
+    #[test]
+    fn utf16_offsets_across_chunks_and_tombstones() {
+        let mut t = TextCrdt::new(1);
+        for i in 0..2000 {
+            t.insert(i, if i % 3 == 0 { '\u{1f600}' } else { 'x' }).unwrap();
+        }
+        for i in (0..2000).step_by(7).rev() {
+            t.remove(i).unwrap();
+        }
+        let text = t.to_string();
+        let mut utf16 = 0;
+        for (i, ch) in text.chars().enumerate() {
+            assert_eq!(t.index_to_utf16(i), Ok(utf16));
+            assert_eq!(t.utf16_to_index(utf16), Ok(i));
+            utf16 += ch.len_utf16();
+        }
+        assert_eq!(t.len_utf16(), text.encode_utf16().count());
+        assert_eq!(t.utf8_to_index(text.len()), Ok(t.len()));
+    }
+
+    #[test]
+    fn line_and_column_lookups() {
+        let mut t = TextCrdt::new(1);
+        assert_eq!(t.line_count(), 1);
+        assert_eq!(t.index_to_line_col(0), Ok((0, 0)));
+        t.insert_str(0, "fn main() {\n    body\n}\n").unwrap();
+        assert_eq!(t.line_count(), 4);
+        assert_eq!(t.index_to_line_col(16), Ok((1, 4)));
+        assert_eq!(t.index_to_line_col(12), Ok((1, 0)));
+        assert_eq!(t.index_to_line_col(11), Ok((0, 11)));
+        assert_eq!(t.index_to_line_col(t.len()), Ok((3, 0)));
+        assert_eq!(t.line_col_to_index(1, 4), Ok(16));
+        assert_eq!(t.line_col_to_index(3, 0), Ok(t.len()));
+        assert_eq!(t.line_range(1), Ok(12..20));
+        assert_eq!(t.line(1).as_deref(), Ok("    body"));
+        assert_eq!(
+            t.line_col_to_index(2, 2),
+            Err(TextError::IndexOutOfBounds { index: 2, len: 1 })
+        );
+        assert_eq!(t.line(4), Err(TextError::LineOutOfBounds { line: 4, lines: 4 }));
+
+        // Removing a line break joins the lines around it.
+        t.remove(11).unwrap();
+        assert_eq!(t.line_count(), 3);
+        let lines: Vec<String> = t.lines(0..10).collect();
+        assert_eq!(lines, ["fn main() {    body", "}", ""]);
+    }
+
+    #[test]
+    fn lines_across_chunks_and_merges() {
+        let mut a = TextCrdt::new(1);
+        for i in 0..600 {
+            let at = a.len();
+            a.insert_str(at, &format!("line {i}\n")).unwrap();
+        }
+        let mut b = a.fork(2);
+        b.insert_str(0, "header\n").unwrap();
+        a.remove_range(0, 7).unwrap(); // "line 0\n"
+        a.merge(&b);
+        assert_eq!(a.line_count(), 601);
+        assert_eq!(a.line(0).as_deref(), Ok("header"));
+        assert_eq!(a.line(300).as_deref(), Ok("line 300"));
+        let index = a.line_col_to_index(300, 5).unwrap();
+        assert_eq!(a.index_to_line_col(index), Ok((300, 5)));
+        assert_eq!(a.lines(598..700).collect::<Vec<_>>(), ["line 598", "line 599", ""]);
+    }
+
+    #[test]
+    fn line_counts_follow_splits_of_long_runs() {
+        let mut t = TextCrdt::new(1);
+        t.insert_str(0, &"abc\n".repeat(500)).unwrap();
+        t.remove_range(401, 4).unwrap(); // "bc\na"
+        t.insert_str(700, "x\ny").unwrap();
+        t.remove(700).unwrap();
+        let text = t.to_string();
+        assert_eq!(t.line_count(), text.lines().count() + 1);
+        for (line, expected) in text.split('\n').enumerate() {
+            assert_eq!(t.line(line).as_deref(), Ok(expected));
+        }
+        let index = text.find("\ny").unwrap() + 1;
+        assert_eq!(t.index_to_line_col(index), Ok((text[..index].matches('\n').count(), 0)));
+    }
 }
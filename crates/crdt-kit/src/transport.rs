@@ -0,0 +1,477 @@
+//! Pluggable transports for shipping [`VersionedEnvelope`]s between peers.
+//!
+//! A [`Transport`] moves whole envelopes: one `send` on one side yields
+//! exactly one `recv` on the other. How the bytes are framed is up to the
+//! implementation.
+//!
+//! With the `std` feature two implementations are provided:
+//!
+//! - [`MemoryTransport`] — a pair of connected in-process channels, handy
+//!   for tests and for replicas that live on different threads.
+//! - [`TcpTransport`] — a [`TcpStream`](std::net::TcpStream) carrying
+//!   length-prefixed frames:
+//!
+//! ```text
+//! [LEN: u32 big-endian][ENVELOPE: LEN bytes]
+//! ```
+//!
+//! With the `websocket` feature, [`websocket::WebSocketTransport`] carries
+//! each envelope as one binary WebSocket message, and
+//! [`websocket::WebSocketHub`] fans deltas out to connected clients.
+//!
+//! [`sync_over`] drives a [`SyncSession`] over any transport, using a
+//! [`SyncCodec`] to turn protocol messages into envelope payloads.
+//!
+//! # Example
+//!
+//! ```
+//! use crdt_kit::transport::{MemoryTransport, Transport};
+//! use crdt_kit::version::{CrdtType, VersionedEnvelope};
+//!
+//! let (mut a, mut b) = MemoryTransport::pair();
+//! a.send(&VersionedEnvelope::new(1, CrdtType::GCounter, vec![42])).unwrap();
+//!
+//! let received = b.recv().unwrap();
+//! assert_eq!(received.crdt_type, CrdtType::GCounter);
+//! assert_eq!(received.payload, vec![42]);
+//! ```
+
+use core::fmt;
+
+use crate::sync::{SyncCodec, SyncError, SyncMessage, SyncSession};
+use crate::version::{EnvelopeError, VersionError, Versioned, VersionedEnvelope};
+use crate::DeltaSince;
+
+/// Largest frame a transport will accept (16 MiB).
+pub const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+/// Error raised while sending or receiving envelopes.
+#[derive(Debug)]
+pub enum TransportError {
+    /// The peer closed the connection.
+    Closed,
+    /// A frame exceeded [`MAX_FRAME_SIZE`].
+    FrameTooLarge {
+        /// Size of the offending frame.
+        len: usize,
+        /// Maximum allowed size.
+        max: usize,
+    },
+    /// A received frame was not a valid envelope.
+    Envelope(EnvelopeError),
+    /// A payload could not be encoded or decoded.
+    Codec(VersionError),
+    /// The sync protocol rejected a message.
+    Sync(SyncError),
+    /// [`recv_delta`] received a message other than a delta.
+    ExpectedDelta,
+    /// An I/O error from the underlying stream.
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    /// A WebSocket protocol error.
+    #[cfg(feature = "websocket")]
+    WebSocket(alloc::boxed::Box<tungstenite::Error>),
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Closed => write!(f, "transport closed by peer"),
+            Self::FrameTooLarge { len, max } => {
+                write!(f, "frame of {len} bytes exceeds maximum of {max}")
+            }
+            Self::Envelope(e) => write!(f, "invalid envelope: {e}"),
+            Self::Codec(e) => write!(f, "codec error: {e}"),
+            Self::Sync(e) => write!(f, "sync error: {e}"),
+            Self::ExpectedDelta => write!(f, "expected a delta message"),
+            #[cfg(feature = "std")]
+            Self::Io(e) => write!(f, "I/O error: {e}"),
+            #[cfg(feature = "websocket")]
+            Self::WebSocket(e) => write!(f, "WebSocket error: {e}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TransportError {}
+
+impl From<EnvelopeError> for TransportError {
+    fn from(e: EnvelopeError) -> Self {
+        Self::Envelope(e)
+    }
+}
+
+impl From<VersionError> for TransportError {
+    fn from(e: VersionError) -> Self {
+        Self::Codec(e)
+    }
+}
+
+impl From<SyncError> for TransportError {
+    fn from(e: SyncError) -> Self {
+        Self::Sync(e)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for TransportError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// A bidirectional channel that carries whole [`VersionedEnvelope`]s.
+pub trait Transport {
+    /// Send one envelope to the peer.
+    fn send(&mut self, envelope: &VersionedEnvelope) -> Result<(), TransportError>;
+
+    /// Block until the next envelope from the peer arrives.
+    ///
+    /// Returns [`TransportError::Closed`] once the peer has gone away.
+    fn recv(&mut self) -> Result<VersionedEnvelope, TransportError>;
+}
+
+impl<T: Transport + ?Sized> Transport for &mut T {
+    fn send(&mut self, envelope: &VersionedEnvelope) -> Result<(), TransportError> {
+        (**self).send(envelope)
+    }
+
+    fn recv(&mut self) -> Result<VersionedEnvelope, TransportError> {
+        (**self).recv()
+    }
+}
+
+/// Run a full [`SyncSession`] for `replica` over `transport`.
+///
+/// Both peers call this concurrently; it returns once the exchange is
+/// complete and `replica` holds everything the peer had. Every message is
+/// wrapped in an envelope tagged with `C`'s type and schema version, and
+/// envelopes for a different CRDT type are rejected.
+///
+/// Returns the number of envelopes sent.
+pub fn sync_over<C, T, K>(
+    transport: &mut T,
+    codec: &K,
+    replica: &mut C,
+) -> Result<usize, TransportError>
+where
+    C: DeltaSince + Versioned,
+    T: Transport + ?Sized,
+    K: SyncCodec<C> + ?Sized,
+{
+    let mut session = SyncSession::new();
+    session.start(replica);
+
+    let mut sent = 0;
+    loop {
+        while let Some(msg) = session.poll_outgoing() {
+            transport.send(&encode_message(codec, &msg)?)?;
+            sent += 1;
+        }
+        if session.is_done() {
+            return Ok(sent);
+        }
+
+        let msg = decode_message(codec, &transport.recv()?)?;
+        session.handle(replica, msg)?;
+    }
+}
+
+/// Push a single delta to the peer outside of a full session.
+///
+/// Used for live updates once [`sync_over`] has brought both sides up to
+/// date; the peer reads it with [`recv_delta`].
+pub fn send_delta<C, T, K>(
+    transport: &mut T,
+    codec: &K,
+    delta: C::Delta,
+) -> Result<(), TransportError>
+where
+    C: DeltaSince + Versioned,
+    T: Transport + ?Sized,
+    K: SyncCodec<C> + ?Sized,
+{
+    transport.send(&encode_message(codec, &SyncMessage::Delta(delta))?)
+}
+
+/// Block until the peer pushes a delta with [`send_delta`].
+///
+/// Any other message fails with [`TransportError::ExpectedDelta`].
+pub fn recv_delta<C, T, K>(transport: &mut T, codec: &K) -> Result<C::Delta, TransportError>
+where
+    C: DeltaSince + Versioned,
+    T: Transport + ?Sized,
+    K: SyncCodec<C> + ?Sized,
+{
+    match decode_message(codec, &transport.recv()?)? {
+        SyncMessage::Delta(delta) => Ok(delta),
+        _ => Err(TransportError::ExpectedDelta),
+    }
+}
+
+/// Wrap a protocol message in an envelope tagged with `C`'s type and version.
+pub(crate) fn encode_message<C, K>(
+    codec: &K,
+    msg: &SyncMessage<C>,
+) -> Result<VersionedEnvelope, TransportError>
+where
+    C: DeltaSince + Versioned,
+    K: SyncCodec<C> + ?Sized,
+{
+    Ok(VersionedEnvelope::new(
+        C::CURRENT_VERSION,
+        C::CRDT_TYPE,
+        codec.encode(msg)?,
+    ))
+}
+
+/// Check an envelope's type and version, then decode its message.
+pub(crate) fn decode_message<C, K>(
+    codec: &K,
+    envelope: &VersionedEnvelope,
+) -> Result<SyncMessage<C>, TransportError>
+where
+    C: DeltaSince + Versioned,
+    K: SyncCodec<C> + ?Sized,
+{
+    if envelope.crdt_type != C::CRDT_TYPE {
+        return Err(SyncError::CrdtTypeMismatch {
+            expected: C::CRDT_TYPE,
+            found: envelope.crdt_type,
+        }
+        .into());
+    }
+    if envelope.version != C::CURRENT_VERSION {
+        return Err(SyncError::VersionMismatch {
+            expected: C::CURRENT_VERSION,
+            found: envelope.version,
+        }
+        .into());
+    }
+    Ok(codec.decode(&envelope.payload)?)
+}
+
+#[cfg(feature = "std")]
+pub use self::std_impls::{MemoryTransport, TcpTransport};
+
+#[cfg(feature = "websocket")]
+pub mod websocket;
+
+#[cfg(feature = "std")]
+mod std_impls {
+    use std::io::{self, Read, Write};
+    use std::net::{TcpStream, ToSocketAddrs};
+    use std::sync::mpsc::{self, Receiver, Sender};
+    use std::vec::Vec;
+
+    use super::{Transport, TransportError, MAX_FRAME_SIZE};
+    use crate::version::VersionedEnvelope;
+
+    /// An in-process transport backed by a pair of channels.
+    ///
+    /// Both ends are `Send`, so each can be moved to its own thread.
+    #[derive(Debug)]
+    pub struct MemoryTransport {
+        tx: Sender<Vec<u8>>,
+        rx: Receiver<Vec<u8>>,
+    }
+
+    impl MemoryTransport {
+        /// Create two connected endpoints.
+        #[must_use]
+        pub fn pair() -> (Self, Self) {
+            let (tx_a, rx_b) = mpsc::channel();
+            let (tx_b, rx_a) = mpsc::channel();
+            (Self { tx: tx_a, rx: rx_a }, Self { tx: tx_b, rx: rx_b })
+        }
+
+        /// Receive an envelope if one is already waiting.
+        pub fn try_recv(&mut self) -> Result<Option<VersionedEnvelope>, TransportError> {
+            match self.rx.try_recv() {
+                Ok(bytes) => Ok(Some(VersionedEnvelope::from_bytes(&bytes)?)),
+                Err(mpsc::TryRecvError::Empty) => Ok(None),
+                Err(mpsc::TryRecvError::Disconnected) => Err(TransportError::Closed),
+            }
+        }
+    }
+
+    impl Transport for MemoryTransport {
+        fn send(&mut self, envelope: &VersionedEnvelope) -> Result<(), TransportError> {
+            self.tx
+                .send(envelope.to_bytes())
+                .map_err(|_| TransportError::Closed)
+        }
+
+        fn recv(&mut self) -> Result<VersionedEnvelope, TransportError> {
+            let bytes = self.rx.recv().map_err(|_| TransportError::Closed)?;
+            Ok(VersionedEnvelope::from_bytes(&bytes)?)
+        }
+    }
+
+    /// A TCP transport using `u32` big-endian length-prefixed frames.
+    #[derive(Debug)]
+    pub struct TcpTransport {
+        stream: TcpStream,
+    }
+
+    impl TcpTransport {
+        /// Connect to a listening peer.
+        pub fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+            Ok(Self::from_stream(TcpStream::connect(addr)?))
+        }
+
+        /// Wrap an already connected stream, e.g. one from
+        /// [`TcpListener::accept`](std::net::TcpListener::accept).
+        #[must_use]
+        pub fn from_stream(stream: TcpStream) -> Self {
+            Self { stream }
+        }
+
+        /// Get a reference to the underlying stream.
+        #[must_use]
+        pub fn get_ref(&self) -> &TcpStream {
+            &self.stream
+        }
+
+        /// Unwrap into the underlying stream.
+        #[must_use]
+        pub fn into_inner(self) -> TcpStream {
+            self.stream
+        }
+    }
+
+    impl From<TcpStream> for TcpTransport {
+        fn from(stream: TcpStream) -> Self {
+            Self::from_stream(stream)
+        }
+    }
+
+    impl Transport for TcpTransport {
+        fn send(&mut self, envelope: &VersionedEnvelope) -> Result<(), TransportError> {
+            let body = envelope.to_bytes();
+            if body.len() > MAX_FRAME_SIZE {
+                return Err(TransportError::FrameTooLarge {
+                    len: body.len(),
+                    max: MAX_FRAME_SIZE,
+                });
+            }
+            let mut frame = Vec::with_capacity(4 + body.len());
+            frame.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            frame.extend_from_slice(&body);
+            self.stream.write_all(&frame)?;
+            self.stream.flush()?;
+            Ok(())
+        }
+
+        fn recv(&mut self) -> Result<VersionedEnvelope, TransportError> {
+            let mut len_buf = [0u8; 4];
+            match self.stream.read_exact(&mut len_buf) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    return Err(TransportError::Closed)
+                }
+                Err(e) => return Err(e.into()),
+            }
+            let len = u32::from_be_bytes(len_buf) as usize;
+            if len > MAX_FRAME_SIZE {
+                return Err(TransportError::FrameTooLarge {
+                    len,
+                    max: MAX_FRAME_SIZE,
+                });
+            }
+            let mut body = vec![0u8; len];
+            self.stream.read_exact(&mut body)?;
+            Ok(VersionedEnvelope::from_bytes(&body)?)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::version::CrdtType;
+    use std::io::Write;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    fn loopback() -> (TcpTransport, TcpTransport) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = thread::spawn(move || TcpTransport::connect(addr).unwrap());
+        let (server, _) = listener.accept().unwrap();
+        (TcpTransport::from(server), client.join().unwrap())
+    }
+
+    #[test]
+    fn memory_pair_roundtrip() {
+        let (mut a, mut b) = MemoryTransport::pair();
+        let env = VersionedEnvelope::new(1, CrdtType::ORSet, vec![1, 2, 3]);
+        a.send(&env).unwrap();
+        assert_eq!(b.recv().unwrap(), env);
+
+        b.send(&env).unwrap();
+        assert_eq!(a.try_recv().unwrap(), Some(env));
+        assert_eq!(a.try_recv().unwrap(), None);
+    }
+
+    #[test]
+    fn memory_detects_closed_peer() {
+        let (mut a, b) = MemoryTransport::pair();
+        drop(b);
+        assert!(matches!(a.recv(), Err(TransportError::Closed)));
+        let env = VersionedEnvelope::new(1, CrdtType::GCounter, Vec::new());
+        assert!(matches!(a.send(&env), Err(TransportError::Closed)));
+    }
+
+    #[test]
+    fn tcp_frames_preserve_boundaries() {
+        let (mut server, mut client) = loopback();
+        let envs: Vec<_> = (0..5u8)
+            .map(|i| VersionedEnvelope::new(1, CrdtType::Rga, vec![i; i as usize * 100]))
+            .collect();
+        for env in &envs {
+            client.send(env).unwrap();
+        }
+        for env in &envs {
+            assert_eq!(&server.recv().unwrap(), env);
+        }
+    }
+
+    #[test]
+    fn tcp_eof_is_closed() {
+        let (mut server, client) = loopback();
+        drop(client);
+        assert!(matches!(server.recv(), Err(TransportError::Closed)));
+    }
+
+    #[test]
+    fn tcp_rejects_oversized_frame() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut raw = TcpStream::connect(addr).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        let mut server = TcpTransport::from(server);
+
+        raw.write_all(&u32::MAX.to_be_bytes()).unwrap();
+        assert!(matches!(
+            server.recv(),
+            Err(TransportError::FrameTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn tcp_rejects_invalid_envelope() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut raw = TcpStream::connect(addr).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        let mut server = TcpTransport::from(server);
+
+        raw.write_all(&3u32.to_be_bytes()).unwrap();
+        raw.write_all(&[0x00, 1, 1]).unwrap();
+        assert!(matches!(
+            server.recv(),
+            Err(TransportError::Envelope(EnvelopeError::InvalidMagic(0)))
+        ));
+    }
+}
@@ -0,0 +1,424 @@
+//! WebSocket transport (requires the `websocket` feature).
+//!
+//! Each [`VersionedEnvelope`] travels as one binary WebSocket message, so a
+//! browser client only needs `ws.binaryType = "arraybuffer"` and the
+//! envelope bytes to talk to a Rust server.
+//!
+//! [`WebSocketHub`] is the server side: it keeps the authoritative replica,
+//! brings every new client up to date with a full sync, and relays each
+//! delta a client pushes to all the others.
+
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+use std::vec::Vec;
+
+use tungstenite::error::UrlError;
+use tungstenite::handshake::{HandshakeError, HandshakeRole};
+use tungstenite::protocol::WebSocket;
+use tungstenite::Message;
+
+use super::{decode_message, encode_message, sync_over, Transport, TransportError, MAX_FRAME_SIZE};
+use crate::sync::{SyncCodec, SyncMessage};
+use crate::version::{Versioned, VersionedEnvelope};
+use crate::DeltaSince;
+
+impl From<tungstenite::Error> for TransportError {
+    fn from(e: tungstenite::Error) -> Self {
+        match e {
+            tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed => {
+                Self::Closed
+            }
+            tungstenite::Error::Io(e) => Self::Io(e),
+            e => Self::WebSocket(e.into()),
+        }
+    }
+}
+
+impl<R: HandshakeRole> From<HandshakeError<R>> for TransportError {
+    fn from(e: HandshakeError<R>) -> Self {
+        match e {
+            HandshakeError::Failure(e) => e.into(),
+            HandshakeError::Interrupted(_) => Self::Io(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+}
+
+/// A transport carrying envelopes as binary WebSocket messages.
+///
+/// Text messages are ignored; pings are answered automatically.
+#[derive(Debug)]
+pub struct WebSocketTransport<S> {
+    socket: WebSocket<S>,
+}
+
+impl WebSocketTransport<TcpStream> {
+    /// Connect to a `ws://` URL and perform the client handshake.
+    ///
+    /// `wss://` URLs are rejected: this transport does not speak TLS.
+    pub fn connect(url: &str) -> Result<Self, TransportError> {
+        use tungstenite::client::IntoClientRequest;
+
+        let request = url.into_client_request()?;
+        let uri = request.uri();
+        match uri.scheme_str() {
+            Some("ws") => {}
+            Some("wss") => return Err(url_error(UrlError::TlsFeatureNotEnabled)),
+            _ => return Err(url_error(UrlError::UnsupportedUrlScheme)),
+        }
+        let host = uri.host().ok_or_else(|| url_error(UrlError::NoHostName))?;
+        let port = uri.port_u16().unwrap_or(80);
+        let stream = TcpStream::connect((host, port))?;
+        let (socket, _response) = tungstenite::client(request, stream)?;
+        Ok(Self { socket })
+    }
+
+    /// Perform the server handshake on an accepted connection.
+    pub fn accept(stream: TcpStream) -> Result<Self, TransportError> {
+        Ok(Self {
+            socket: tungstenite::accept(stream)?,
+        })
+    }
+
+    /// Wait at most `timeout` for the next envelope.
+    ///
+    /// Returns `Ok(None)` if nothing arrived in time. A zero `timeout` only
+    /// reads what has already arrived, without blocking. A partially
+    /// received message is kept and completed by the next call.
+    pub fn recv_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<VersionedEnvelope>, TransportError> {
+        // The OS rejects a zero read timeout, so poll in non-blocking mode.
+        let stream = self.socket.get_ref();
+        if timeout.is_zero() {
+            stream.set_nonblocking(true)?;
+        } else {
+            stream.set_read_timeout(Some(timeout))?;
+        }
+        let result = self.recv();
+        let stream = self.socket.get_ref();
+        if timeout.is_zero() {
+            stream.set_nonblocking(false)?;
+        } else {
+            stream.set_read_timeout(None)?;
+        }
+        match result {
+            Ok(envelope) => Ok(Some(envelope)),
+            Err(TransportError::Io(e)) if is_transient(&e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn url_error(e: UrlError) -> TransportError {
+    tungstenite::Error::Url(e).into()
+}
+
+/// Whether a read failed only because no data was ready yet.
+fn is_transient(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted
+    )
+}
+
+impl<S: Read + Write> WebSocketTransport<S> {
+    /// Wrap a WebSocket that has already completed its handshake.
+    #[must_use]
+    pub fn from_socket(socket: WebSocket<S>) -> Self {
+        Self { socket }
+    }
+
+    /// Get a reference to the underlying WebSocket.
+    #[must_use]
+    pub fn get_ref(&self) -> &WebSocket<S> {
+        &self.socket
+    }
+
+    /// Start the closing handshake.
+    pub fn close(&mut self) -> Result<(), TransportError> {
+        self.socket.close(None)?;
+        Ok(())
+    }
+}
+
+impl<S: Read + Write> Transport for WebSocketTransport<S> {
+    fn send(&mut self, envelope: &VersionedEnvelope) -> Result<(), TransportError> {
+        let bytes = envelope.to_bytes();
+        if bytes.len() > MAX_FRAME_SIZE {
+            return Err(TransportError::FrameTooLarge {
+                len: bytes.len(),
+                max: MAX_FRAME_SIZE,
+            });
+        }
+        self.socket.send(Message::binary(bytes))?;
+        Ok(())
+    }
+
+    fn recv(&mut self) -> Result<VersionedEnvelope, TransportError> {
+        loop {
+            match self.socket.read()? {
+                Message::Binary(bytes) => {
+                    if bytes.len() > MAX_FRAME_SIZE {
+                        return Err(TransportError::FrameTooLarge {
+                            len: bytes.len(),
+                            max: MAX_FRAME_SIZE,
+                        });
+                    }
+                    return Ok(VersionedEnvelope::from_bytes(&bytes)?);
+                }
+                Message::Close(_) => return Err(TransportError::Closed),
+                Message::Text(_) | Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => {}
+            }
+        }
+    }
+}
+
+/// Identifies a client connected to a [`WebSocketHub`].
+pub type ClientId = u64;
+
+/// Server-side helper that fans deltas out to connected WebSocket clients.
+///
+/// The hub owns the server's replica. [`accept`](Self::accept) runs a full
+/// [`sync_over`] with each new client; afterwards clients push live updates
+/// with [`send_delta`](super::send_delta). [`poll`](Self::poll) applies
+/// every incoming delta to the hub's replica and forwards it to all other
+/// clients, and [`publish`](Self::publish) sends server-side changes to
+/// everyone.
+pub struct WebSocketHub<C> {
+    replica: C,
+    clients: Vec<(ClientId, WebSocketTransport<TcpStream>)>,
+    next_id: ClientId,
+}
+
+impl<C: DeltaSince + Versioned> WebSocketHub<C> {
+    /// Create a hub serving `replica`.
+    pub fn new(replica: C) -> Self {
+        Self {
+            replica,
+            clients: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Complete the WebSocket handshake with a new connection, sync it with
+    /// the hub's replica, and start relaying to it.
+    pub fn accept<K: SyncCodec<C> + ?Sized>(
+        &mut self,
+        stream: TcpStream,
+        codec: &K,
+    ) -> Result<ClientId, TransportError> {
+        let mut transport = WebSocketTransport::accept(stream)?;
+        sync_over(&mut transport, codec, &mut self.replica)?;
+        let id = self.next_id;
+        self.next_id += 1;
+        self.clients.push((id, transport));
+        Ok(id)
+    }
+
+    /// Check each client once for a pushed delta, waiting at most `timeout`
+    /// per client, and relay whatever arrived.
+    ///
+    /// Clients are read one after another, so a call can take up to
+    /// `timeout` for every idle client. Pass [`Duration::ZERO`] to only pick
+    /// up what has already arrived.
+    ///
+    /// A client that disconnects, breaks the WebSocket protocol or sends
+    /// anything other than a delta is dropped; after any other I/O error it
+    /// stays connected and is read again on the next call. Returns the number
+    /// of deltas relayed.
+    pub fn poll<K: SyncCodec<C> + ?Sized>(
+        &mut self,
+        codec: &K,
+        timeout: Duration,
+    ) -> Result<usize, TransportError> {
+        let mut relayed = 0;
+        let mut i = 0;
+        while i < self.clients.len() {
+            let (origin, transport) = &mut self.clients[i];
+            let origin = *origin;
+            let delta = match transport.recv_timeout(timeout) {
+                Ok(None) => {
+                    i += 1;
+                    continue;
+                }
+                Ok(Some(envelope)) => match decode_message(codec, &envelope) {
+                    Ok(SyncMessage::Delta(delta)) => Some(delta),
+                    _ => None,
+                },
+                Err(TransportError::Io(e)) => match e.kind() {
+                    io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::BrokenPipe
+                    | io::ErrorKind::UnexpectedEof => None,
+                    _ => {
+                        i += 1;
+                        continue;
+                    }
+                },
+                Err(_) => None,
+            };
+            let Some(delta) = delta else {
+                self.clients.remove(i);
+                continue;
+            };
+            self.replica.apply_delta(&delta);
+            self.broadcast(codec, delta, Some(origin))?;
+            relayed += 1;
+            i += 1;
+        }
+        Ok(relayed)
+    }
+
+    /// Apply a server-side delta and send it to every client.
+    pub fn publish<K: SyncCodec<C> + ?Sized>(
+        &mut self,
+        codec: &K,
+        delta: C::Delta,
+    ) -> Result<(), TransportError> {
+        self.replica.apply_delta(&delta);
+        self.broadcast(codec, delta, None)
+    }
+
+    /// The hub's replica.
+    #[must_use]
+    pub fn replica(&self) -> &C {
+        &self.replica
+    }
+
+    /// Number of connected clients.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Check whether no clients are connected.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    fn broadcast<K: SyncCodec<C> + ?Sized>(
+        &mut self,
+        codec: &K,
+        delta: C::Delta,
+        except: Option<ClientId>,
+    ) -> Result<(), TransportError> {
+        let envelope = encode_message(codec, &SyncMessage::<C>::Delta(delta))?;
+        self.clients
+            .retain_mut(|(id, transport)| Some(*id) == except || transport.send(&envelope).is_ok());
+        Ok(())
+    }
+}
+
+impl<C> core::fmt::Debug for WebSocketHub<C> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("WebSocketHub")
+            .field("clients", &self.clients.len())
+            .field("next_id", &self.next_id)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::CrdtType;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn envelopes_roundtrip_over_localhost() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let client = thread::spawn(move || {
+            let mut t = WebSocketTransport::connect(&url).unwrap();
+            let env = t.recv().unwrap();
+            t.send(&env).unwrap();
+            t.close().unwrap();
+        });
+
+        let (stream, _) = listener.accept().unwrap();
+        let mut server = WebSocketTransport::accept(stream).unwrap();
+        let env = VersionedEnvelope::new(1, CrdtType::TextCrdt, b"hello".to_vec());
+        server.send(&env).unwrap();
+        assert_eq!(server.recv().unwrap(), env);
+        assert!(matches!(server.recv(), Err(TransportError::Closed)));
+        client.join().unwrap();
+    }
+
+    #[test]
+    fn recv_timeout_returns_none_when_idle() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let client = thread::spawn(move || WebSocketTransport::connect(&url).unwrap());
+
+        let (stream, _) = listener.accept().unwrap();
+        let mut server = WebSocketTransport::accept(stream).unwrap();
+        let mut client = client.join().unwrap();
+        assert!(server
+            .recv_timeout(Duration::from_millis(10))
+            .unwrap()
+            .is_none());
+
+        let env = VersionedEnvelope::new(1, CrdtType::GCounter, vec![7]);
+        client.send(&env).unwrap();
+        assert_eq!(
+            server.recv_timeout(Duration::from_secs(5)).unwrap(),
+            Some(env)
+        );
+    }
+
+    #[test]
+    fn zero_timeout_polls_without_blocking() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let client = thread::spawn(move || WebSocketTransport::connect(&url).unwrap());
+
+        let (stream, _) = listener.accept().unwrap();
+        let mut server = WebSocketTransport::accept(stream).unwrap();
+        let mut client = client.join().unwrap();
+        assert!(server.recv_timeout(Duration::ZERO).unwrap().is_none());
+
+        let env = VersionedEnvelope::new(1, CrdtType::GCounter, vec![7]);
+        client.send(&env).unwrap();
+        let received = (0..500).find_map(|_| {
+            thread::sleep(Duration::from_millis(10));
+            server.recv_timeout(Duration::ZERO).unwrap()
+        });
+        assert_eq!(received, Some(env));
+    }
+
+    #[test]
+    fn idle_clients_stay_connected_to_the_hub() {
+        use crate::codec::BinaryCodec;
+        use crate::GCounter;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let client = thread::spawn(move || {
+            let mut t = WebSocketTransport::connect(&url).unwrap();
+            let mut replica = GCounter::new(2);
+            sync_over(&mut t, &BinaryCodec, &mut replica).unwrap();
+            t
+        });
+
+        let mut hub = WebSocketHub::new(GCounter::new(1));
+        let (stream, _) = listener.accept().unwrap();
+        hub.accept(stream, &BinaryCodec).unwrap();
+        let _client = client.join().unwrap();
+        assert_eq!(hub.poll(&BinaryCodec, Duration::ZERO).unwrap(), 0);
+        assert_eq!(hub.len(), 1);
+    }
+
+    #[test]
+    fn connect_rejects_other_schemes() {
+        for url in ["wss://127.0.0.1:1", "http://127.0.0.1:1"] {
+            assert!(matches!(
+                WebSocketTransport::connect(url),
+                Err(TransportError::WebSocket(_))
+            ));
+        }
+    }
+}
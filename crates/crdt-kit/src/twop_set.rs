@@ -1,5 +1,6 @@
 use alloc::collections::BTreeSet;
 
+use crate::codec::{CodecError, Decode, Encode, Reader, Writer};
 use crate::{Crdt, DeltaCrdt};
 
 /// A two-phase set (2P-Set).
@@ -150,6 +151,38 @@ impl<T: Ord + Clone> Crdt for TwoPSet<T> {
     }
 }
 
+impl<T: Ord + Clone + Encode> Encode for TwoPSet<T> {
+    fn encode(&self, w: &mut Writer) {
+        self.added.encode(w);
+        self.removed.encode(w);
+    }
+}
+
+impl<T: Ord + Clone + Decode> Decode for TwoPSet<T> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Ok(Self {
+            added: BTreeSet::decode(r)?,
+            removed: BTreeSet::decode(r)?,
+        })
+    }
+}
+
+impl<T: Ord + Clone + Encode> Encode for TwoPSetDelta<T> {
+    fn encode(&self, w: &mut Writer) {
+        self.added.encode(w);
+        self.removed.encode(w);
+    }
+}
+
+impl<T: Ord + Clone + Decode> Decode for TwoPSetDelta<T> {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Ok(Self {
+            added: BTreeSet::decode(r)?,
+            removed: BTreeSet::decode(r)?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
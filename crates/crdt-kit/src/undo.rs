@@ -0,0 +1,450 @@
+//! Undo and redo of local edits on an [`Rga`] or [`TextCrdt`].
+//!
+//! An [`UndoManager`] edits a sequence on the user's behalf and remembers
+//! the ids of what each edit inserted and removed. Undo never rewinds the
+//! replica's state: it applies the inverse edit as new CRDT operations, so
+//! it can be merged like any other edit, and it only ever touches this
+//! replica's own changes, however many remote edits were merged since.
+//!
+//! - Undoing an insert removes whichever of the inserted elements are
+//!   still there.
+//! - Undoing a removal inserts the removed values again where they used to
+//!   be. They get new ids (a tombstone is never revived); the manager
+//!   remembers which elements they replace, so undoing the original insert
+//!   or a redo removes them too.
+//!
+//! Edits made within [`with_capture_timeout`](UndoManager::with_capture_timeout)
+//! of each other, or between [`begin_group`](UndoManager::begin_group) and
+//! [`end_group`](UndoManager::end_group), are undone together.
+//!
+//! # Example
+//!
+//! ```
+//! use crdt_kit::prelude::*;
+//! use crdt_kit::undo::TextUndoManager;
+//!
+//! let mut alice = TextCrdt::new(1);
+//! let mut undo = TextUndoManager::new();
+//! undo.insert(&mut alice, 0, "hello").unwrap();
+//!
+//! let mut bob = alice.fork(2);
+//! undo.insert(&mut alice, 5, " world").unwrap();
+//! bob.insert_str(0, "oh, ").unwrap();
+//! alice.merge(&bob);
+//!
+//! // Only Alice's last edit is undone; Bob's stays.
+//! assert!(undo.undo(&mut alice));
+//! assert_eq!(alice.to_string(), "oh, hello");
+//! assert!(undo.redo(&mut alice));
+//! assert_eq!(alice.to_string(), "oh, hello world");
+//! ```
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::rga::{Rga, RgaError, Run};
+use crate::{NodeId, TextCrdt};
+
+mod sealed {
+    use crate::rga::{Rga, Run};
+
+    pub trait Sealed<T: Clone + Ord, S: Run<T>> {
+        fn sequence(&mut self) -> &mut Rga<T, S>;
+    }
+}
+
+/// A sequence an [`UndoManager`] can edit: an [`Rga<T, S>`](Rga), or a
+/// [`TextCrdt`] (as `Undoable<char, String>`).
+pub trait Undoable<T: Clone + Ord, S: Run<T>>: sealed::Sealed<T, S> {
+    /// Error returned for out-of-range indices.
+    type Error: From<RgaError>;
+}
+
+impl<T: Clone + Ord, S: Run<T>> sealed::Sealed<T, S> for Rga<T, S> {
+    fn sequence(&mut self) -> &mut Rga<T, S> {
+        self
+    }
+}
+
+impl<T: Clone + Ord, S: Run<T>> Undoable<T, S> for Rga<T, S> {
+    type Error = RgaError;
+}
+
+impl sealed::Sealed<char, String> for TextCrdt {
+    fn sequence(&mut self) -> &mut Rga<char, String> {
+        self.rga_mut()
+    }
+}
+
+impl Undoable<char, String> for TextCrdt {
+    type Error = crate::TextError;
+}
+
+/// One recorded edit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Change<S> {
+    /// `len` elements with consecutive ids from `first` were inserted.
+    Inserted { first: (NodeId, u64), len: usize },
+    /// `values` were removed from where `at` (the first of them) is.
+    Removed { at: (NodeId, u64), values: S },
+}
+
+/// Undo and redo stacks of local edits on one sequence.
+///
+/// Make the edits that should be undoable through the manager
+/// ([`insert`](Self::insert), [`remove`](Self::remove)), always on the same
+/// replica; edits made on the sequence directly, and remote ones, are left
+/// alone by [`undo`](Self::undo) and [`redo`](Self::redo).
+#[derive(Debug, Clone)]
+pub struct UndoManager<T: Clone + Ord, S: Run<T> = Vec<T>> {
+    undo: Vec<Vec<Change<S>>>,
+    redo: Vec<Vec<Change<S>>>,
+    /// Removed spans inserted again by an undo, by first id: the first id
+    /// of the replacement and the span's length.
+    restored: BTreeMap<(NodeId, u64), ((NodeId, u64), usize)>,
+    /// Whether the next edit may join the step on top of the undo stack.
+    joinable: bool,
+    group_depth: usize,
+    capture_timeout: u64,
+    time_fn: Option<fn() -> u64>,
+    last_edit: u64,
+    marker: core::marker::PhantomData<fn() -> T>,
+}
+
+/// [`UndoManager`] for a [`TextCrdt`].
+pub type TextUndoManager = UndoManager<char, String>;
+
+impl<T: Clone + Ord, S: Run<T>> Default for UndoManager<T, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone + Ord, S: Run<T>> UndoManager<T, S> {
+    /// Create a manager that makes every edit its own undo step (unless it
+    /// is part of a group).
+    pub fn new() -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            restored: BTreeMap::new(),
+            joinable: false,
+            group_depth: 0,
+            capture_timeout: 0,
+            time_fn: None,
+            last_edit: 0,
+            marker: core::marker::PhantomData,
+        }
+    }
+
+    /// Create a manager that merges an edit into the previous undo step
+    /// when it follows it within `timeout_ms`, as read from `time_fn`
+    /// (milliseconds, like [`HybridClock::with_time_source`]).
+    ///
+    /// [`HybridClock::with_time_source`]: crate::clock::HybridClock::with_time_source
+    pub fn with_capture_timeout(timeout_ms: u64, time_fn: fn() -> u64) -> Self {
+        Self {
+            capture_timeout: timeout_ms,
+            time_fn: Some(time_fn),
+            ..Self::new()
+        }
+    }
+
+    /// Insert `values` at `index` in `doc`, as one undoable edit.
+    pub fn insert<D: Undoable<T, S>>(
+        &mut self,
+        doc: &mut D,
+        index: usize,
+        values: impl Into<S>,
+    ) -> Result<(), D::Error> {
+        let (first, len) = doc.sequence().insert_span(index, values.into())?;
+        if len > 0 {
+            self.record(Vec::from([Change::Inserted { first, len }]));
+        }
+        Ok(())
+    }
+
+    /// Remove `count` elements from `start` on in `doc`, as one undoable
+    /// edit.
+    pub fn remove<D: Undoable<T, S>>(
+        &mut self,
+        doc: &mut D,
+        start: usize,
+        count: usize,
+    ) -> Result<(), D::Error> {
+        let removed = doc.sequence().remove_span(start, count)?;
+        let changes: Vec<_> = removed
+            .into_iter()
+            .map(|(at, values)| Change::Removed { at, values })
+            .collect();
+        if !changes.is_empty() {
+            self.record(changes);
+        }
+        Ok(())
+    }
+
+    /// Start a group: every edit until the matching
+    /// [`end_group`](Self::end_group) is undone in one step. Groups nest.
+    pub fn begin_group(&mut self) {
+        if self.group_depth == 0 {
+            self.joinable = false;
+        }
+        self.group_depth += 1;
+    }
+
+    /// End the group started by the matching
+    /// [`begin_group`](Self::begin_group).
+    pub fn end_group(&mut self) {
+        self.group_depth = self.group_depth.saturating_sub(1);
+        if self.group_depth == 0 {
+            self.joinable = false;
+        }
+    }
+
+    /// Make the next edit start a new undo step, even within the capture
+    /// timeout.
+    pub fn stop_capturing(&mut self) {
+        self.joinable = false;
+    }
+
+    /// Undo the most recent undo step in `doc`. Returns `false` if there
+    /// was nothing to undo.
+    pub fn undo<D: Undoable<T, S>>(&mut self, doc: &mut D) -> bool {
+        let Some(step) = self.undo.pop() else {
+            return false;
+        };
+        let inverse = self.invert(doc.sequence(), step);
+        self.redo.push(inverse);
+        self.joinable = false;
+        true
+    }
+
+    /// Redo the most recently undone step in `doc`. Returns `false` if
+    /// there was nothing to redo.
+    pub fn redo<D: Undoable<T, S>>(&mut self, doc: &mut D) -> bool {
+        let Some(step) = self.redo.pop() else {
+            return false;
+        };
+        let inverse = self.invert(doc.sequence(), step);
+        self.undo.push(inverse);
+        self.joinable = false;
+        true
+    }
+
+    /// Whether there is a step to undo.
+    #[must_use]
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// Whether there is a step to redo.
+    #[must_use]
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Forget all undo and redo steps.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.restored.clear();
+        self.joinable = false;
+    }
+
+    /// Push `changes` as a new undo step or add them to the current one.
+    fn record(&mut self, changes: Vec<Change<S>>) {
+        self.redo.clear();
+        let now = self.time_fn.map_or(0, |time| time());
+        let join = self.joinable
+            && (self.group_depth > 0
+                || (self.time_fn.is_some()
+                    && now.saturating_sub(self.last_edit) <= self.capture_timeout));
+        match self.undo.last_mut() {
+            Some(step) if join => step.extend(changes),
+            _ => self.undo.push(changes),
+        }
+        self.joinable = true;
+        self.last_edit = now;
+    }
+
+    /// Apply the inverse of each change in `step`, latest first, returning
+    /// the step that reverts this again.
+    fn invert(&mut self, rga: &mut Rga<T, S>, step: Vec<Change<S>>) -> Vec<Change<S>> {
+        let mut inverse = Vec::new();
+        for change in step.into_iter().rev() {
+            match change {
+                Change::Inserted { first, len } => {
+                    let mut removed = Vec::new();
+                    self.remove_with_replacements(rga, first, len, &mut removed);
+                    inverse.extend(
+                        removed
+                            .into_iter()
+                            .rev()
+                            .map(|(at, values)| Change::Removed { at, values }),
+                    );
+                }
+                Change::Removed { at, values } => {
+                    let len = values.count();
+                    let first = rga.insert_at_id(at, values);
+                    self.restored.insert(at, (first, len));
+                    inverse.push(Change::Inserted { first, len });
+                }
+            }
+        }
+        inverse.reverse();
+        inverse
+    }
+
+    /// Remove what is left of the `len` elements from `first` on, and of
+    /// whatever an undo inserted to replace them.
+    fn remove_with_replacements(
+        &self,
+        rga: &mut Rga<T, S>,
+        (actor, start): (NodeId, u64),
+        len: usize,
+        removed: &mut Vec<((NodeId, u64), S)>,
+    ) {
+        removed.extend(rga.remove_ids((actor, start), len));
+        let end = start + len as u64;
+        let spans = self
+            .restored
+            .range(..(actor, start))
+            .next_back()
+            .into_iter()
+            .chain(self.restored.range((actor, start)..(actor, end)))
+            .filter(|&(&(a, from), &(_, n))| a == actor && from + n as u64 > start)
+            .map(|(&(_, from), &(to, n))| (from, to, n));
+        let spans: Vec<_> = spans.collect();
+        for (from, (to_actor, to), n) in spans {
+            // The part of the replaced span that lies within this one.
+            let skip = start.saturating_sub(from);
+            let take = (from + n as u64).min(end) - from.max(start);
+            self.remove_with_replacements(rga, (to_actor, to + skip), take as usize, removed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Crdt;
+
+    #[test]
+    fn undo_and_redo_inserts_and_removals() {
+        let mut text = TextCrdt::new(1);
+        let mut undo = TextUndoManager::new();
+        undo.insert(&mut text, 0, "hello world").unwrap();
+        undo.remove(&mut text, 5, 6).unwrap();
+        assert_eq!(text.to_string(), "hello");
+
+        assert!(undo.undo(&mut text));
+        assert_eq!(text.to_string(), "hello world");
+        assert!(undo.undo(&mut text));
+        assert_eq!(text.to_string(), "");
+        assert!(!undo.undo(&mut text));
+
+        assert!(undo.redo(&mut text));
+        assert!(undo.redo(&mut text));
+        assert_eq!(text.to_string(), "hello");
+        assert!(!undo.can_redo());
+        assert!(undo.undo(&mut text));
+        assert_eq!(text.to_string(), "hello world");
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut rga = Rga::new(1);
+        let mut undo = UndoManager::new();
+        undo.insert(&mut rga, 0, [1, 2]).unwrap();
+        undo.undo(&mut rga);
+        assert!(undo.can_redo());
+        undo.insert(&mut rga, 0, [3]).unwrap();
+        assert!(!undo.can_redo());
+        assert_eq!(
+            undo.remove(&mut rga, 0, 2),
+            Err(RgaError::IndexOutOfBounds { index: 2, len: 1 })
+        );
+    }
+
+    #[test]
+    fn undo_leaves_remote_edits_alone() {
+        let mut alice = TextCrdt::new(1);
+        let mut undo = TextUndoManager::new();
+        undo.insert(&mut alice, 0, "abc").unwrap();
+        let mut bob = alice.fork(2);
+        undo.remove(&mut alice, 1, 1).unwrap(); // "ac"
+        bob.insert_str(2, "XY").unwrap(); // "abXYc"
+        bob.remove(0).unwrap(); // "bXYc"
+        alice.merge(&bob);
+        assert_eq!(alice.to_string(), "XYc");
+
+        // The 'b' comes back where it was, before Bob's insert.
+        assert!(undo.undo(&mut alice));
+        assert_eq!(alice.to_string(), "bXYc");
+        // Undoing the insert removes what is left of "abc".
+        assert!(undo.undo(&mut alice));
+        assert_eq!(alice.to_string(), "XY");
+
+        bob.merge(&alice);
+        assert_eq!(bob.to_string(), "XY");
+        assert!(undo.redo(&mut alice));
+        assert!(undo.redo(&mut alice));
+        assert_eq!(alice.to_string(), "XYc");
+    }
+
+    #[test]
+    fn redo_after_concurrent_edits() {
+        let mut alice = TextCrdt::new(1);
+        alice.insert_str(0, "ac").unwrap();
+        let mut undo = TextUndoManager::new();
+        undo.insert(&mut alice, 1, "b").unwrap();
+        assert!(undo.undo(&mut alice));
+
+        let mut bob = alice.fork(2);
+        bob.insert_str(0, ">").unwrap();
+        bob.insert_str(3, "<").unwrap();
+        alice.merge(&bob);
+        assert!(undo.redo(&mut alice));
+        assert_eq!(alice.to_string(), ">abc<");
+
+        bob.merge(&alice);
+        assert!(undo.undo(&mut alice));
+        assert_eq!(alice.to_string(), ">ac<");
+        alice.merge(&bob);
+        assert_eq!(alice.to_string(), ">ac<");
+    }
+
+    #[test]
+    fn groups_and_capture_timeout() {
+        let mut rga = Rga::new(1);
+        let mut undo = UndoManager::new();
+        undo.begin_group();
+        undo.insert(&mut rga, 0, [1]).unwrap();
+        undo.begin_group();
+        undo.insert(&mut rga, 1, [2]).unwrap();
+        undo.end_group();
+        undo.remove(&mut rga, 0, 1).unwrap();
+        undo.end_group();
+        undo.insert(&mut rga, 0, [3]).unwrap();
+        assert_eq!(rga.to_vec(), vec![3, 2]);
+        undo.undo(&mut rga);
+        assert_eq!(rga.to_vec(), vec![2]);
+        undo.undo(&mut rga);
+        assert!(rga.is_empty());
+        assert!(!undo.can_undo());
+
+        fn frozen() -> u64 {
+            7
+        }
+        let mut undo = UndoManager::with_capture_timeout(500, frozen);
+        undo.insert(&mut rga, 0, [4]).unwrap();
+        undo.insert(&mut rga, 1, [5]).unwrap();
+        undo.stop_capturing();
+        undo.insert(&mut rga, 2, [6]).unwrap();
+        undo.undo(&mut rga);
+        assert_eq!(rga.to_vec(), vec![4, 5]);
+        undo.undo(&mut rga);
+        assert!(rga.is_empty());
+    }
+}
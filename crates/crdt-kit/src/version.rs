@@ -5,15 +5,28 @@
 
 use alloc::vec::Vec;
 use core::fmt;
+use core::ops::BitOr;
 
-/// Magic byte identifying crdt-kit serialized data.
+use crate::codec::{Reader, Writer};
+
+/// Magic byte identifying crdt-kit serialized data (v1 envelope).
 pub const MAGIC_BYTE: u8 = 0xCF;
 
-/// Size of the version envelope header in bytes.
+/// Magic byte identifying a v2 envelope, with flags, length and checksum.
+pub const MAGIC_BYTE_V2: u8 = 0xCE;
+
+/// Size of the v1 version envelope header in bytes.
 pub const ENVELOPE_HEADER_SIZE: usize = 3;
 
+/// Size of the fixed part of the v2 header, before the varint payload length.
+pub const ENVELOPE_V2_HEADER_SIZE: usize = 4;
+
+/// Size of the CRC32 trailing a v2 envelope.
+pub const ENVELOPE_CHECKSUM_SIZE: usize = 4;
+
 /// Identifies the type of CRDT for the version envelope.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum CrdtType {
     /// Grow-only counter.
@@ -38,6 +51,8 @@ pub enum CrdtType {
     LWWMap = 10,
     /// Add-wins map.
     AWMap = 11,
+    /// Collaborative rich text.
+    RichText = 12,
 }
 
 impl CrdtType {
@@ -55,6 +70,7 @@ impl CrdtType {
             9 => Some(Self::TextCrdt),
             10 => Some(Self::LWWMap),
             11 => Some(Self::AWMap),
+            12 => Some(Self::RichText),
             _ => None,
         }
     }
@@ -62,7 +78,7 @@ impl CrdtType {
 
 /// Trait for CRDT types that support versioned serialization.
 ///
-/// Types implementing this trait can be serialized with a 3-byte version
+/// Types implementing this trait can be serialized with a version
 /// envelope, enabling automatic migration when data schemas change.
 pub trait Versioned: Sized {
     /// Current schema version for this CRDT type's serialization format.
@@ -96,7 +112,10 @@ impl std::error::Error for VersionError {}
 // ── Versioned Envelope ─────────────────────────────────────────────
 
 /// Error parsing a version envelope.
+///
+/// New envelope formats may add variants, so matches need a wildcard arm.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum EnvelopeError {
     /// Data is too short to contain a valid envelope.
     TooShort,
@@ -104,14 +123,47 @@ pub enum EnvelopeError {
     InvalidMagic(u8),
     /// Unknown CRDT type byte.
     UnknownCrdtType(u8),
+    /// The flags byte of a v2 envelope has unknown bits set.
+    UnknownFlags(u8),
+    /// The payload length of a v2 envelope is not a valid varint.
+    InvalidLength,
+    /// The payload length of a v2 envelope disagrees with the data.
+    LengthMismatch {
+        /// Payload length declared in the header.
+        declared: usize,
+        /// Bytes actually available for the payload.
+        available: usize,
+    },
+    /// The CRC32 of a v2 envelope does not match its contents.
+    ChecksumMismatch {
+        /// Checksum stored in the envelope.
+        expected: u32,
+        /// Checksum computed over the received bytes.
+        found: u32,
+    },
 }
 
 impl fmt::Display for EnvelopeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::TooShort => write!(f, "data too short for version envelope"),
-            Self::InvalidMagic(b) => write!(f, "invalid magic byte: 0x{b:02X}, expected 0xCF"),
+            Self::InvalidMagic(b) => {
+                write!(f, "invalid magic byte: 0x{b:02X}, expected 0xCF or 0xCE")
+            }
             Self::UnknownCrdtType(b) => write!(f, "unknown CRDT type: {b}"),
+            Self::UnknownFlags(b) => write!(f, "unknown envelope flags: 0b{b:08b}"),
+            Self::InvalidLength => write!(f, "invalid envelope payload length"),
+            Self::LengthMismatch {
+                declared,
+                available,
+            } => write!(
+                f,
+                "envelope declares a {declared}-byte payload, {available} bytes available"
+            ),
+            Self::ChecksumMismatch { expected, found } => write!(
+                f,
+                "envelope checksum mismatch: stored 0x{expected:08X}, computed 0x{found:08X}"
+            ),
         }
     }
 }
@@ -119,9 +171,93 @@ impl fmt::Display for EnvelopeError {
 #[cfg(feature = "std")]
 impl std::error::Error for EnvelopeError {}
 
+/// Flags carried by a v2 envelope.
+///
+/// With no flags set the payload is a full state. Flags combine with `|`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnvelopeFlags(u8);
+
+impl EnvelopeFlags {
+    /// A full state: no flags set.
+    pub const STATE: Self = Self(0);
+    /// The payload is a delta rather than a full state.
+    pub const DELTA: Self = Self(0b0001);
+    /// The payload is compressed.
+    pub const COMPRESSED: Self = Self(0b0010);
+    /// The payload is encrypted.
+    pub const ENCRYPTED: Self = Self(0b0100);
+
+    const KNOWN: u8 = 0b0111;
+
+    /// Convert from a raw byte, rejecting unknown bits.
+    pub fn from_bits(bits: u8) -> Option<Self> {
+        (bits & !Self::KNOWN == 0).then_some(Self(bits))
+    }
+
+    /// The raw flags byte.
+    #[must_use]
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Check whether every flag in `other` is set.
+    #[must_use]
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Check whether the payload is a delta.
+    #[must_use]
+    pub fn is_delta(self) -> bool {
+        self.contains(Self::DELTA)
+    }
+}
+
+impl BitOr for EnvelopeFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// CRC-32 (IEEE 802.3), the checksum of v2 envelopes.
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0u32; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut c = i as u32;
+            let mut k = 0;
+            while k < 8 {
+                c = if c & 1 != 0 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+                k += 1;
+            }
+            table[i] = c;
+            i += 1;
+        }
+        table
+    };
+    let mut crc = !0u32;
+    for &b in data {
+        crc = TABLE[((crc ^ u32::from(b)) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
 /// A version envelope wrapping serialized CRDT data.
 ///
-/// Binary format (3 bytes overhead):
+/// [`to_bytes`](Self::to_bytes) writes the v2 format, which adds flags, the
+/// payload length and a CRC32 over everything before it:
+///
+/// ```text
+/// [MAGIC: 0xCE][VERSION: u8][CRDT_TYPE: u8][FLAGS: u8][LEN: varint][PAYLOAD: LEN bytes][CRC32: u32 big-endian]
+/// ```
+///
+/// [`from_bytes`](Self::from_bytes) also accepts the original v1 format,
+/// written by [`to_bytes_v1`](Self::to_bytes_v1) (3 bytes overhead):
+///
 /// ```text
 /// [MAGIC: 0xCF][VERSION: u8][CRDT_TYPE: u8][PAYLOAD: N bytes]
 /// ```
@@ -129,16 +265,18 @@ impl std::error::Error for EnvelopeError {}
 /// # Example
 ///
 /// ```
-/// use crdt_kit::version::{VersionedEnvelope, CrdtType};
+/// use crdt_kit::version::{CrdtType, EnvelopeFlags, VersionedEnvelope};
 ///
-/// let data = b"some serialized crdt state";
-/// let envelope = VersionedEnvelope::new(1, CrdtType::GCounter, data.to_vec());
+/// let data = b"some serialized crdt delta";
+/// let envelope = VersionedEnvelope::new(1, CrdtType::GCounter, data.to_vec())
+///     .with_flags(EnvelopeFlags::DELTA);
 ///
 /// let bytes = envelope.to_bytes();
 /// let decoded = VersionedEnvelope::from_bytes(&bytes).unwrap();
 ///
 /// assert_eq!(decoded.version, 1);
 /// assert_eq!(decoded.crdt_type, CrdtType::GCounter);
+/// assert!(decoded.flags.is_delta());
 /// assert_eq!(decoded.payload, data);
 /// ```
 #[derive(Debug, Clone, PartialEq)]
@@ -147,23 +285,50 @@ pub struct VersionedEnvelope {
     pub version: u8,
     /// Type of CRDT contained.
     pub crdt_type: CrdtType,
+    /// What the payload holds and how it is encoded. Always empty when
+    /// parsed from a v1 envelope, which cannot tell a state from a delta.
+    pub flags: EnvelopeFlags,
     /// Serialized CRDT data.
     pub payload: Vec<u8>,
 }
 
 impl VersionedEnvelope {
-    /// Create a new envelope.
+    /// Create a new envelope holding a full state.
     pub fn new(version: u8, crdt_type: CrdtType, payload: Vec<u8>) -> Self {
         Self {
             version,
             crdt_type,
+            flags: EnvelopeFlags::STATE,
             payload,
         }
     }
 
-    /// Serialize the envelope to bytes.
+    /// Replace the envelope's flags.
+    #[must_use]
+    pub fn with_flags(mut self, flags: EnvelopeFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Serialize the envelope to bytes in the v2 format.
     #[must_use]
     pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer::new();
+        w.write_u8(MAGIC_BYTE_V2);
+        w.write_u8(self.version);
+        w.write_u8(self.crdt_type as u8);
+        w.write_u8(self.flags.bits());
+        w.write_bytes(&self.payload);
+        let mut bytes = w.into_bytes();
+        let crc = crc32(&bytes);
+        bytes.extend_from_slice(&crc.to_be_bytes());
+        bytes
+    }
+
+    /// Serialize the envelope to bytes in the v1 format, for peers that
+    /// predate v2. Flags are not representable and are dropped.
+    #[must_use]
+    pub fn to_bytes_v1(&self) -> Vec<u8> {
         let mut bytes = Vec::with_capacity(ENVELOPE_HEADER_SIZE + self.payload.len());
         bytes.push(MAGIC_BYTE);
         bytes.push(self.version);
@@ -172,44 +337,74 @@ impl VersionedEnvelope {
         bytes
     }
 
-    /// Parse an envelope from bytes.
+    /// Parse a v1 or v2 envelope from bytes.
     pub fn from_bytes(data: &[u8]) -> Result<Self, EnvelopeError> {
         if data.len() < ENVELOPE_HEADER_SIZE {
             return Err(EnvelopeError::TooShort);
         }
-        if data[0] != MAGIC_BYTE {
+        if !Self::is_versioned(data) {
             return Err(EnvelopeError::InvalidMagic(data[0]));
         }
         let version = data[1];
         let crdt_type =
             CrdtType::from_byte(data[2]).ok_or(EnvelopeError::UnknownCrdtType(data[2]))?;
-        let payload = data[ENVELOPE_HEADER_SIZE..].to_vec();
+        let (flags, payload) = if data[0] == MAGIC_BYTE_V2 {
+            Self::parse_v2_body(data)?
+        } else {
+            (EnvelopeFlags::STATE, &data[ENVELOPE_HEADER_SIZE..])
+        };
         Ok(Self {
             version,
             crdt_type,
-            payload,
+            flags,
+            payload: payload.to_vec(),
         })
     }
 
+    fn parse_v2_body(data: &[u8]) -> Result<(EnvelopeFlags, &[u8]), EnvelopeError> {
+        if data.len() < ENVELOPE_V2_HEADER_SIZE + 1 + ENVELOPE_CHECKSUM_SIZE {
+            return Err(EnvelopeError::TooShort);
+        }
+        let flags = EnvelopeFlags::from_bits(data[3]).ok_or(EnvelopeError::UnknownFlags(data[3]))?;
+
+        let (body, crc) = data.split_at(data.len() - ENVELOPE_CHECKSUM_SIZE);
+        let expected = u32::from_be_bytes([crc[0], crc[1], crc[2], crc[3]]);
+        let found = crc32(body);
+
+        let mut r = Reader::new(&body[ENVELOPE_V2_HEADER_SIZE..]);
+        let declared = r.read_varint().map_err(|_| EnvelopeError::InvalidLength)?;
+        let available = r.remaining();
+        if declared != available as u64 {
+            return Err(EnvelopeError::LengthMismatch {
+                declared: usize::try_from(declared).unwrap_or(usize::MAX),
+                available,
+            });
+        }
+        if expected != found {
+            return Err(EnvelopeError::ChecksumMismatch { expected, found });
+        }
+        Ok((flags, &body[body.len() - available..]))
+    }
+
     /// Peek at the version without fully parsing the envelope.
     pub fn peek_version(data: &[u8]) -> Result<u8, EnvelopeError> {
         if data.len() < 2 {
             return Err(EnvelopeError::TooShort);
         }
-        if data[0] != MAGIC_BYTE {
+        if !Self::is_versioned(data) {
             return Err(EnvelopeError::InvalidMagic(data[0]));
         }
         Ok(data[1])
     }
 
-    /// Check if bytes look like a versioned envelope (starts with magic byte).
+    /// Check if bytes look like a versioned envelope (starts with a magic byte).
     #[must_use]
     pub fn is_versioned(data: &[u8]) -> bool {
-        data.first() == Some(&MAGIC_BYTE)
+        matches!(data.first(), Some(&MAGIC_BYTE | &MAGIC_BYTE_V2))
     }
 }
 
-// --- Versioned implementations for all 11 CRDT types ---
+// --- Versioned implementations for all 12 CRDT types ---
 
 impl Versioned for crate::GCounter {
     const CURRENT_VERSION: u8 = 1;
@@ -242,17 +437,17 @@ impl<T: Clone + Ord> Versioned for crate::MVRegister<T> {
 }
 
 impl<T: Ord + Clone> Versioned for crate::ORSet<T> {
-    const CURRENT_VERSION: u8 = 1;
+    const CURRENT_VERSION: u8 = 2;
     const CRDT_TYPE: CrdtType = CrdtType::ORSet;
 }
 
 impl<T: Clone + Ord> Versioned for crate::Rga<T> {
-    const CURRENT_VERSION: u8 = 1;
+    const CURRENT_VERSION: u8 = 2;
     const CRDT_TYPE: CrdtType = CrdtType::Rga;
 }
 
 impl Versioned for crate::TextCrdt {
-    const CURRENT_VERSION: u8 = 1;
+    const CURRENT_VERSION: u8 = 2;
     const CRDT_TYPE: CrdtType = CrdtType::TextCrdt;
 }
 
@@ -262,10 +457,15 @@ impl<K: Ord + Clone, V: Clone> Versioned for crate::LWWMap<K, V> {
 }
 
 impl<K: Ord + Clone, V: Clone + Eq> Versioned for crate::AWMap<K, V> {
-    const CURRENT_VERSION: u8 = 1;
+    const CURRENT_VERSION: u8 = 2;
     const CRDT_TYPE: CrdtType = CrdtType::AWMap;
 }
 
+impl Versioned for crate::RichText {
+    const CURRENT_VERSION: u8 = 1;
+    const CRDT_TYPE: CrdtType = CrdtType::RichText;
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -281,8 +481,71 @@ mod tests {
     #[test]
     fn envelope_header_size() {
         let envelope = VersionedEnvelope::new(1, CrdtType::GCounter, vec![]);
-        let bytes = envelope.to_bytes();
+        let bytes = envelope.to_bytes_v1();
         assert_eq!(bytes.len(), ENVELOPE_HEADER_SIZE);
+        let bytes = envelope.to_bytes();
+        assert_eq!(bytes.len(), ENVELOPE_V2_HEADER_SIZE + 1 + ENVELOPE_CHECKSUM_SIZE);
+    }
+
+    #[test]
+    fn envelope_v1_still_parses() {
+        let original = VersionedEnvelope::new(3, CrdtType::ORSet, b"test-payload".to_vec());
+        let decoded = VersionedEnvelope::from_bytes(&original.to_bytes_v1()).unwrap();
+        assert_eq!(original, decoded);
+        assert_eq!(decoded.flags, EnvelopeFlags::STATE);
+    }
+
+    #[test]
+    fn envelope_flags_roundtrip() {
+        let flags = EnvelopeFlags::DELTA | EnvelopeFlags::COMPRESSED;
+        let original = VersionedEnvelope::new(1, CrdtType::Rga, b"zz".to_vec()).with_flags(flags);
+        let decoded = VersionedEnvelope::from_bytes(&original.to_bytes()).unwrap();
+        assert_eq!(decoded.flags, flags);
+        assert!(decoded.flags.is_delta());
+        assert!(decoded.flags.contains(EnvelopeFlags::COMPRESSED));
+        assert!(!decoded.flags.contains(EnvelopeFlags::ENCRYPTED));
+    }
+
+    #[test]
+    fn envelope_detects_corruption() {
+        let mut bytes = VersionedEnvelope::new(1, CrdtType::GSet, b"payload".to_vec()).to_bytes();
+        bytes[6] ^= 0x01;
+        assert!(matches!(
+            VersionedEnvelope::from_bytes(&bytes),
+            Err(EnvelopeError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn envelope_detects_truncation() {
+        let bytes = VersionedEnvelope::new(1, CrdtType::GSet, b"payload".to_vec()).to_bytes();
+        assert_eq!(
+            VersionedEnvelope::from_bytes(&bytes[..bytes.len() - 2]),
+            Err(EnvelopeError::LengthMismatch {
+                declared: 7,
+                available: 5
+            })
+        );
+        assert_eq!(
+            VersionedEnvelope::from_bytes(&bytes[..4]),
+            Err(EnvelopeError::TooShort)
+        );
+    }
+
+    #[test]
+    fn envelope_error_unknown_flags() {
+        let mut bytes = VersionedEnvelope::new(1, CrdtType::GSet, vec![]).to_bytes();
+        bytes[3] = 0x80;
+        assert_eq!(
+            VersionedEnvelope::from_bytes(&bytes),
+            Err(EnvelopeError::UnknownFlags(0x80))
+        );
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
     }
 
     #[test]
@@ -295,6 +558,7 @@ mod tests {
     #[test]
     fn envelope_is_versioned() {
         assert!(VersionedEnvelope::is_versioned(&[MAGIC_BYTE, 1, 1]));
+        assert!(VersionedEnvelope::is_versioned(&[MAGIC_BYTE_V2, 1, 1]));
         assert!(!VersionedEnvelope::is_versioned(&[0x00, 1, 1]));
         assert!(!VersionedEnvelope::is_versioned(&[]));
     }
@@ -337,6 +601,7 @@ mod tests {
             CrdtType::TextCrdt,
             CrdtType::LWWMap,
             CrdtType::AWMap,
+            CrdtType::RichText,
         ];
         for ct in types {
             let envelope = VersionedEnvelope::new(1, ct, b"x".to_vec());
@@ -348,7 +613,7 @@ mod tests {
     #[test]
     fn crdt_type_from_byte_unknown() {
         assert_eq!(CrdtType::from_byte(0), None);
-        assert_eq!(CrdtType::from_byte(12), None);
+        assert_eq!(CrdtType::from_byte(13), None);
         assert_eq!(CrdtType::from_byte(255), None);
     }
 }
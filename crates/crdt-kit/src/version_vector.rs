@@ -0,0 +1,187 @@
+use alloc::collections::BTreeMap;
+
+use crate::codec::{CodecError, Decode, Encode, Reader, Writer};
+use crate::{Crdt, NodeId};
+
+/// A version vector: the highest operation counter observed per node.
+///
+/// Version vectors are the compact causal summary exchanged by
+/// [`DeltaSince`](crate::DeltaSince). Instead of holding a full copy of a
+/// peer's state, a replica sends its version vector and receives back only
+/// the operations it has not observed yet.
+///
+/// Merging two version vectors takes the per-node maximum, so a
+/// `VersionVector` is itself a CRDT.
+///
+/// # Example
+///
+/// ```
+/// use crdt_kit::prelude::*;
+///
+/// let mut c1 = GCounter::new(1);
+/// c1.increment();
+/// c1.increment();
+///
+/// let mut c2 = GCounter::new(2);
+/// c2.increment();
+///
+/// // c2 only sends a summary of what it has seen...
+/// let summary = c2.version_vector();
+///
+/// // ...and c1 answers with exactly what is missing.
+/// let delta = c1.delta_since(&summary);
+/// c2.apply_delta(&delta);
+/// assert_eq!(c2.value(), 3);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct VersionVector {
+    counters: BTreeMap<NodeId, u64>,
+}
+
+impl VersionVector {
+    /// Create an empty version vector.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            counters: BTreeMap::new(),
+        }
+    }
+
+    /// Get the highest counter observed for `actor` (0 if none).
+    #[must_use]
+    pub fn get(&self, actor: NodeId) -> u64 {
+        self.counters.get(&actor).copied().unwrap_or(0)
+    }
+
+    /// Record that `counter` has been observed for `actor`.
+    ///
+    /// The entry only ever grows; observing a lower counter is a no-op.
+    pub fn observe(&mut self, actor: NodeId, counter: u64) {
+        if counter == 0 {
+            return;
+        }
+        let entry = self.counters.entry(actor).or_insert(0);
+        *entry = (*entry).max(counter);
+    }
+
+    /// Check whether the operation `(actor, counter)` has been observed.
+    #[must_use]
+    pub fn contains(&self, actor: NodeId, counter: u64) -> bool {
+        counter <= self.get(actor)
+    }
+
+    /// Check whether `self` has observed everything `other` has.
+    #[must_use]
+    pub fn dominates(&self, other: &Self) -> bool {
+        other
+            .counters
+            .iter()
+            .all(|(&actor, &count)| self.get(actor) >= count)
+    }
+
+    /// Iterate over `(actor, counter)` pairs in actor order.
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, u64)> + '_ {
+        self.counters.iter().map(|(&actor, &count)| (actor, count))
+    }
+
+    /// Number of actors with a non-zero entry.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.counters.len()
+    }
+
+    /// Check whether nothing has been observed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.counters.is_empty()
+    }
+}
+
+impl Crdt for VersionVector {
+    fn merge(&mut self, other: &Self) {
+        for (actor, count) in other.iter() {
+            self.observe(actor, count);
+        }
+    }
+}
+
+impl From<BTreeMap<NodeId, u64>> for VersionVector {
+    fn from(counters: BTreeMap<NodeId, u64>) -> Self {
+        counters.into_iter().collect()
+    }
+}
+
+impl FromIterator<(NodeId, u64)> for VersionVector {
+    fn from_iter<I: IntoIterator<Item = (NodeId, u64)>>(iter: I) -> Self {
+        let mut vv = Self::new();
+        for (actor, count) in iter {
+            vv.observe(actor, count);
+        }
+        vv
+    }
+}
+
+impl Encode for VersionVector {
+    fn encode(&self, w: &mut Writer) {
+        self.counters.encode(w);
+    }
+}
+
+impl Decode for VersionVector {
+    fn decode(r: &mut Reader<'_>) -> Result<Self, CodecError> {
+        Ok(Self {
+            counters: BTreeMap::decode(r)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_is_empty() {
+        let vv = VersionVector::new();
+        assert!(vv.is_empty());
+        assert_eq!(vv.get(1), 0);
+    }
+
+    #[test]
+    fn observe_only_grows() {
+        let mut vv = VersionVector::new();
+        vv.observe(1, 5);
+        vv.observe(1, 3);
+        assert_eq!(vv.get(1), 5);
+        assert!(vv.contains(1, 5));
+        assert!(!vv.contains(1, 6));
+    }
+
+    #[test]
+    fn observe_zero_is_noop() {
+        let mut vv = VersionVector::new();
+        vv.observe(1, 0);
+        assert!(vv.is_empty());
+    }
+
+    #[test]
+    fn merge_takes_pointwise_max() {
+        let mut a: VersionVector = [(1, 3), (2, 1)].into_iter().collect();
+        let b: VersionVector = [(2, 4), (3, 2)].into_iter().collect();
+        a.merge(&b);
+        assert_eq!(a.iter().collect::<Vec<_>>(), vec![(1, 3), (2, 4), (3, 2)]);
+    }
+
+    #[test]
+    fn dominates_is_partial_order() {
+        let a: VersionVector = [(1, 3), (2, 1)].into_iter().collect();
+        let b: VersionVector = [(1, 2)].into_iter().collect();
+        let c: VersionVector = [(2, 5)].into_iter().collect();
+        assert!(a.dominates(&b));
+        assert!(!b.dominates(&a));
+        assert!(!a.dominates(&c));
+        assert!(!c.dominates(&a));
+        assert!(a.dominates(&VersionVector::new()));
+    }
+}
@@ -14,11 +14,11 @@ fn lwwmap_compact_all_after_convergence_is_safe() {
     let mut a: LWWMap<&str, i32> = LWWMap::new();
     let mut b: LWWMap<&str, i32> = LWWMap::new();
 
-    let ts1 = HybridTimestamp::new(100, 0, 1);
-    let ts2 = HybridTimestamp::new(200, 0, 1);
+    let ts1 = HybridTimestamp { physical: 100, logical: 0, node_id: 1 };
+    let ts2 = HybridTimestamp { physical: 200, logical: 0, node_id: 1 };
 
-    a.put("x", 1, ts1);
-    a.remove("x", ts2);
+    a.insert("x", 1, ts1);
+    a.remove(&"x", ts2);
 
     // Fully converge
     b.merge(&a);
@@ -44,19 +44,19 @@ fn lwwmap_compact_before_convergence_causes_resurrection() {
     let mut a: LWWMap<&str, i32> = LWWMap::new();
     let mut b: LWWMap<&str, i32> = LWWMap::new();
 
-    let ts_insert = HybridTimestamp::new(100, 0, 1);
-    let ts_remove = HybridTimestamp::new(200, 0, 1);
+    let ts_insert = HybridTimestamp { physical: 100, logical: 0, node_id: 1 };
+    let ts_remove = HybridTimestamp { physical: 200, logical: 0, node_id: 1 };
 
     // A inserts and removes "x"
-    a.put("x", 42, ts_insert);
-    a.remove("x", ts_remove);
+    a.insert("x", 42, ts_insert);
+    a.remove(&"x", ts_remove);
 
     // A compacts BEFORE syncing with B
     a.compact_tombstones_all();
     assert_eq!(a.tombstone_count(), 0);
 
     // B has a stale insert (lower timestamp)
-    b.put("x", 42, ts_insert);
+    b.insert("x", 42, ts_insert);
 
     // Now A merges B's stale insert — no tombstone to block it
     a.merge(&b);
@@ -72,11 +72,11 @@ fn lwwmap_compact_before_convergence_causes_resurrection() {
 fn lwwmap_compact_with_age_respects_latency_bound() {
     let mut map: LWWMap<&str, i32> = LWWMap::new();
 
-    let ts_insert = HybridTimestamp::new(100, 0, 1);
-    let ts_remove = HybridTimestamp::new(200, 0, 1);
+    let ts_insert = HybridTimestamp { physical: 100, logical: 0, node_id: 1 };
+    let ts_remove = HybridTimestamp { physical: 200, logical: 0, node_id: 1 };
 
-    map.put("x", 1, ts_insert);
-    map.remove("x", ts_remove);
+    map.insert("x", 1, ts_insert);
+    map.remove(&"x", ts_remove);
 
     // Now = 300ms, max_age = 50ms, latency_bound = 50ms
     // safe_cutoff = 50 + 2*50 = 150ms
@@ -139,15 +139,15 @@ fn rga_compact_demonstrates_divergence_risk() {
     let mut a: Rga<char> = Rga::new(1);
     let mut b: Rga<char> = Rga::new(2);
 
-    a.insert(0, 'A');
-    a.insert(1, 'B');
-    a.insert(2, 'C');
+    a.insert_at(0, 'A').unwrap();
+    a.insert_at(1, 'B').unwrap();
+    a.insert_at(2, 'C').unwrap();
 
     // Sync so both have [A, B, C]
     b.merge(&a);
 
     // Both delete B
-    a.delete(1);
+    a.remove(1).unwrap();
     b.merge(&a);
 
     let before_compact = a.to_vec();
@@ -0,0 +1,134 @@
+//! Gossip among many peers over in-memory transports.
+
+use std::collections::BTreeMap;
+
+use crdt_kit::codec::{BinaryCodec, Decode, Encode};
+use crdt_kit::gossip::{Gossip, GossipConfig};
+use crdt_kit::prelude::*;
+use crdt_kit::transport::MemoryTransport;
+
+struct Peer<C: DeltaSince> {
+    id: NodeId,
+    replica: C,
+    gossip: Gossip<C>,
+    links: BTreeMap<NodeId, MemoryTransport>,
+}
+
+/// A full mesh of peers, one `MemoryTransport` pair per edge.
+struct Mesh<C: DeltaSince> {
+    peers: Vec<Peer<C>>,
+}
+
+impl<C> Mesh<C>
+where
+    C: DeltaSince + Versioned,
+    C::Summary: PartialEq + Encode + Decode,
+    C::Delta: Encode + Decode,
+{
+    fn new(n: u64, config: GossipConfig, replica: impl Fn(NodeId) -> C) -> Self {
+        let mut peers: Vec<Peer<C>> = (1..=n)
+            .map(|id| Peer {
+                id,
+                replica: replica(id),
+                gossip: Gossip::new((1..=n).filter(|&p| p != id), config, id),
+                links: BTreeMap::new(),
+            })
+            .collect();
+        for i in 0..peers.len() {
+            for j in i + 1..peers.len() {
+                let (a, b) = MemoryTransport::pair();
+                let (id_i, id_j) = (peers[i].id, peers[j].id);
+                peers[i].links.insert(id_j, a);
+                peers[j].links.insert(id_i, b);
+            }
+        }
+        Self { peers }
+    }
+
+    /// Tick every peer, then pump envelopes until no link has traffic.
+    fn round(&mut self) {
+        for p in &mut self.peers {
+            p.gossip.tick(&p.replica);
+            p.gossip.flush(&BinaryCodec, &mut p.links).unwrap();
+        }
+        loop {
+            let mut received = 0;
+            for p in &mut self.peers {
+                for (&from, link) in &mut p.links {
+                    while let Some(envelope) = link.try_recv().unwrap() {
+                        p.gossip
+                            .receive(&mut p.replica, from, &envelope, &BinaryCodec)
+                            .unwrap();
+                        received += 1;
+                    }
+                }
+                p.gossip.flush(&BinaryCodec, &mut p.links).unwrap();
+            }
+            if received == 0 {
+                return;
+            }
+        }
+    }
+
+    /// Run rounds until `view` agrees on every peer; panics after `max`.
+    fn converge<V: PartialEq + std::fmt::Debug>(&mut self, max: usize, view: impl Fn(&C) -> V) {
+        for _ in 0..max {
+            let first = view(&self.peers[0].replica);
+            if self.peers.iter().all(|p| view(&p.replica) == first) {
+                return;
+            }
+            self.round();
+        }
+        panic!("not converged within {max} rounds");
+    }
+}
+
+#[test]
+fn text_converges_across_sixteen_peers() {
+    let mut mesh = Mesh::new(16, GossipConfig::default(), TextCrdt::new);
+    for (i, p) in mesh.peers.iter_mut().enumerate() {
+        p.replica.insert_str(0, &format!("<{i}>")).unwrap();
+    }
+    mesh.converge(8, |t| t.to_string());
+    assert_eq!(
+        mesh.peers[0].replica.len(),
+        (0..16).map(|i| format!("<{i}>").len()).sum::<usize>()
+    );
+}
+
+#[test]
+fn orset_edits_between_rounds_converge() {
+    let config = GossipConfig {
+        fanout: 2,
+        rumor_rounds: 2,
+        idle_fanout: 1,
+    };
+    let mut mesh = Mesh::new(12, config, ORSet::new);
+    for round in 0..10u32 {
+        let p = &mut mesh.peers[(round as usize * 5) % 12];
+        p.replica.insert(round);
+        if round % 3 == 0 {
+            p.replica.remove(&(round / 2));
+        }
+        mesh.round();
+    }
+    mesh.converge(12, |s| s.iter().copied().collect::<Vec<_>>());
+    assert!(!mesh.peers[0].replica.contains(&0));
+    assert!(mesh.peers[0].replica.contains(&9));
+}
+
+#[test]
+fn idle_mesh_goes_quiet() {
+    let config = GossipConfig {
+        idle_fanout: 0,
+        ..GossipConfig::default()
+    };
+    let mut mesh = Mesh::new(8, config, GCounter::new);
+    mesh.peers[3].replica.increment_by(4);
+    mesh.converge(8, |c| c.value());
+    for _ in 0..config.rumor_rounds {
+        mesh.round();
+    }
+    assert!(mesh.peers.iter().all(|p| !p.gossip.is_hot()));
+    assert_eq!(mesh.peers[0].replica.value(), 4);
+}
@@ -3,8 +3,12 @@
 //! Verifies the three fundamental CRDT laws (commutativity, associativity,
 //! idempotency) and delta equivalence under random operation sequences.
 
+use crdt_kit::causal::{CausalBuffer, CausalDelta};
 use crdt_kit::clock::HybridTimestamp;
+use crdt_kit::codec;
+use crdt_kit::merkle::reconcile_in_memory;
 use crdt_kit::prelude::*;
+use crdt_kit::undo::TextUndoManager;
 use proptest::prelude::*;
 
 // ─── Strategies ──────────────────────────────────────────────────────
@@ -476,6 +480,19 @@ proptest! {
         let after: Vec<_> = { let mut v: Vec<_> = compacted.iter().cloned().collect(); v.sort(); v };
         prop_assert_eq!(before, after);
     }
+
+    #[test]
+    fn orset_reconcile_equivalent_to_merge(a in orset_with_ops(), b in orset_with_ops()) {
+        let mut via_merge = a.clone();
+        via_merge.merge(&b);
+
+        let (mut a, mut b) = (a, b);
+        reconcile_in_memory(&mut a, &mut b);
+
+        let expected: Vec<_> = via_merge.iter().cloned().collect();
+        prop_assert_eq!(a.iter().cloned().collect::<Vec<_>>(), expected.clone());
+        prop_assert_eq!(b.iter().cloned().collect::<Vec<_>>(), expected);
+    }
 }
 
 // ─── Rga ─────────────────────────────────────────────────────────────
@@ -484,6 +501,8 @@ proptest! {
 enum RgaOp {
     Insert(usize, u32),
     Remove(usize),
+    Move(usize, usize),
+    Set(usize, u32),
 }
 
 fn rga_pair() -> impl Strategy<Value = (Rga<u32>, Rga<u32>)> {
@@ -492,6 +511,8 @@ fn rga_pair() -> impl Strategy<Value = (Rga<u32>, Rga<u32>)> {
             prop_oneof![
                 (0usize..10, 0u32..100).prop_map(|(i, v)| RgaOp::Insert(i, v)),
                 (0usize..10).prop_map(RgaOp::Remove),
+                (0usize..10, 0usize..10).prop_map(|(f, t)| RgaOp::Move(f, t)),
+                (0usize..10, 0u32..100).prop_map(|(i, v)| RgaOp::Set(i, v)),
             ],
             0..15,
         ),
@@ -499,6 +520,8 @@ fn rga_pair() -> impl Strategy<Value = (Rga<u32>, Rga<u32>)> {
             prop_oneof![
                 (0usize..10, 0u32..100).prop_map(|(i, v)| RgaOp::Insert(i, v)),
                 (0usize..10).prop_map(RgaOp::Remove),
+                (0usize..10, 0usize..10).prop_map(|(f, t)| RgaOp::Move(f, t)),
+                (0usize..10, 0u32..100).prop_map(|(i, v)| RgaOp::Set(i, v)),
             ],
             0..15,
         ),
@@ -516,6 +539,14 @@ fn rga_pair() -> impl Strategy<Value = (Rga<u32>, Rga<u32>)> {
                         let len = a.len();
                         if len > 0 { let _ = a.remove(idx % len); }
                     }
+                    RgaOp::Move(from, to) => {
+                        let len = a.len();
+                        if len > 0 { let _ = a.move_item(from % len, to % len); }
+                    }
+                    RgaOp::Set(idx, val) => {
+                        let len = a.len();
+                        if len > 0 { let _ = a.set(idx % len, val); }
+                    }
                 }
             }
             let mut b = Rga::new(2);
@@ -530,6 +561,14 @@ fn rga_pair() -> impl Strategy<Value = (Rga<u32>, Rga<u32>)> {
                         let len = b.len();
                         if len > 0 { let _ = b.remove(idx % len); }
                     }
+                    RgaOp::Move(from, to) => {
+                        let len = b.len();
+                        if len > 0 { let _ = b.move_item(from % len, to % len); }
+                    }
+                    RgaOp::Set(idx, val) => {
+                        let len = b.len();
+                        if len > 0 { let _ = b.set(idx % len, val); }
+                    }
                 }
             }
             (a, b)
@@ -566,6 +605,83 @@ proptest! {
 
         prop_assert_eq!(via_merge.to_vec(), via_delta.to_vec());
     }
+
+    #[test]
+    fn rga_concurrent_moves_keep_one_copy(
+        moves in prop::collection::vec((0usize..3, 0usize..8, 0usize..8), 0..20),
+    ) {
+        let mut base = Rga::new(0);
+        for v in 0..8u32 {
+            base.insert_at(v as usize, v).unwrap();
+        }
+        let mut replicas = [base.fork(1), base.fork(2), base.fork(3)];
+        for (r, from, to) in moves {
+            replicas[r].move_item(from, to).unwrap();
+        }
+
+        let mut forward = replicas[0].clone();
+        forward.merge(&replicas[1]);
+        forward.merge(&replicas[2]);
+        let mut backward = replicas[2].clone();
+        let delta = replicas[1].delta(&backward);
+        backward.apply_delta(&delta);
+        let delta = replicas[0].delta(&backward);
+        backward.apply_delta(&delta);
+
+        let mut values = forward.to_vec();
+        prop_assert_eq!(backward.to_vec(), values.clone());
+        values.sort_unstable();
+        prop_assert_eq!(values, (0..8).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn rga_patches_replay_merges_and_deltas(
+        ops in prop::collection::vec((0usize..3, 0u8..4, 0usize..16, 0usize..16), 0..30),
+    ) {
+        let mut base = Rga::new(0);
+        for v in 0..8u32 {
+            base.insert_at(v as usize, v).unwrap();
+        }
+        let mut replicas = [base.fork(1), base.fork(2), base.fork(3)];
+        for (n, (r, kind, i, j)) in ops.into_iter().enumerate() {
+            let replica = &mut replicas[r];
+            let len = replica.len();
+            match kind {
+                0 => replica.insert_at(i % (len + 1), 100 + n as u32).unwrap(),
+                1 if len > 0 => replica.remove_range(i % len, (j % 3).min(len - i % len)).unwrap(),
+                2 if len > 0 => replica.move_item(i % len, j % len).unwrap(),
+                3 if len > 0 => {
+                    replica.set(i % len, 200 + n as u32).unwrap();
+                }
+                _ => {}
+            }
+        }
+
+        let mut via_merge = replicas[0].clone();
+        let mut via_delta = replicas[0].clone();
+        for other in &replicas[1..] {
+            let mut view = via_merge.to_vec();
+            for splice in via_merge.merge_with_patches(other) {
+                view.splice(splice.index..splice.index + splice.deleted, splice.inserted);
+            }
+            prop_assert_eq!(&view, &via_merge.to_vec());
+
+            let mut view = via_delta.to_vec();
+            let delta = other.delta(&via_delta);
+            for splice in via_delta.apply_delta_with_patches(&delta) {
+                view.splice(splice.index..splice.index + splice.deleted, splice.inserted);
+            }
+            prop_assert_eq!(&view, &via_delta.to_vec());
+        }
+
+        let mut backward = replicas[2].clone();
+        backward.merge(&replicas[1]);
+        backward.merge(&replicas[0]);
+        prop_assert_eq!(backward.to_vec(), via_merge.to_vec());
+        prop_assert_eq!(via_delta.to_vec(), via_merge.to_vec());
+        prop_assert_eq!(backward.blame(), via_merge.blame());
+        prop_assert_eq!(via_merge.blame().last().map_or(0, |run| run.range.end), via_merge.len());
+    }
 }
 
 // ─── TextCrdt ────────────────────────────────────────────────────────
@@ -642,4 +758,355 @@ proptest! {
 
         prop_assert_eq!(via_merge.to_string(), via_delta.to_string());
     }
+
+    #[test]
+    fn text_concurrent_runs_stay_contiguous(
+        (base, _) in text_pair(),
+        at in 0usize..20,
+        word_a in "[a-m]{1,8}",
+        word_b in "[n-z]{1,8}",
+    ) {
+        let at = at % (base.len() + 1);
+        let mut a = base.fork(3);
+        let mut b = base.fork(4);
+        a.insert_str(at, &word_a).unwrap();
+        b.insert_str(at, &word_b).unwrap();
+
+        let mut ab = a.clone();
+        ab.merge(&b);
+        let mut ba = b.clone();
+        ba.apply_delta(&a.delta(&b));
+        prop_assert_eq!(ab.to_string(), ba.to_string());
+
+        let merged: String = ab.to_string().chars().skip(at).take(word_a.len() + word_b.len()).collect();
+        prop_assert!(
+            merged == format!("{word_a}{word_b}") || merged == format!("{word_b}{word_a}"),
+            "interleaved: {}", merged
+        );
+    }
+
+    #[test]
+    fn text_offsets_match_std_encodings(
+        words in prop::collection::vec((0usize..40, "[a\u{e9}\u{4e2d}\u{1f600}]{1,6}"), 1..12),
+        removals in prop::collection::vec((0usize..40, 0usize..4), 0..6),
+    ) {
+        let mut text = TextCrdt::new(1);
+        for (at, word) in words {
+            let at = at % (text.len() + 1);
+            text.insert_str(at, &word).unwrap();
+        }
+        for (at, count) in removals {
+            if !text.is_empty() {
+                let at = at % text.len();
+                text.remove_range(at, count.min(text.len() - at)).unwrap();
+            }
+        }
+        let s = text.to_string();
+        prop_assert_eq!(text.len_utf8(), s.len());
+        prop_assert_eq!(text.len_utf16(), s.encode_utf16().count());
+        let (mut utf8, mut utf16) = (0, 0);
+        for (i, ch) in s.chars().chain(Some('\0')).enumerate() {
+            prop_assert_eq!(text.index_to_utf8(i), Ok(utf8));
+            prop_assert_eq!(text.index_to_utf16(i), Ok(utf16));
+            prop_assert_eq!(text.utf8_to_index(utf8), Ok(i));
+            prop_assert_eq!(text.utf16_to_index(utf16), Ok(i));
+            if ch.len_utf16() == 2 {
+                prop_assert!(text.utf16_to_index(utf16 + 1).is_err());
+            }
+            utf8 += ch.len_utf8();
+            utf16 += ch.len_utf16();
+        }
+    }
+
+    #[test]
+    fn text_lines_match_split(
+        words in prop::collection::vec((0usize..40, "[ab\n]{1,6}"), 1..12),
+        removals in prop::collection::vec((0usize..40, 0usize..4), 0..6),
+    ) {
+        let mut text = TextCrdt::new(1);
+        for (at, word) in words {
+            let at = at % (text.len() + 1);
+            text.insert_str(at, &word).unwrap();
+        }
+        for (at, count) in removals {
+            if !text.is_empty() {
+                let at = at % text.len();
+                text.remove_range(at, count.min(text.len() - at)).unwrap();
+            }
+        }
+        let s = text.to_string();
+        let expected: Vec<&str> = s.split('\n').collect();
+        prop_assert_eq!(text.line_count(), expected.len());
+        prop_assert_eq!(text.lines(0..usize::MAX).collect::<Vec<_>>(), expected.clone());
+        let (mut line, mut col) = (0, 0);
+        for (i, ch) in s.chars().chain(Some('\0')).enumerate() {
+            prop_assert_eq!(text.index_to_line_col(i), Ok((line, col)));
+            prop_assert_eq!(text.line_col_to_index(line, col), Ok(i));
+            if ch == '\n' {
+                line += 1;
+                col = 0;
+            } else {
+                col += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn text_undo_all_removes_only_local_edits(
+        edits in prop::collection::vec((0u8..4, 0usize..20, "[a-z]{1,4}"), 0..25),
+    ) {
+        let mut alice = TextCrdt::new(1);
+        let mut bob = TextCrdt::new(2);
+        let mut undo = TextUndoManager::new();
+        for (kind, at, word) in edits {
+            match kind {
+                0 => {
+                    let at = at % (alice.len() + 1);
+                    undo.insert(&mut alice, at, word.as_str()).unwrap();
+                }
+                1 if !alice.is_empty() => {
+                    let at = at % alice.len();
+                    let count = (alice.len() - at).min(word.len());
+                    undo.remove(&mut alice, at, count).unwrap();
+                }
+                2 => {
+                    let at = at % (bob.len() + 1);
+                    bob.insert_str(at, &word.to_uppercase()).unwrap();
+                }
+                _ => {
+                    bob.merge(&alice);
+                    alice.merge(&bob);
+                }
+            }
+        }
+        alice.merge(&bob);
+        let before = alice.to_string();
+
+        while undo.undo(&mut alice) {}
+        let undone = alice.to_string();
+        prop_assert!(undone.chars().all(|c| c.is_ascii_uppercase()), "{}", undone);
+        while undo.redo(&mut alice) {}
+        prop_assert_eq!(alice.to_string(), before);
+
+        bob.merge(&alice);
+        prop_assert_eq!(bob.to_string(), alice.to_string());
+    }
+
+    #[test]
+    fn text_update_from_inserts_only_what_is_missing(
+        start in "[abc ]{0,30}",
+        target in "[abc ]{0,30}",
+        remote in "[XY]{1,3}",
+    ) {
+        let mut text = TextCrdt::new(1);
+        text.insert_str(0, &start).unwrap();
+        let mut other = text.fork(2);
+        let before = text.summary();
+        text.update_from(&target);
+        prop_assert_eq!(text.to_string(), target.clone());
+
+        // Only the characters outside a longest common subsequence are new.
+        let (a, b): (Vec<char>, Vec<char>) = (start.chars().collect(), target.chars().collect());
+        let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+        for i in 1..=a.len() {
+            for j in 1..=b.len() {
+                lcs[i][j] = if a[i - 1] == b[j - 1] {
+                    lcs[i - 1][j - 1] + 1
+                } else {
+                    lcs[i - 1][j].max(lcs[i][j - 1])
+                };
+            }
+        }
+        let delta = text.delta_since(&before);
+        let inserted = delta.new_elements.iter().flat_map(|run| run.ids()).count();
+        prop_assert_eq!(inserted, b.len() - lcs[a.len()][b.len()]);
+
+        other.insert_str(other.len(), &remote).unwrap();
+        text.merge(&other);
+        other.merge(&text);
+        prop_assert_eq!(text.to_string(), other.to_string());
+        prop_assert!(text.to_string().contains(&remote));
+    }
+}
+
+// ─── DeltaSince ──────────────────────────────────────────────────────
+
+// Summaries assume every replica has a unique node ID.
+fn disjoint_actors(a: &VersionVector, b: &VersionVector) -> bool {
+    a.iter().all(|(actor, _)| b.get(actor) == 0)
+}
+
+proptest! {
+    #[test]
+    fn gcounter_delta_since_equivalent_to_merge(a in gcounter_with_ops(), b in gcounter_with_ops()) {
+        let mut via_merge = b.clone();
+        via_merge.merge(&a);
+
+        let mut via_delta = b.clone();
+        via_delta.apply_delta(&a.delta_since(&b.summary()));
+
+        prop_assert_eq!(via_merge, via_delta);
+    }
+
+    #[test]
+    fn pncounter_delta_since_equivalent_to_merge(a in pncounter_with_ops(), b in pncounter_with_ops()) {
+        let (sa, sb) = (a.summary(), b.summary());
+        prop_assume!(disjoint_actors(&sa.increments, &sb.increments));
+        prop_assume!(disjoint_actors(&sa.decrements, &sb.decrements));
+
+        let mut via_merge = b.clone();
+        via_merge.merge(&a);
+
+        let mut via_delta = b.clone();
+        via_delta.apply_delta(&a.delta_since(&sb));
+
+        prop_assert_eq!(via_merge.value(), via_delta.value());
+    }
+
+    #[test]
+    fn orset_delta_since_equivalent_to_merge(a in orset_with_ops(), b in orset_with_ops()) {
+        prop_assume!(disjoint_actors(&a.version_vector(), &b.version_vector()));
+
+        let mut via_merge = b.clone();
+        via_merge.merge(&a);
+
+        let mut via_delta = b.clone();
+        via_delta.apply_delta(&a.delta_since(&b.summary()));
+
+        let mut merge_items: Vec<_> = via_merge.iter().cloned().collect();
+        merge_items.sort();
+        let mut delta_items: Vec<_> = via_delta.iter().cloned().collect();
+        delta_items.sort();
+        prop_assert_eq!(merge_items, delta_items);
+    }
+
+    #[test]
+    fn rga_delta_since_after_partial_sync((a, b) in rga_pair(), extra in 0u32..100) {
+        let mut b = b;
+        b.merge(&a);
+        let mut a = a;
+        let len = a.len();
+        a.insert_at(len / 2, extra).unwrap();
+
+        let mut via_merge = b.clone();
+        via_merge.merge(&a);
+
+        let mut via_delta = b.clone();
+        via_delta.apply_delta(&a.delta_since(&b.summary()));
+
+        prop_assert_eq!(via_merge.to_vec(), via_delta.to_vec());
+    }
+
+    #[test]
+    fn text_delta_since_equivalent_to_merge((a, b) in text_pair()) {
+        let mut via_merge = b.clone();
+        via_merge.merge(&a);
+
+        let mut via_delta = b.clone();
+        via_delta.apply_delta(&a.delta_since(&b.summary()));
+
+        prop_assert_eq!(via_merge.to_string(), via_delta.to_string());
+    }
+}
+
+// ─── CausalBuffer ────────────────────────────────────────────────────
+
+/// Apply a random edit to `text` and stamp the resulting delta.
+fn stamped_edit(
+    text: &mut TextCrdt,
+    out: &mut CausalBuffer<TextCrdt>,
+    pos: usize,
+    ch: char,
+    delete: bool,
+) -> CausalDelta<TextDelta> {
+    let before = text.version_vector();
+    if delete && !text.is_empty() {
+        text.remove(pos % text.len()).unwrap();
+    } else {
+        text.insert(pos % (text.len() + 1), ch).unwrap();
+    }
+    out.stamp(text.actor(), text.delta_since(&before))
+}
+
+proptest! {
+    #[test]
+    fn causal_buffer_delivers_any_order(
+        ops in prop::collection::vec((any::<bool>(), 0usize..50, prop::char::range('a', 'z'), any::<bool>()), 1..30),
+        keys in prop::collection::vec(any::<u32>(), 60),
+    ) {
+        let mut a = TextCrdt::new(1);
+        let mut b = TextCrdt::new(2);
+        let mut out_a = CausalBuffer::new();
+        let mut out_b = CausalBuffer::new();
+        let mut sent: Vec<CausalDelta<TextDelta>> = Vec::new();
+        let mut b_seen = 0;
+
+        for (on_b, pos, ch, delete) in ops {
+            if on_b {
+                // B catches up on A's deltas first, so its edits depend on them.
+                for d in sent.iter().skip(b_seen).filter(|d| d.origin == 1) {
+                    out_b.receive(&mut b, d.clone());
+                }
+                b_seen = sent.len();
+                sent.push(stamped_edit(&mut b, &mut out_b, pos, ch, delete));
+            } else {
+                sent.push(stamped_edit(&mut a, &mut out_a, pos, ch, delete));
+            }
+        }
+
+        // Deliver everything twice, in a random order.
+        let mut shuffled: Vec<_> = sent.iter().chain(sent.iter()).cloned().enumerate().collect();
+        shuffled.sort_by_key(|(i, _)| keys[*i]);
+
+        let mut c = TextCrdt::new(3);
+        let mut inbox = CausalBuffer::new();
+        let mut applied = 0;
+        for (_, d) in shuffled {
+            applied += inbox.receive(&mut c, d);
+        }
+
+        let mut expected = a.clone();
+        expected.merge(&b);
+        prop_assert_eq!(applied, sent.len());
+        prop_assert!(inbox.is_empty());
+        prop_assert_eq!(c.to_string(), expected.to_string());
+    }
+}
+
+// ─── Binary codec ────────────────────────────────────────────────────
+
+proptest! {
+    #[test]
+    fn codec_roundtrips_orset(a in orset_with_ops(), b in orset_with_ops()) {
+        let mut merged = a.clone();
+        merged.merge(&b);
+        let decoded: ORSet<u32> = codec::decode(&codec::encode(&merged)).unwrap();
+        prop_assert_eq!(&decoded, &merged);
+
+        let delta = a.delta(&b);
+        let bytes = codec::encode_delta::<ORSet<u32>>(&delta);
+        prop_assert_eq!(codec::decode_delta::<ORSet<u32>>(&bytes).unwrap(), delta);
+    }
+
+    #[test]
+    fn codec_roundtrips_rga((a, b) in rga_pair()) {
+        let mut merged = a.clone();
+        merged.merge(&b);
+        let decoded: Rga<u32> = codec::decode(&codec::encode(&merged)).unwrap();
+        prop_assert_eq!(decoded.to_vec(), merged.to_vec());
+        prop_assert_eq!(&decoded, &merged);
+
+        let mut via_delta = b.clone();
+        let bytes = codec::encode_delta::<Rga<u32>>(&a.delta_since(&b.summary()));
+        via_delta.apply_delta(&codec::decode_delta::<Rga<u32>>(&bytes).unwrap());
+        prop_assert_eq!(via_delta.to_vec(), merged.to_vec());
+    }
+
+    #[test]
+    fn codec_rejects_truncation(a in orset_with_ops()) {
+        let bytes = codec::encode(&a);
+        for len in 0..bytes.len() {
+            prop_assert!(codec::decode::<ORSet<u32>>(&bytes[..len]).is_err());
+        }
+    }
 }
@@ -0,0 +1,240 @@
+//! Multi-replica convergence under a faulty simulated network.
+//!
+//! Each test replays a batch of seeds. To reproduce a failure, rerun with
+//! the seed from the panic message:
+//!
+//! ```text
+//! CRDT_SIM_SEED=1234 cargo test --test simulation
+//! ```
+
+use crdt_kit::clock::HybridTimestamp;
+use crdt_kit::prelude::*;
+use crdt_kit::sim::{SimConfig, Simulation};
+
+const REPLICAS: u64 = 5;
+const STEPS: usize = 60;
+const MAX_ROUNDS: usize = 5;
+
+fn seeds() -> Vec<u64> {
+    match std::env::var("CRDT_SIM_SEED") {
+        Ok(seed) => vec![seed.parse().expect("CRDT_SIM_SEED must be a u64")],
+        Err(_) => (0..16).collect(),
+    }
+}
+
+/// Drive a simulation: one random local op per step, frequent gossip, and
+/// a rolling partition. Ends with quiescence.
+fn chaos<C>(sim: &mut Simulation<C>, mut op: impl FnMut(&mut Simulation<C>, usize, u64))
+where
+    C: DeltaCrdt + Clone + PartialEq,
+    C::Delta: Clone,
+{
+    let n = sim.len() as u64;
+    for step in 0..STEPS {
+        let replica = sim.random_below(n) as usize;
+        let choice = sim.random_below(1000);
+        op(sim, replica, choice);
+
+        match step % 20 {
+            0 => sim.partition(&[&[0, 1], &[2]]),
+            10 => sim.heal(),
+            _ => {}
+        }
+        if step % 3 == 0 {
+            sim.gossip();
+        }
+        sim.step();
+    }
+    let seed = sim.seed();
+    sim.quiesce(MAX_ROUNDS)
+        .unwrap_or_else(|e| panic!("seed {seed}: {e}"));
+}
+
+#[test]
+fn gcounter_converges() {
+    for seed in seeds() {
+        let mut sim = Simulation::new(
+            (1..=REPLICAS).map(GCounter::new).collect(),
+            SimConfig::chaotic(),
+            seed,
+        );
+        let mut total = 0;
+        chaos(&mut sim, |sim, i, n| {
+            sim.update(i, |c| c.increment_by(n % 10));
+            total += n % 10;
+        });
+        sim.assert_converged(|c| c.value());
+        assert_eq!(sim.replica(0).value(), total, "seed {seed}");
+    }
+}
+
+#[test]
+fn pncounter_converges() {
+    for seed in seeds() {
+        let mut sim = Simulation::new(
+            (1..=REPLICAS).map(PNCounter::new).collect(),
+            SimConfig::chaotic(),
+            seed,
+        );
+        let mut total = 0i64;
+        chaos(&mut sim, |sim, i, n| {
+            if n % 2 == 0 {
+                sim.update(i, |c| c.increment());
+                total += 1;
+            } else {
+                sim.update(i, |c| c.decrement());
+                total -= 1;
+            }
+        });
+        sim.assert_converged(|c| c.value());
+        assert_eq!(sim.replica(0).value(), total, "seed {seed}");
+    }
+}
+
+#[test]
+fn twop_set_converges() {
+    for seed in seeds() {
+        let mut sim = Simulation::new(
+            (0..REPLICAS).map(|_| TwoPSet::new()).collect(),
+            SimConfig::chaotic(),
+            seed,
+        );
+        chaos(&mut sim, |sim, i, n| {
+            let x = n % 8;
+            if n % 4 == 0 {
+                sim.update(i, |s| s.remove(&x));
+            } else {
+                sim.update(i, |s| s.insert(x));
+            }
+        });
+        sim.assert_converged(|s| s.iter().copied().collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn orset_converges() {
+    for seed in seeds() {
+        let mut sim = Simulation::new(
+            (1..=REPLICAS).map(ORSet::new).collect(),
+            SimConfig::chaotic(),
+            seed,
+        );
+        chaos(&mut sim, |sim, i, n| {
+            let x = n % 8;
+            if n % 3 == 0 {
+                sim.update(i, |s| s.remove(&x));
+            } else {
+                sim.update(i, |s| s.insert(x));
+            }
+        });
+        sim.assert_converged(|s| s.iter().copied().collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn mv_register_converges() {
+    for seed in seeds() {
+        let mut sim = Simulation::new(
+            (1..=REPLICAS).map(MVRegister::new).collect(),
+            SimConfig::chaotic(),
+            seed,
+        );
+        chaos(&mut sim, |sim, i, n| sim.update(i, |r| r.set(n)));
+        sim.assert_converged(|r| r.values().into_iter().copied().collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn lww_map_converges() {
+    for seed in seeds() {
+        let mut sim = Simulation::new(
+            (0..REPLICAS).map(|_| LWWMap::new()).collect(),
+            SimConfig::chaotic(),
+            seed,
+        );
+        let mut physical = 0;
+        chaos(&mut sim, |sim, i, n| {
+            physical += 1;
+            let ts = HybridTimestamp {
+                physical,
+                logical: 0,
+                node_id: i as u16,
+            };
+            let key = n % 6;
+            if n % 4 == 0 {
+                sim.update(i, |m| m.remove(&key, ts));
+            } else {
+                sim.update(i, |m| m.insert(key, n, ts));
+            }
+        });
+        sim.assert_converged(|m| m.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn aw_map_converges() {
+    for seed in seeds() {
+        let mut sim = Simulation::new(
+            (1..=REPLICAS).map(AWMap::new).collect(),
+            SimConfig::chaotic(),
+            seed,
+        );
+        chaos(&mut sim, |sim, i, n| {
+            let key = n % 6;
+            if n % 3 == 0 {
+                sim.update(i, |m| m.remove(&key));
+            } else {
+                sim.update(i, |m| m.insert(key, n));
+            }
+        });
+        sim.assert_converged(|m| m.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn text_converges() {
+    for seed in seeds() {
+        let mut sim = Simulation::new(
+            (1..=REPLICAS).map(TextCrdt::new).collect(),
+            SimConfig::chaotic(),
+            seed,
+        );
+        chaos(&mut sim, |sim, i, n| {
+            let len = sim.replica(i).len();
+            if len > 0 && n % 3 == 0 {
+                let at = (n as usize / 3) % len;
+                sim.update(i, |t| t.remove(at)).unwrap();
+            } else {
+                let at = n as usize % (len + 1);
+                let ch = char::from(b'a' + (n % 26) as u8);
+                sim.update(i, |t| t.insert(at, ch)).unwrap();
+            }
+        });
+        sim.assert_converged(|t| t.to_string());
+    }
+}
+
+#[test]
+fn rga_converges() {
+    for seed in seeds() {
+        let mut sim = Simulation::new(
+            (1..=REPLICAS).map(Rga::new).collect(),
+            SimConfig::chaotic(),
+            seed,
+        );
+        chaos(&mut sim, |sim, i, n| {
+            let len = sim.replica(i).len();
+            let at = n as usize / 5 % len.max(1);
+            match n % 5 {
+                0 if len > 0 => {
+                    sim.update(i, |r| r.remove(at)).unwrap();
+                }
+                _ => {
+                    let at = n as usize % (len + 1);
+                    sim.update(i, |r| r.insert_at(at, n)).unwrap();
+                }
+            }
+        });
+        sim.assert_converged(|r| r.to_vec());
+    }
+}
@@ -0,0 +1,179 @@
+//! Replicate CRDTs between two peers over real transports.
+
+use std::net::TcpListener;
+use std::thread;
+
+use crdt_kit::codec::{BinaryCodec, Decode, Encode};
+use crdt_kit::prelude::*;
+use crdt_kit::sync::{SyncCodec, SyncMessage};
+use crdt_kit::transport::{
+    recv_delta, sync_over, MemoryTransport, TcpTransport, Transport, TransportError,
+};
+use crdt_kit::version::{Versioned, VersionedEnvelope};
+
+fn tcp_pair() -> (TcpTransport, TcpTransport) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let client = thread::spawn(move || TcpTransport::connect(addr).unwrap());
+    let (server, _) = listener.accept().unwrap();
+    (TcpTransport::from(server), client.join().unwrap())
+}
+
+/// Sync `a` and `b` over the given transports, each side on its own thread.
+fn replicate<C, T>(mut a: C, mut b: C, mut ta: T, mut tb: T) -> (C, C)
+where
+    C: DeltaSince + Versioned + Send + 'static,
+    C::Summary: Encode + Decode,
+    C::Delta: Encode + Decode,
+    T: Transport + Send + 'static,
+{
+    let peer = thread::spawn(move || {
+        sync_over(&mut tb, &BinaryCodec, &mut b).unwrap();
+        b
+    });
+    sync_over(&mut ta, &BinaryCodec, &mut a).unwrap();
+    (a, peer.join().unwrap())
+}
+
+#[test]
+fn gcounter_over_tcp() {
+    let mut a = GCounter::new(1);
+    a.increment_by(10);
+    let mut b = GCounter::new(2);
+    b.increment_by(5);
+
+    let (ta, tb) = tcp_pair();
+    let (a, b) = replicate(a, b, ta, tb);
+    assert_eq!(a.value(), 15);
+    assert_eq!(b.value(), 15);
+}
+
+#[test]
+fn orset_over_tcp() {
+    let mut a = ORSet::new(1);
+    a.insert("apple".to_string());
+    a.insert("banana".to_string());
+    let mut b = ORSet::new(2);
+    b.merge(&a);
+    a.remove(&"banana".to_string());
+    b.insert("cherry".to_string());
+
+    let (ta, tb) = tcp_pair();
+    let (a, b) = replicate(a, b, ta, tb);
+    let items: Vec<_> = a.iter().cloned().collect();
+    assert_eq!(items, vec!["apple".to_string(), "cherry".to_string()]);
+    assert_eq!(items, b.iter().cloned().collect::<Vec<_>>());
+}
+
+#[test]
+fn text_over_tcp() {
+    let mut a = TextCrdt::new(1);
+    a.insert_str(0, "hello").unwrap();
+    let mut b = a.fork(2);
+    a.insert_str(5, " world").unwrap();
+    b.remove(0).unwrap();
+    b.insert(0, 'H').unwrap();
+
+    let (ta, tb) = tcp_pair();
+    let (a, b) = replicate(a, b, ta, tb);
+    assert_eq!(a.to_string(), "Hello world");
+    assert_eq!(b.to_string(), "Hello world");
+}
+
+#[test]
+fn text_over_memory() {
+    let mut a = TextCrdt::new(1);
+    a.insert_str(0, "abc").unwrap();
+    let mut b = TextCrdt::new(2);
+    b.insert_str(0, "xyz").unwrap();
+
+    let (ta, tb) = MemoryTransport::pair();
+    let (a, b) = replicate(a, b, ta, tb);
+    assert_eq!(a.to_string(), b.to_string());
+    assert_eq!(a.len(), 6);
+}
+
+#[test]
+fn mismatched_types_are_rejected() {
+    let (mut ta, mut tb) = MemoryTransport::pair();
+    let peer = thread::spawn(move || {
+        let mut b = ORSet::<String>::new(2);
+        sync_over(&mut tb, &BinaryCodec, &mut b)
+    });
+    let mut a = GCounter::new(1);
+    assert!(sync_over(&mut ta, &BinaryCodec, &mut a).is_err());
+    drop(ta);
+    assert!(peer.join().unwrap().is_err());
+}
+
+#[test]
+fn recv_delta_rejects_other_messages() {
+    let (mut ta, mut tb) = MemoryTransport::pair();
+    let done = SyncCodec::<GCounter>::encode(&BinaryCodec, &SyncMessage::Done).unwrap();
+    ta.send(&VersionedEnvelope::new(
+        GCounter::CURRENT_VERSION,
+        GCounter::CRDT_TYPE,
+        done,
+    ))
+    .unwrap();
+    assert!(matches!(
+        recv_delta::<GCounter, _, _>(&mut tb, &BinaryCodec),
+        Err(TransportError::ExpectedDelta)
+    ));
+}
+
+#[cfg(feature = "websocket")]
+mod websocket {
+    use super::*;
+    use std::time::Duration;
+
+    use crdt_kit::transport::send_delta;
+    use crdt_kit::transport::websocket::{WebSocketHub, WebSocketTransport};
+
+    #[test]
+    fn hub_fans_out_deltas() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        let mut server_state = TextCrdt::new(0);
+        server_state.insert_str(0, "doc").unwrap();
+
+        let server = thread::spawn(move || {
+            let mut hub = WebSocketHub::new(server_state);
+            for _ in 0..2 {
+                let (stream, _) = listener.accept().unwrap();
+                hub.accept(stream, &BinaryCodec).unwrap();
+            }
+            let mut relayed = 0;
+            while relayed == 0 {
+                relayed += hub.poll(&BinaryCodec, Duration::from_millis(10)).unwrap();
+            }
+            hub.replica().to_string()
+        });
+
+        // The writer joins, catches up, and pushes one edit.
+        let writer_url = url.clone();
+        let writer = thread::spawn(move || {
+            let mut t = WebSocketTransport::connect(&writer_url).unwrap();
+            let mut doc = TextCrdt::new(1);
+            sync_over(&mut t, &BinaryCodec, &mut doc).unwrap();
+            assert_eq!(doc.to_string(), "doc");
+
+            let before = doc.version_vector();
+            doc.insert_str(3, "!").unwrap();
+            send_delta::<TextCrdt, _, _>(&mut t, &BinaryCodec, doc.delta_since(&before)).unwrap();
+            doc.to_string()
+        });
+
+        // The reader joins and receives the edit through the hub.
+        let mut t = WebSocketTransport::connect(&url).unwrap();
+        let mut doc = TextCrdt::new(2);
+        sync_over(&mut t, &BinaryCodec, &mut doc).unwrap();
+        let delta = recv_delta::<TextCrdt, _, _>(&mut t, &BinaryCodec).unwrap();
+        doc.apply_delta(&delta);
+
+        assert_eq!(writer.join().unwrap(), "doc!");
+        assert_eq!(server.join().unwrap(), "doc!");
+        assert_eq!(doc.to_string(), "doc!");
+    }
+}